@@ -0,0 +1,512 @@
+use pmd_flow::{
+    BulkEdit, DotOptions, EditError, FlowData, FlowDataError, FlowDataOutput, FlowDiff, FlowGraph,
+    FlowMerge, FlowPatch, FlowQuery, FlowTree, NodePath, PatchError, ProjectError, QueryError,
+    TextError,
+};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Write};
+use std::path::Path;
+use std::process::exit;
+
+const USAGE: &str = "usage: flowtool <command> <arguments>
+
+commands:
+    decode <input.bin> [output.json]     decode a flow file to JSON (or to the text format if
+                                         the output end with .flow)
+    encode <input.json> <output.bin>     encode a JSON file back to a flow file
+    raw-dump <input.bin> [output.json]   dump the raw dictionaries and vectors of a flow file
+    validate <input>                     check the links and the reachability of the nodes
+    stats <input>                        print statistics about the nodes
+    graph [--layout] <input> [output.dot]
+                                         draw the flow as a Graphviz DOT graph
+    diff [--json] <old> <new>            print the nodes added, removed and changed
+    make-patch <old> <new> [patch.json]  create a patch that turn old into new
+    apply-patch <input> <output> <patch.json>...
+                                         apply patches in order, and write the result
+    query <input> <query>                print the nodes matching the query, like
+                                         'kind = Dungeon and dungeon ^= d0'
+    rename <input> <old> <new> <output>  rename the idname old, and update the links to it
+    bulk <input> <query> <edit> (--dry-run | --output <output>)
+                                         print the changes the edit would do on the nodes
+                                         matching the query, and apply them with --output.
+                                         <edit> is one of:
+                                           set <field> <value>  (value is JSON or a string)
+                                           replace-entry <regex> <replacement>
+                                           move <group/subgroup>  (an empty group is the top)
+                                           renumber <start> [step]
+    split <input> <directory>            write the flow as a project directory, with a file per
+                                         node and a directory per group
+    merge [--markers] <base> <ours> <theirs> <output>
+                                         merge the changes of ours and theirs, keeping ours
+                                         on conflict (or writing conflict markers in the
+                                         JSON output with --markers)
+
+<input> can be either a flow file, a JSON file (if its name end with .json), a flow text file
+(if its name end with .flow) or a project directory written by split (so encode <directory>
+<output.bin> reassemble a project). The output is written to the standard output when it isn't
+given, and <output> is written as JSON or as text if its name end with .json or .flow.
+
+exit code: 0 on success, 1 if validate found problems, diff found differences or merge found
+conflicts, 2 on bad usage, 3 on other errors";
+
+enum CliError {
+    Usage(String),
+    Io(String, io::Error),
+    Flow(String, FlowDataError),
+    Json(String, serde_json::Error),
+    Patch(String, PatchError),
+    Edit(String, EditError),
+    Query(QueryError),
+    Project(ProjectError),
+    Text(String, TextError),
+}
+
+impl CliError {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Self::Usage(_) => 2,
+            _ => 3,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Usage(message) => write!(f, "{}\n\n{}", message, USAGE),
+            Self::Io(path, err) => write!(f, "{}: {}", path, err),
+            Self::Flow(path, err) => write!(f, "{}: {}", path, err),
+            Self::Json(path, err) => write!(f, "{}: {}", path, err),
+            Self::Patch(path, err) => write!(f, "{}: {}", path, err),
+            Self::Edit(path, err) => write!(f, "{}: {}", path, err),
+            Self::Query(err) => write!(f, "invalid query: {}", err),
+            Self::Project(err) => write!(f, "{}", err),
+            Self::Text(path, err) => write!(f, "{}: {}", path, err),
+        }
+    }
+}
+
+fn is_json(path: &str) -> bool {
+    Path::new(path).extension() == Some(OsStr::new("json"))
+}
+
+fn is_text(path: &str) -> bool {
+    Path::new(path).extension() == Some(OsStr::new("flow"))
+}
+
+fn read_flowdata(path: &str) -> Result<FlowData, CliError> {
+    let file = File::open(path).map_err(|err| CliError::Io(path.to_string(), err))?;
+    FlowData::new(&mut BufReader::new(file)).map_err(|err| CliError::Flow(path.to_string(), err))
+}
+
+fn read_output(path: &str) -> Result<FlowDataOutput, CliError> {
+    if Path::new(path).is_dir() {
+        FlowDataOutput::read_project(Path::new(path)).map_err(CliError::Project)
+    } else if is_text(path) {
+        let text =
+            std::fs::read_to_string(path).map_err(|err| CliError::Io(path.to_string(), err))?;
+        FlowDataOutput::from_text(&text).map_err(|err| CliError::Text(path.to_string(), err))
+    } else if is_json(path) {
+        let file = File::open(path).map_err(|err| CliError::Io(path.to_string(), err))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|err| CliError::Json(path.to_string(), err))
+    } else {
+        FlowDataOutput::new(read_flowdata(path)?)
+            .map_err(|err| CliError::Flow(path.to_string(), err))
+    }
+}
+
+fn write_output(path: Option<&str>, content: &[u8]) -> Result<(), CliError> {
+    match path {
+        Some(path) => File::create(path)
+            .and_then(|mut file| file.write_all(content))
+            .map_err(|err| CliError::Io(path.to_string(), err)),
+        None => io::stdout()
+            .write_all(content)
+            .map_err(|err| CliError::Io("<stdout>".to_string(), err)),
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<Vec<u8>, CliError> {
+    let mut result =
+        serde_json::to_vec_pretty(value).map_err(|err| CliError::Json("<output>".into(), err))?;
+    result.push(b'\n');
+    Ok(result)
+}
+
+/// describe a node as its group names, debugname and path
+fn describe(output: &FlowDataOutput, path: &NodePath) -> String {
+    let mut result = String::new();
+    for group in output.group_names(path) {
+        result.push_str(group);
+        result.push('/');
+    }
+    if let Some(debugname) = output.get_node(path).and_then(|node| node.debugname()) {
+        result.push_str(debugname);
+    }
+    format!("{} ({})", result, path)
+}
+
+fn decode(input: &str, output: Option<&str>) -> Result<i32, CliError> {
+    let flow = read_output(input)?;
+    match output {
+        Some(output) if is_text(output) => write_flow(&flow, output)?,
+        output => write_output(output, &to_json(&flow)?)?,
+    };
+    Ok(0)
+}
+
+/// write `flow` to `output` as a flow file
+fn write_flowdata(flow: &FlowDataOutput, output: &str) -> Result<(), CliError> {
+    let flowdata = flow
+        .generate_flowdata()
+        .map_err(|err| CliError::Flow(output.to_string(), err))?;
+    let mut content = Cursor::new(Vec::new());
+    flowdata
+        .write(&mut content)
+        .map_err(|err| CliError::Flow(output.to_string(), err))?;
+    write_output(Some(output), &content.into_inner())
+}
+
+/// write `flow` to `output`, as JSON if its name end with .json, as a flow file otherwise
+fn write_flow(flow: &FlowDataOutput, output: &str) -> Result<(), CliError> {
+    if is_text(output) {
+        let text = flow
+            .to_text()
+            .map_err(|err| CliError::Json(output.to_string(), err))?;
+        write_output(Some(output), text.as_bytes())
+    } else if is_json(output) {
+        write_output(Some(output), &to_json(flow)?)
+    } else {
+        write_flowdata(flow, output)
+    }
+}
+
+fn encode(input: &str, output: &str) -> Result<i32, CliError> {
+    write_flowdata(&read_output(input)?, output)?;
+    Ok(0)
+}
+
+fn raw_dump(input: &str, output: Option<&str>) -> Result<i32, CliError> {
+    let flowdata = read_flowdata(input)?;
+    let tree =
+        FlowTree::from_root(&flowdata).map_err(|err| CliError::Flow(input.to_string(), err))?;
+    write_output(output, &to_json(&tree)?)?;
+    Ok(0)
+}
+
+fn validate(input: &str) -> Result<i32, CliError> {
+    let flow = read_output(input)?;
+    let mut problems = 0;
+
+    if let Err(err) = flow.generate_flowdata() {
+        println!("error: the flow can't be encoded: {}", err);
+        problems += 1;
+    };
+
+    for finding in flow.lint() {
+        println!("lint: {}", finding);
+        problems += 1;
+    }
+
+    let report = flow.reachability();
+    if report.entry_points.is_empty() {
+        println!("reachability: no node declare the $START idname");
+        problems += 1;
+    };
+    for path in &report.unreachable_nodes {
+        println!("unreachable node: {}", describe(&flow, path));
+        problems += 1;
+    }
+    for path in &report.unreachable_groups {
+        println!("unreachable group: {}", describe(&flow, path));
+        problems += 1;
+    }
+    for path in &report.dead_ends {
+        println!("dead end: {}", describe(&flow, path));
+        problems += 1;
+    }
+    for cycle in &report.exitless_cycles {
+        let nodes: Vec<String> = cycle.iter().map(|path| describe(&flow, path)).collect();
+        println!("cycle without exit: {}", nodes.join(" -> "));
+        problems += 1;
+    }
+
+    if problems == 0 {
+        println!("no problem found");
+        Ok(0)
+    } else {
+        println!("{} problem(s) found", problems);
+        Ok(1)
+    }
+}
+
+fn stats(input: &str) -> Result<i32, CliError> {
+    let flow = read_output(input)?;
+    let graph = FlowGraph::new(&flow);
+    let mut kinds: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut scripts = 0;
+    for (_, node) in flow.nodes() {
+        *kinds.entry(node.key()).or_insert(0) += 1;
+        scripts += node.entry().map_or(0, <[String]>::len);
+    }
+    println!("nodes: {}", flow.nodes().len());
+    for (kind, count) in kinds {
+        println!("    {}: {}", kind, count);
+    }
+    println!("links: {}", graph.edges().len());
+    println!("dangling links: {}", graph.dangling_links().len());
+    println!("duplicate idnames: {}", graph.duplicate_idnames().len());
+    println!("entry points: {}", graph.entry_points().len());
+    println!("scripts: {}", scripts);
+    Ok(0)
+}
+
+fn graph(args: &[&str]) -> Result<i32, CliError> {
+    let mut options = DotOptions::default();
+    let mut paths = Vec::new();
+    for arg in args {
+        match *arg {
+            "--layout" => options.use_layout = true,
+            _ => paths.push(*arg),
+        }
+    }
+    let (input, output) = match paths.as_slice() {
+        [input] => (*input, None),
+        [input, output] => (*input, Some(*output)),
+        _ => {
+            return Err(CliError::Usage(
+                "graph take one input and an optional output".into(),
+            ))
+        }
+    };
+    let flow = read_output(input)?;
+    write_output(output, flow.to_dot(&options).as_bytes())?;
+    Ok(0)
+}
+
+fn diff(args: &[&str]) -> Result<i32, CliError> {
+    let (json, old, new) = match args {
+        ["--json", old, new] => (true, *old, *new),
+        [old, new] => (false, *old, *new),
+        _ => return Err(CliError::Usage("diff take two inputs".into())),
+    };
+    let diff = FlowDiff::new(&read_output(old)?, &read_output(new)?);
+    if json {
+        write_output(None, &to_json(&diff)?)?;
+    } else {
+        write_output(None, diff.to_string().as_bytes())?;
+    };
+    Ok(if diff.is_empty() { 0 } else { 1 })
+}
+
+fn make_patch(old: &str, new: &str, output: Option<&str>) -> Result<i32, CliError> {
+    let patch = FlowPatch::create(&read_output(old)?, &read_output(new)?);
+    write_output(output, &to_json(&patch)?)?;
+    Ok(0)
+}
+
+fn apply_patch(args: &[&str]) -> Result<i32, CliError> {
+    let (input, output, patches) = match args {
+        [input, output, patches @ ..] if !patches.is_empty() => (*input, *output, patches),
+        _ => {
+            return Err(CliError::Usage(
+                "apply-patch take an input, an output and at least one patch".into(),
+            ))
+        }
+    };
+    let mut flow = read_output(input)?;
+    for path in patches {
+        let file = File::open(path).map_err(|err| CliError::Io(path.to_string(), err))?;
+        let patch: FlowPatch = serde_json::from_reader(BufReader::new(file))
+            .map_err(|err| CliError::Json(path.to_string(), err))?;
+        patch
+            .apply(&mut flow)
+            .map_err(|err| CliError::Patch(path.to_string(), err))?;
+    }
+    write_flow(&flow, output)?;
+    Ok(0)
+}
+
+fn query(input: &str, query: &str) -> Result<i32, CliError> {
+    let query: FlowQuery = query.parse().map_err(CliError::Query)?;
+    let flow = read_output(input)?;
+    for path in flow.query(&query) {
+        println!("{}", describe(&flow, &path));
+    }
+    Ok(0)
+}
+
+fn rename(input: &str, old: &str, new: &str, output: &str) -> Result<i32, CliError> {
+    let mut flow = read_output(input)?;
+    let updated = flow
+        .rename_idname(old, new)
+        .map_err(|err| CliError::Edit(input.to_string(), err))?;
+    write_flow(&flow, output)?;
+    println!("{} link(s) updated", updated);
+    Ok(0)
+}
+
+fn parse_bulk_edit(args: &[&str]) -> Result<BulkEdit, CliError> {
+    let parse_number = |text: &str| {
+        text.parse::<i64>()
+            .map_err(|_| CliError::Usage(format!("{:?} isn't a number", text)))
+    };
+    Ok(match args {
+        ["set", field, value] => BulkEdit::SetField {
+            field: field.to_string(),
+            value: serde_json::from_str(value)
+                .unwrap_or_else(|_| serde_json::Value::String(value.to_string())),
+        },
+        ["replace-entry", pattern, replacement] => BulkEdit::ReplaceEntry {
+            pattern: Regex::new(pattern)
+                .map_err(|err| CliError::Usage(format!("invalid regex: {}", err)))?,
+            replacement: replacement.to_string(),
+        },
+        ["move", group] => BulkEdit::MoveToGroup {
+            group: group
+                .split('/')
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect(),
+        },
+        ["renumber", start] => BulkEdit::RenumberProgress {
+            start: parse_number(start)?,
+            step: 1,
+        },
+        ["renumber", start, step] => BulkEdit::RenumberProgress {
+            start: parse_number(start)?,
+            step: parse_number(step)?,
+        },
+        _ => return Err(CliError::Usage("invalid bulk edit".into())),
+    })
+}
+
+fn bulk(args: &[&str]) -> Result<i32, CliError> {
+    let (input, query_text, edit, output) = match args {
+        [input, query_text, edit @ .., "--dry-run"] => (*input, *query_text, edit, None),
+        [input, query_text, edit @ .., "--output", output] => {
+            (*input, *query_text, edit, Some(*output))
+        }
+        _ => {
+            return Err(CliError::Usage(
+                "bulk take an input, a query, an edit, and either --dry-run or --output".into(),
+            ))
+        }
+    };
+    let query: FlowQuery = query_text.parse().map_err(CliError::Query)?;
+    let edit = parse_bulk_edit(edit)?;
+    let mut flow = read_output(input)?;
+    let patch = flow
+        .bulk_edit(&query, &edit)
+        .map_err(|err| CliError::Edit(input.to_string(), err))?;
+    print!("{}", patch);
+    println!("{} change(s)", patch.operations.len());
+    if let Some(output) = output {
+        patch
+            .apply(&mut flow)
+            .map_err(|err| CliError::Patch(input.to_string(), err))?;
+        write_flow(&flow, output)?;
+    };
+    Ok(0)
+}
+
+fn split(input: &str, directory: &str) -> Result<i32, CliError> {
+    read_output(input)?
+        .write_project(Path::new(directory))
+        .map_err(CliError::Project)?;
+    Ok(0)
+}
+
+fn merge(args: &[&str]) -> Result<i32, CliError> {
+    let (markers, base, ours, theirs, output) = match args {
+        ["--markers", base, ours, theirs, output] => (true, *base, *ours, *theirs, *output),
+        [base, ours, theirs, output] => (false, *base, *ours, *theirs, *output),
+        _ => {
+            return Err(CliError::Usage(
+                "merge take three inputs and an output".into(),
+            ))
+        }
+    };
+    if markers && !is_json(output) {
+        return Err(CliError::Usage(
+            "the output of merge --markers should be a JSON file".into(),
+        ));
+    };
+    let merge = FlowMerge::new(
+        &read_output(base)?,
+        &read_output(ours)?,
+        &read_output(theirs)?,
+    );
+    for conflict in &merge.conflicts {
+        println!("conflict: {}", conflict);
+    }
+    if markers {
+        let json = merge
+            .to_json_with_markers()
+            .map_err(|err| CliError::Json(output.to_string(), err))?;
+        write_output(Some(output), &to_json(&json)?)?;
+    } else {
+        write_flow(&merge.merged, output)?;
+    };
+    Ok(if merge.is_clean() { 0 } else { 1 })
+}
+
+fn run(args: &[String]) -> Result<i32, CliError> {
+    let command = match args.first() {
+        Some(command) => command.as_str(),
+        None => return Err(CliError::Usage("no command given".into())),
+    };
+    let args: Vec<&str> = args[1..].iter().map(String::as_str).collect();
+    match (command, args.as_slice()) {
+        ("decode", [input]) => decode(input, None),
+        ("decode", [input, output]) => decode(input, Some(output)),
+        ("encode", [input, output]) => encode(input, output),
+        ("raw-dump", [input]) => raw_dump(input, None),
+        ("raw-dump", [input, output]) => raw_dump(input, Some(output)),
+        ("validate", [input]) => validate(input),
+        ("stats", [input]) => stats(input),
+        ("graph", args) => graph(args),
+        ("diff", args) => diff(args),
+        ("make-patch", [old, new]) => make_patch(old, new, None),
+        ("make-patch", [old, new, output]) => make_patch(old, new, Some(output)),
+        ("apply-patch", args) => apply_patch(args),
+        ("query", [input, query_text]) => query(input, query_text),
+        ("rename", [input, old, new, output]) => rename(input, old, new, output),
+        ("merge", args) => merge(args),
+        ("bulk", args) => bulk(args),
+        ("split", [input, directory]) => split(input, directory),
+        ("help", _) | ("--help", _) | ("-h", _) => {
+            println!("{}", USAGE);
+            Ok(0)
+        }
+        ("decode", _)
+        | ("encode", _)
+        | ("raw-dump", _)
+        | ("validate", _)
+        | ("stats", _)
+        | ("make-patch", _)
+        | ("query", _)
+        | ("rename", _)
+        | ("split", _) => Err(CliError::Usage(format!(
+            "wrong number of arguments for {}",
+            command
+        ))),
+        _ => Err(CliError::Usage(format!("unknown command {:?}", command))),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(code) => exit(code),
+        Err(err) => {
+            eprintln!("flowtool: {}", err);
+            exit(err.exit_code());
+        }
+    }
+}
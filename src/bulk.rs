@@ -0,0 +1,340 @@
+use crate::diff::node_fields;
+use crate::patch::{apply_operation, find_group, reference};
+use crate::query::normalize;
+use crate::{EditError, FlowDataOutput, FlowPatch, FlowQuery, NodePath, PatchOperation};
+use regex::Regex;
+use serde_json::Value;
+
+/// An edit done on every node matching a [`FlowQuery`], with [`FlowDataOutput::bulk_edit`]
+#[derive(Debug, Clone)]
+pub enum BulkEdit {
+    /// set a field of every node that has it (like `debugmenu_tag`)
+    SetField {
+        /// the name of the field, as in the JSON or the flow file (case and `_` are ignored)
+        field: String,
+        /// the new value, as it is in the JSON
+        value: Value,
+    },
+    /// replace the text matching `pattern` in the scripts of the `entry` of every node
+    ReplaceEntry {
+        /// the replaced text
+        pattern: Regex,
+        /// the replacement, where `$1` or `${name}` refer to the groups of `pattern`
+        replacement: String,
+    },
+    /// move every node at the end of a group
+    MoveToGroup {
+        /// the names of the group, from the outermost. Empty for the top level list.
+        group: Vec<String>,
+    },
+    /// set the non-empty `scenarioProgressNo` of the nodes to `start`, `start + step`, ... in
+    /// order. The progress of their timeline is changed with it.
+    RenumberProgress {
+        /// the first progress
+        start: i64,
+        /// the difference between two progresses
+        step: i64,
+    },
+}
+
+/// apply `operation` to `working`, and add it to the patch
+fn push(
+    patch: &mut FlowPatch,
+    working: &mut FlowDataOutput,
+    operation: PatchOperation,
+) -> Result<(), EditError> {
+    apply_operation(working, &operation).map_err(EditError::Conflict)?;
+    patch.operations.push(operation);
+    Ok(())
+}
+
+impl FlowDataOutput {
+    /// Return the changes doing `edit` on every node matching `query` would make, as a
+    /// [`FlowPatch`]. It can be printed to review the changes, then applied with
+    /// [`FlowPatch::apply`].
+    ///
+    /// Nodes that wouldn't change (like nodes without the field to set, or already in the group
+    /// to move to) are skipped. Fail if a change isn't possible, like a value of the wrong type.
+    pub fn bulk_edit(&self, query: &FlowQuery, edit: &BulkEdit) -> Result<FlowPatch, EditError> {
+        let matches = self.query(query);
+        let mut patch = FlowPatch::new();
+        let mut working = self.clone();
+        let set_field =
+            |path: &NodePath, field: &str, old: Value, new: Value| PatchOperation::SetField {
+                node: reference(self, path),
+                field: field.to_string(),
+                old,
+                new,
+            };
+
+        match edit {
+            BulkEdit::SetField { field, value } => {
+                let wanted = normalize(field);
+                for path in &matches {
+                    let fields = node_fields(self.get_node(path).unwrap());
+                    let found = fields
+                        .into_iter()
+                        .find(|(name, _)| normalize(name) == wanted);
+                    if let Some((name, old)) = found {
+                        if old != *value {
+                            let operation = set_field(path, &name, old, value.clone());
+                            push(&mut patch, &mut working, operation)?;
+                        }
+                    }
+                }
+            }
+            BulkEdit::ReplaceEntry {
+                pattern,
+                replacement,
+            } => {
+                for path in &matches {
+                    let entry = match self.get_node(path).unwrap().entry() {
+                        Some(entry) => entry,
+                        None => continue,
+                    };
+                    let replaced: Vec<String> = entry
+                        .iter()
+                        .map(|script| {
+                            pattern
+                                .replace_all(script, replacement.as_str())
+                                .into_owned()
+                        })
+                        .collect();
+                    if replaced != entry {
+                        let operation = set_field(path, "entry", entry.into(), replaced.into());
+                        push(&mut patch, &mut working, operation)?;
+                    }
+                }
+            }
+            BulkEdit::MoveToGroup { group } => {
+                let target = find_group(self, group)
+                    .ok_or_else(|| EditError::UnknownGroup(group.clone()))?;
+                let mut moved: Vec<&NodePath> = Vec::new();
+                for path in &matches {
+                    let already_moved = moved.last().map(|last| last.contains(path)) == Some(true);
+                    if !already_moved
+                        && !path.contains(&target)
+                        && path.parent().as_ref() != Some(&target)
+                    {
+                        moved.push(path);
+                    }
+                }
+                // delete from the last, so the paths of the other nodes stay the same
+                for path in moved.iter().rev() {
+                    let operation = PatchOperation::DeleteNode {
+                        node: reference(&working, path),
+                    };
+                    push(&mut patch, &mut working, operation)?;
+                }
+                for path in moved {
+                    let operation = PatchOperation::AddToGroup {
+                        group: group.clone(),
+                        position: None,
+                        node: self.get_node(path).unwrap().clone(),
+                    };
+                    push(&mut patch, &mut working, operation)?;
+                }
+            }
+            BulkEdit::RenumberProgress { start, step } => {
+                let mut progress = *start;
+                for path in &matches {
+                    let node = self.get_node(path).unwrap();
+                    let old = match node.scenario_progress_no() {
+                        Some(old) if !old.is_empty() => old,
+                        _ => continue,
+                    };
+                    let new = progress.to_string();
+                    progress += step;
+                    if old == new {
+                        continue;
+                    };
+                    let operation =
+                        set_field(path, "scenario_progress_no", old.into(), new.clone().into());
+                    push(&mut patch, &mut working, operation)?;
+                    if let Some(timeline) = node.timeline() {
+                        if timeline.scenario_progress_no.as_deref() == Some(old) {
+                            let mut renumbered = timeline.clone();
+                            renumbered.scenario_progress_no = Some(new);
+                            let operation = set_field(
+                                path,
+                                "timeline",
+                                serde_json::to_value(timeline).unwrap(),
+                                serde_json::to_value(renumbered).unwrap(),
+                            );
+                            push(&mut patch, &mut working, operation)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_same, flow, scenario};
+    use serde_json::json;
+
+    /// a [`crate::ScenarioWithProgNo`] setting the progress `progress`, with a timeline for
+    /// `timeline`
+    fn progress(name: &str, progress: &str, timeline: &str) -> Value {
+        json!({ "ScenarioWithProgNo": {
+            "entry": [],
+            "socket": { "socket_in": { "start": name }, "socket_out": {} },
+            "debugname": name,
+            "timeline": { "scenario_progress_no": timeline },
+            "scenario_progress_no": progress,
+        }})
+    }
+
+    fn base() -> FlowDataOutput {
+        flow(vec![
+            (
+                "Main",
+                vec![
+                    scenario("a", "a01", Some("b")),
+                    scenario("b", "b01", Some("c")),
+                    scenario("c", "c01", None),
+                ],
+            ),
+            ("Side", vec![scenario("s", "s01", None)]),
+        ])
+    }
+
+    /// return the flow after applying the bulk edit, checking the patch give the same result
+    fn edit(output: &FlowDataOutput, query: &str, edit: BulkEdit) -> FlowDataOutput {
+        let patch = output.bulk_edit(&query.parse().unwrap(), &edit).unwrap();
+        let mut edited = output.clone();
+        patch.apply(&mut edited).unwrap();
+        assert!(edited
+            .bulk_edit(&query.parse().unwrap(), &edit)
+            .unwrap()
+            .operations
+            .is_empty());
+        edited
+    }
+
+    #[test]
+    fn set_field() {
+        let edited = edit(
+            &base(),
+            "group = Main and idname != b",
+            BulkEdit::SetField {
+                field: "DebugMenu_Tag".into(),
+                value: json!("system"),
+            },
+        );
+        let tags: Vec<Value> = edited
+            .nodes()
+            .into_iter()
+            .filter(|(path, _)| path.0.len() == 2)
+            .map(|(_, node)| node_fields(node)["debugmenu_tag"].clone())
+            .collect();
+        assert_eq!(
+            tags,
+            vec![json!("system"), json!(""), json!("system"), json!("")]
+        );
+    }
+
+    #[test]
+    fn set_field_of_the_wrong_type() {
+        let result = base().bulk_edit(
+            &"idname = a".parse().unwrap(),
+            &BulkEdit::SetField {
+                field: "entry".into(),
+                value: json!(3),
+            },
+        );
+        assert!(matches!(result, Err(EditError::Conflict(_))));
+    }
+
+    #[test]
+    fn replace_entry() {
+        let edited = edit(
+            &base(),
+            "group = Main",
+            BulkEdit::ReplaceEntry {
+                pattern: Regex::new("([ab])01").unwrap(),
+                replacement: "${1}02".into(),
+            },
+        );
+        let entries: Vec<&[String]> = edited
+            .nodes()
+            .into_iter()
+            .filter_map(|(_, node)| node.entry())
+            .collect();
+        assert_eq!(entries, vec![["a02"], ["b02"], ["c01"], ["s01"]]);
+    }
+
+    #[test]
+    fn move_to_group() {
+        let edited = edit(
+            &base(),
+            "idname = a or idname = c or group = Side",
+            BulkEdit::MoveToGroup {
+                group: vec!["Side".into()],
+            },
+        );
+        assert_same(
+            &edited,
+            &flow(vec![
+                ("Main", vec![scenario("b", "b01", Some("c"))]),
+                (
+                    "Side",
+                    vec![
+                        scenario("s", "s01", None),
+                        scenario("a", "a01", Some("b")),
+                        scenario("c", "c01", None),
+                    ],
+                ),
+            ]),
+        );
+
+        let unknown = base().bulk_edit(
+            &"idname = a".parse().unwrap(),
+            &BulkEdit::MoveToGroup {
+                group: vec!["Other".into()],
+            },
+        );
+        assert_eq!(
+            unknown.unwrap_err(),
+            EditError::UnknownGroup(vec!["Other".into()])
+        );
+    }
+
+    #[test]
+    fn renumber_progress() {
+        let output = flow(vec![(
+            "Main",
+            vec![
+                progress("a", "10", "10"),
+                progress("b", "", ""),
+                progress("c", "11", "12"),
+                progress("d", "30", "30"),
+            ],
+        )]);
+        let edited = edit(
+            &output,
+            "kind = ScenarioWithProgNo",
+            BulkEdit::RenumberProgress {
+                start: 100,
+                step: 10,
+            },
+        );
+        assert_same(
+            &edited,
+            &flow(vec![(
+                "Main",
+                vec![
+                    progress("a", "100", "100"),
+                    progress("b", "", ""),
+                    // the timeline didn't match the node, so it is left unchanged
+                    progress("c", "110", "12"),
+                    progress("d", "120", "120"),
+                ],
+            )]),
+        );
+    }
+}
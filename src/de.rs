@@ -0,0 +1,373 @@
+use crate::{FlowData, FlowDataError, FlowDataValue};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Unexpected, Visitor};
+use std::convert::TryInto;
+use std::vec;
+
+/// Deserialize a `T` from the root vector of a [`FlowData`] (the last one, like
+/// [`crate::FlowDataOutput`]).
+///
+/// See [`crate::to_flowdata`] for how values are mapped.
+pub fn from_flowdata<T: DeserializeOwned>(source: &FlowData) -> Result<T, FlowDataError> {
+    let root_id: u16 = source.vector_len().saturating_sub(1).try_into()?;
+    from_flowdata_value(source, &FlowDataValue::RefVec(root_id))
+}
+
+/// Deserialize a `T` from the given value of a [`FlowData`].
+///
+/// See [`crate::to_flowdata`] for how values are mapped.
+pub fn from_flowdata_value<T: DeserializeOwned>(
+    source: &FlowData,
+    value: &FlowDataValue,
+) -> Result<T, FlowDataError> {
+    T::deserialize(FlowDataDeserializer::new(source, value.clone()))
+}
+
+/// A serde [`de::Deserializer`] that read a value of a [`FlowData`]
+pub struct FlowDataDeserializer<'a> {
+    source: &'a FlowData,
+    value: FlowDataValue,
+}
+
+impl<'a> FlowDataDeserializer<'a> {
+    /// Create a new deserializer that will read `value`, in `source`
+    pub fn new(source: &'a FlowData, value: FlowDataValue) -> Self {
+        Self { source, value }
+    }
+
+    fn unexpected(&self) -> Unexpected<'_> {
+        match &self.value {
+            FlowDataValue::String(str) => Unexpected::Str(str),
+            FlowDataValue::RefDic(_) => Unexpected::Map,
+            FlowDataValue::RefVec(_) => Unexpected::Seq,
+        }
+    }
+
+    fn get_string(&self, expected: &'static str) -> Result<&str, FlowDataError> {
+        match &self.value {
+            FlowDataValue::String(str) => Ok(str),
+            _ => Err(de::Error::invalid_type(self.unexpected(), &expected)),
+        }
+    }
+
+    fn parse<T: std::str::FromStr>(&self, expected: &'static str) -> Result<T, FlowDataError> {
+        let str = self.get_string(expected)?;
+        str.parse()
+            .map_err(|_| de::Error::invalid_value(Unexpected::Str(str), &expected))
+    }
+
+    fn dictionary_access(&self, dicid: u16) -> Result<DictionaryAccess<'a>, FlowDataError> {
+        let dic = self.source.get_dictionary(dicid as usize).ok_or_else(|| {
+            FlowDataError::DicReferenceTooBig(dicid, self.source.dictionary_len() as u32)
+        })?;
+        let mut entries: Vec<(String, FlowDataValue)> = dic
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(DictionaryAccess {
+            source: self.source,
+            entries: entries.into_iter(),
+            next_value: None,
+        })
+    }
+
+    fn vector_access(&self, vecid: u16) -> Result<VectorAccess<'a>, FlowDataError> {
+        let vec = self.source.get_vector(vecid as usize).ok_or_else(|| {
+            FlowDataError::VecReferenceTooBig(vecid, self.source.vector_len() as u32)
+        })?;
+        Ok(VectorAccess {
+            source: self.source,
+            values: vec.clone().into_iter(),
+        })
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident: $type:ty,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FlowDataError> {
+                visitor.$visit(self.parse::<$type>(stringify!($type))?)
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for FlowDataDeserializer<'a> {
+    type Error = FlowDataError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FlowDataError> {
+        match &self.value {
+            FlowDataValue::String(str) => visitor.visit_string(str.clone()),
+            FlowDataValue::RefDic(dicid) => visitor.visit_map(self.dictionary_access(*dicid)?),
+            FlowDataValue::RefVec(vecid) => visitor.visit_seq(self.vector_access(*vecid)?),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool: bool,
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+        deserialize_char => visit_char: char,
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FlowDataError> {
+        visitor.visit_string(self.get_string("a string")?.to_string())
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FlowDataError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, FlowDataError> {
+        Err(de::Error::custom("bytes can't be stored in a flow file"))
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FlowDataError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FlowDataError> {
+        // a `None` is stored as a missing key, so a present value is always `Some`
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FlowDataError> {
+        let is_empty = match &self.value {
+            FlowDataValue::String(str) => str.is_empty(),
+            FlowDataValue::RefVec(vecid) => self.vector_access(*vecid)?.values.len() == 0,
+            FlowDataValue::RefDic(_) => false,
+        };
+        if is_empty {
+            visitor.visit_unit()
+        } else {
+            Err(de::Error::invalid_type(
+                self.unexpected(),
+                &"an empty string or vector",
+            ))
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, FlowDataError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, FlowDataError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FlowDataError> {
+        match &self.value {
+            FlowDataValue::RefVec(vecid) => visitor.visit_seq(self.vector_access(*vecid)?),
+            _ => Err(de::Error::invalid_type(self.unexpected(), &"a vector")),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, FlowDataError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, FlowDataError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FlowDataError> {
+        match &self.value {
+            FlowDataValue::RefDic(dicid) => visitor.visit_map(self.dictionary_access(*dicid)?),
+            _ => Err(de::Error::invalid_type(self.unexpected(), &"a dictionary")),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, FlowDataError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, FlowDataError> {
+        match &self.value {
+            FlowDataValue::String(str) => visitor.visit_enum(str.clone().into_deserializer()),
+            FlowDataValue::RefDic(dicid) => {
+                let dic = self.source.get_dictionary(*dicid as usize).ok_or_else(|| {
+                    FlowDataError::DicReferenceTooBig(*dicid, self.source.dictionary_len() as u32)
+                })?;
+                if dic.len() != 1 {
+                    return Err(de::Error::invalid_length(
+                        dic.len(),
+                        &"a dictionary with a single key",
+                    ));
+                };
+                let (variant, value) = dic.iter().next().unwrap();
+                visitor.visit_enum(EnumAccess {
+                    source: self.source,
+                    variant: variant.clone(),
+                    value: value.clone(),
+                })
+            }
+            FlowDataValue::RefVec(_) => Err(de::Error::invalid_type(
+                self.unexpected(),
+                &"a string or a dictionary",
+            )),
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, FlowDataError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, FlowDataError> {
+        visitor.visit_unit()
+    }
+}
+
+struct DictionaryAccess<'a> {
+    source: &'a FlowData,
+    entries: vec::IntoIter<(String, FlowDataValue)>,
+    next_value: Option<FlowDataValue>,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for DictionaryAccess<'a> {
+    type Error = FlowDataError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, FlowDataError> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.next_value = Some(value);
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, FlowDataError> {
+        match self.next_value.take() {
+            Some(value) => seed.deserialize(FlowDataDeserializer::new(self.source, value)),
+            None => Err(de::Error::custom("next_value called before next_key")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct VectorAccess<'a> {
+    source: &'a FlowData,
+    values: vec::IntoIter<FlowDataValue>,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for VectorAccess<'a> {
+    type Error = FlowDataError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, FlowDataError> {
+        match self.values.next() {
+            Some(value) => seed
+                .deserialize(FlowDataDeserializer::new(self.source, value))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+struct EnumAccess<'a> {
+    source: &'a FlowData,
+    variant: String,
+    value: FlowDataValue,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for EnumAccess<'a> {
+    type Error = FlowDataError;
+    type Variant = FlowDataDeserializer<'a>;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, FlowDataDeserializer<'a>), FlowDataError> {
+        let variant_deserializer: de::value::StringDeserializer<FlowDataError> =
+            self.variant.into_deserializer();
+        let variant = seed.deserialize(variant_deserializer)?;
+        Ok((variant, FlowDataDeserializer::new(self.source, self.value)))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for FlowDataDeserializer<'a> {
+    type Error = FlowDataError;
+
+    fn unit_variant(self) -> Result<(), FlowDataError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, FlowDataError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, FlowDataError> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, FlowDataError> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
@@ -0,0 +1,449 @@
+use crate::lint::is_special_idname;
+use crate::{FlowDataOutput, NodePath, OutputEnum};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+/// return the fields of a node, as JSON values, without its socket and the content of a group
+pub(crate) fn node_fields(node: &OutputEnum) -> BTreeMap<String, Value> {
+    let inner = match serde_json::to_value(node) {
+        Ok(Value::Object(object)) => object.into_iter().next().map(|(_, inner)| inner),
+        _ => None,
+    };
+    match inner {
+        Some(Value::Object(fields)) => fields
+            .into_iter()
+            .filter(|(field, _)| {
+                field != "socket"
+                    && !(field == "data" && matches!(node, OutputEnum::FollowGroup(_)))
+            })
+            .collect(),
+        _ => BTreeMap::new(),
+    }
+}
+
+/// return the idnames declared by a node
+pub(crate) fn node_idnames(node: &OutputEnum) -> Vec<String> {
+    node.socket()
+        .map(|socket| socket.idnames().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Pair the nodes of `old` with the nodes of `new`: first by a shared `socket_in` idname (other
+/// than a special one like `$START`, that several nodes can declare), then by the same key and
+/// debugname. Return the pairs, the removed nodes and the added nodes.
+#[allow(clippy::type_complexity)]
+pub(crate) fn match_nodes(
+    old: &FlowDataOutput,
+    new: &FlowDataOutput,
+) -> (Vec<(NodePath, NodePath)>, Vec<NodePath>, Vec<NodePath>) {
+    let old_nodes = old.nodes();
+    let new_nodes = new.nodes();
+    let mut old_by_idname: HashMap<String, usize> = HashMap::new();
+    for (index, (_, node)) in old_nodes.iter().enumerate() {
+        for idname in node_idnames(node) {
+            if !is_special_idname(&idname) {
+                old_by_idname.entry(idname).or_insert(index);
+            }
+        }
+    }
+
+    let mut old_matched = vec![false; old_nodes.len()];
+    let mut new_match: Vec<Option<usize>> = vec![None; new_nodes.len()];
+    for (new_index, (_, node)) in new_nodes.iter().enumerate() {
+        for idname in node_idnames(node) {
+            if let Some(old_index) = old_by_idname.get(&idname) {
+                if !old_matched[*old_index] {
+                    old_matched[*old_index] = true;
+                    new_match[new_index] = Some(*old_index);
+                    break;
+                }
+            }
+        }
+    }
+
+    let mut old_by_name: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
+    for (index, (_, node)) in old_nodes.iter().enumerate().rev() {
+        if !old_matched[index] {
+            old_by_name
+                .entry((node.key(), node.debugname().unwrap_or_default()))
+                .or_default()
+                .push(index);
+        }
+    }
+    for (new_index, (_, node)) in new_nodes.iter().enumerate() {
+        if new_match[new_index].is_some() {
+            continue;
+        };
+        if let Some(candidates) =
+            old_by_name.get_mut(&(node.key(), node.debugname().unwrap_or_default()))
+        {
+            if let Some(old_index) = candidates.pop() {
+                old_matched[old_index] = true;
+                new_match[new_index] = Some(old_index);
+            }
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let mut added = Vec::new();
+    for (new_index, old_index) in new_match.into_iter().enumerate() {
+        match old_index {
+            Some(old_index) => pairs.push((
+                old_nodes[old_index].0.clone(),
+                new_nodes[new_index].0.clone(),
+            )),
+            None => added.push(new_nodes[new_index].0.clone()),
+        }
+    }
+    let removed = old_nodes
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !old_matched[*index])
+        .map(|(_, (path, _))| path.clone())
+        .collect();
+    (pairs, removed, added)
+}
+
+/// A node, as described in a [`FlowDiff`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeSummary {
+    /// the path of the node
+    pub path: NodePath,
+    /// the name of the groups containing the node, from the outermost
+    pub groups: Vec<String>,
+    /// the key of the node (like `"Scenario"`)
+    pub key: String,
+    /// the debugname of the node
+    pub debugname: String,
+    /// the idnames declared by the node
+    pub idnames: Vec<String>,
+}
+
+impl NodeSummary {
+    pub(crate) fn new(output: &FlowDataOutput, path: &NodePath) -> Self {
+        let node = output.get_node(path);
+        Self {
+            path: path.clone(),
+            groups: output
+                .group_names(path)
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            key: node.map(|node| node.key().to_string()).unwrap_or_default(),
+            debugname: node
+                .and_then(|node| node.debugname())
+                .unwrap_or_default()
+                .to_string(),
+            idnames: node.map(node_idnames).unwrap_or_default(),
+        }
+    }
+}
+
+impl fmt::Display for NodeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", self.key)?;
+        for group in &self.groups {
+            write!(f, "{}/", group)?;
+        }
+        write!(f, "{:?} ({})", self.debugname, self.path)
+    }
+}
+
+/// A field of a node that changed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    /// the name of the field, as in the JSON (`group` when the node moved to another group)
+    pub field: String,
+    /// the old value, null if it didn't exist
+    pub old: Value,
+    /// the new value, null if it doesn't exist anymore
+    pub new: Value,
+}
+
+/// Whether a [`LinkChange`] is about an incoming or an outgoing link
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LinkDirection {
+    /// a `socket_in` entry, from label to idname
+    In,
+    /// a `socket_out` entry, from label to the target idname
+    Out,
+}
+
+/// A socket link of a node that was added, removed or rewired
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LinkChange {
+    /// whether this is an incoming or outgoing link
+    pub direction: LinkDirection,
+    /// the label of the link
+    pub label: String,
+    /// the old idname, None if the link was added
+    pub old: Option<String>,
+    /// the new idname, None if the link was removed
+    pub new: Option<String>,
+}
+
+/// A node present in both flows, that changed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeChange {
+    /// the node in the old flow
+    pub old: NodeSummary,
+    /// the node in the new flow
+    pub new: NodeSummary,
+    /// the changed fields
+    pub fields: Vec<FieldChange>,
+    /// the changed socket links
+    pub links: Vec<LinkChange>,
+}
+
+/// The semantic difference between two [`FlowDataOutput`].
+///
+/// Nodes are paired by the `socket_in` idnames they declare, then by their key and debugname
+/// (for nodes without socket, like groups). Moving a node in the list doesn't count as a change,
+/// but moving it to another group does.
+///
+/// It is displayed as a human readable summary, and can be serialized (to JSON for example).
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FlowDiff {
+    /// the nodes only present in the new flow
+    pub added: Vec<NodeSummary>,
+    /// the nodes only present in the old flow
+    pub removed: Vec<NodeSummary>,
+    /// the nodes present in both flow, but changed
+    pub changed: Vec<NodeChange>,
+}
+
+fn link_changes(
+    direction: LinkDirection,
+    old: &BTreeMap<String, String>,
+    new: &BTreeMap<String, String>,
+) -> Vec<LinkChange> {
+    let labels: HashSet<&String> = old.keys().chain(new.keys()).collect();
+    let mut labels: Vec<&String> = labels.into_iter().collect();
+    labels.sort();
+    labels
+        .into_iter()
+        .filter(|label| old.get(*label) != new.get(*label))
+        .map(|label| LinkChange {
+            direction,
+            label: label.clone(),
+            old: old.get(label).cloned(),
+            new: new.get(label).cloned(),
+        })
+        .collect()
+}
+
+/// return the changes between two version of a node
+pub(crate) fn compare_nodes(
+    old: &OutputEnum,
+    new: &OutputEnum,
+) -> (Vec<FieldChange>, Vec<LinkChange>) {
+    let mut fields = Vec::new();
+    if old.key() != new.key() {
+        fields.push(FieldChange {
+            field: "kind".into(),
+            old: Value::String(old.key().to_string()),
+            new: Value::String(new.key().to_string()),
+        });
+    };
+    let old_fields = node_fields(old);
+    let new_fields = node_fields(new);
+    let names: HashSet<&String> = old_fields.keys().chain(new_fields.keys()).collect();
+    let mut names: Vec<&String> = names.into_iter().collect();
+    names.sort();
+    for name in names {
+        let old_value = old_fields.get(name).cloned().unwrap_or(Value::Null);
+        let new_value = new_fields.get(name).cloned().unwrap_or(Value::Null);
+        if old_value != new_value {
+            fields.push(FieldChange {
+                field: name.clone(),
+                old: old_value,
+                new: new_value,
+            });
+        }
+    }
+
+    let empty = BTreeMap::new();
+    let mut links = Vec::new();
+    let old_socket = old.socket();
+    let new_socket = new.socket();
+    links.extend(link_changes(
+        LinkDirection::In,
+        old_socket.map_or(&empty, |socket| socket.socket_in()),
+        new_socket.map_or(&empty, |socket| socket.socket_in()),
+    ));
+    links.extend(link_changes(
+        LinkDirection::Out,
+        old_socket.map_or(&empty, |socket| socket.socket_out()),
+        new_socket.map_or(&empty, |socket| socket.socket_out()),
+    ));
+    (fields, links)
+}
+
+impl FlowDiff {
+    /// Compute the difference between `old` and `new`
+    pub fn new(old: &FlowDataOutput, new: &FlowDataOutput) -> Self {
+        let (pairs, removed, added) = match_nodes(old, new);
+        let mut diff = FlowDiff {
+            added: added
+                .iter()
+                .map(|path| NodeSummary::new(new, path))
+                .collect(),
+            removed: removed
+                .iter()
+                .map(|path| NodeSummary::new(old, path))
+                .collect(),
+            changed: Vec::new(),
+        };
+
+        for (old_path, new_path) in pairs {
+            let old_summary = NodeSummary::new(old, &old_path);
+            let new_summary = NodeSummary::new(new, &new_path);
+            let (mut fields, links) = compare_nodes(
+                old.get_node(&old_path).unwrap(),
+                new.get_node(&new_path).unwrap(),
+            );
+            if old_summary.groups != new_summary.groups {
+                fields.push(FieldChange {
+                    field: "group".into(),
+                    old: Value::String(old_summary.groups.join("/")),
+                    new: Value::String(new_summary.groups.join("/")),
+                });
+            };
+            if !fields.is_empty() || !links.is_empty() {
+                diff.changed.push(NodeChange {
+                    old: old_summary,
+                    new: new_summary,
+                    fields,
+                    links,
+                });
+            }
+        }
+        diff
+    }
+
+    /// return true if the two flows are the same
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for FlowDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in &self.removed {
+            writeln!(f, "- {}", node)?;
+        }
+        for node in &self.added {
+            writeln!(f, "+ {}", node)?;
+        }
+        for change in &self.changed {
+            writeln!(f, "~ {}", change.new)?;
+            for field in &change.fields {
+                writeln!(f, "    {}: {} -> {}", field.field, field.old, field.new)?;
+            }
+            for link in &change.links {
+                let direction = match link.direction {
+                    LinkDirection::In => "in",
+                    LinkDirection::Out => "out",
+                };
+                let describe = |idname: &Option<String>| match idname {
+                    Some(idname) => format!("{:?}", idname),
+                    None => "(none)".to_string(),
+                };
+                writeln!(
+                    f,
+                    "    {} {}: {} -> {}",
+                    direction,
+                    link.label,
+                    describe(&link.old),
+                    describe(&link.new)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{flow, scenario};
+    use serde_json::json;
+
+    fn custom(value: &str) -> Value {
+        json!({ "Custom": { "key": "Unknown", "data": {
+            "value": value,
+            "socket": [{ "idname": "u", "in": "start" }],
+        }}})
+    }
+
+    #[test]
+    fn changed_fields_and_links() {
+        let old = flow(vec![(
+            "Main",
+            vec![scenario("a", "a01", Some("b")), scenario("b", "b01", None)],
+        )]);
+        let new = flow(vec![(
+            "Main",
+            vec![scenario("a", "a02", Some("c")), scenario("c", "c01", None)],
+        )]);
+        let diff = FlowDiff::new(&old, &new);
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].debugname, "b");
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].debugname, "c");
+        assert_eq!(diff.changed.len(), 1);
+        let change = &diff.changed[0];
+        assert_eq!(
+            change.fields,
+            vec![FieldChange {
+                field: "entry".into(),
+                old: json!(["a01"]),
+                new: json!(["a02"]),
+            }]
+        );
+        assert_eq!(
+            change.links,
+            vec![LinkChange {
+                direction: LinkDirection::Out,
+                label: "next".into(),
+                old: Some("b".into()),
+                new: Some("c".into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn same_flow_give_an_empty_diff() {
+        let flow = flow(vec![(
+            "Main",
+            vec![scenario("a", "a01", None), custom("1")],
+        )]);
+        assert!(FlowDiff::new(&flow, &flow).is_empty());
+    }
+
+    #[test]
+    fn custom_node_data_is_compared() {
+        let old = flow(vec![("Main", vec![custom("1")])]);
+        let new = flow(vec![("Main", vec![custom("2")])]);
+        let diff = FlowDiff::new(&old, &new);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].fields[0].field, "data");
+    }
+
+    #[test]
+    fn special_idname_dont_pair_nodes() {
+        let mut start = scenario("$START", "a01", None);
+        start["Scenario"]["debugname"] = "a".into();
+        let mut other_start = scenario("$START", "n01", None);
+        other_start["Scenario"]["debugname"] = "n".into();
+        let old = flow(vec![("Main", vec![start.clone()])]);
+        let new = flow(vec![("Main", vec![other_start, start])]);
+        let diff = FlowDiff::new(&old, &new);
+        assert!(diff.removed.is_empty());
+        assert!(diff.changed.is_empty());
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].debugname, "n");
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{FlowDataOutput, FlowGraph, Follow, NodePath, OutputEnum};
+use std::fmt::Write as _;
+use std::io;
+
+/// Options for [`FlowDataOutput::to_dot`]
+#[derive(Debug, Clone, Default)]
+pub struct DotOptions {
+    /// Place each node at the position stored in its [`crate::Layout`], so the picture match the
+    /// one of the debug editor. `posX` is used as the horizontal position, `posH` as the vertical
+    /// one (going down) and `posW` as the width of the node.
+    ///
+    /// Position are only honored by layout engine that accept them, like `neato -n`.
+    pub use_layout: bool,
+}
+
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for chara in text.chars() {
+        match chara {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            _ => result.push(chara),
+        }
+    }
+    result
+}
+
+fn node_id(path: &NodePath) -> String {
+    let mut result = "n".to_string();
+    for index in &path.0 {
+        write!(result, "_{}", index).unwrap();
+    }
+    result
+}
+
+fn write_node(
+    dot: &mut String,
+    node: &OutputEnum,
+    path: &NodePath,
+    indent: usize,
+    options: &DotOptions,
+) {
+    let pad = "    ".repeat(indent);
+    if let Some(children) = node.children() {
+        writeln!(dot, "{}subgraph cluster{} {{", pad, node_id(path)).unwrap();
+        writeln!(
+            dot,
+            "{}    label=\"{}\";",
+            pad,
+            escape(node.debugname().unwrap_or_default())
+        )
+        .unwrap();
+        write_follow(dot, children, path, indent + 1, options);
+        writeln!(dot, "{}}}", pad).unwrap();
+        return;
+    };
+
+    let label = match node.debugname() {
+        Some(debugname) if !debugname.is_empty() => format!("{}\n{}", node.key(), debugname),
+        _ => node.key().to_string(),
+    };
+    write!(
+        dot,
+        "{}{} [label=\"{}\"",
+        pad,
+        node_id(path),
+        escape(&label)
+    )
+    .unwrap();
+    if options.use_layout {
+        if let Some(layout) = node.layout() {
+            write!(dot, ", pos=\"{},{}!\"", layout.x, -layout.h).unwrap();
+            if layout.w > 0 {
+                write!(dot, ", width={}", layout.w as f64 / 72.0).unwrap();
+            }
+        }
+    }
+    writeln!(dot, "];").unwrap();
+}
+
+fn write_follow(
+    dot: &mut String,
+    follow: &Follow,
+    parent: &NodePath,
+    indent: usize,
+    options: &DotOptions,
+) {
+    for (index, node) in follow.iter().enumerate() {
+        write_node(dot, node, &parent.child(index), indent, options);
+    }
+}
+
+impl FlowDataOutput {
+    /// Draw the story flow as a Graphviz DOT graph.
+    ///
+    /// Each node is labeled with its kind and its `debugname`, [`crate::FollowGroup`] are drawn as
+    /// cluster, and each link is labeled with its `socket_out` label. Link to an idname no node
+    /// declare are drawn to a dashed node with the name of the missing idname.
+    pub fn to_dot(&self, options: &DotOptions) -> String {
+        let mut dot = String::new();
+        dot.push_str("digraph flow {\n");
+        dot.push_str("    node [shape=box];\n");
+        write_follow(&mut dot, self.output(), &NodePath::default(), 1, options);
+
+        let graph = FlowGraph::new(self);
+        for edge in graph.edges() {
+            writeln!(
+                dot,
+                "    {} -> {} [label=\"{}\"];",
+                node_id(&graph.vertex(edge.from).path),
+                node_id(&graph.vertex(edge.to).path),
+                escape(&edge.label)
+            )
+            .unwrap();
+        }
+        for (count, link) in graph.dangling_links().iter().enumerate() {
+            writeln!(
+                dot,
+                "    missing_{} [label=\"{}\", style=dashed];",
+                count,
+                escape(&link.idname)
+            )
+            .unwrap();
+            writeln!(
+                dot,
+                "    {} -> missing_{} [label=\"{}\", style=dashed];",
+                node_id(&graph.vertex(link.from).path),
+                count,
+                escape(&link.label)
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Write the DOT graph made by [`FlowDataOutput::to_dot`] to `file`
+    pub fn write_dot<T: io::Write>(&self, file: &mut T, options: &DotOptions) -> io::Result<()> {
+        file.write_all(self.to_dot(options).as_bytes())
+    }
+}
@@ -0,0 +1,435 @@
+use crate::lint::is_special_idname;
+use crate::{FlowDataOutput, Follow, FollowSocket, NodePath, NodeRef, OutputEnum, PatchConflict};
+use std::collections::{BTreeSet, HashSet};
+use std::error::Error;
+use std::fmt;
+
+/// The error returned by the editing functions of [`FlowDataOutput`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// no node declare this idname
+    UnknownIdname(String),
+    /// there is no node at this path
+    UnknownNode(NodePath),
+    /// the node doesn't have this outgoing link
+    UnknownLabel(String),
+    /// the node doesn't have a socket (like a group)
+    NoSocket,
+    /// the inserted node doesn't have any incoming link to link it from
+    NoInput,
+    /// the idname is already declared by a node
+    IdnameExists(String),
+    /// the removed node doesn't have a single target to bridge its incoming links to. Contain the
+    /// targets it has.
+    NotBridgeable(Vec<String>),
+    /// the removed node declare an idname, like `$START`, that is referenced by the game
+    SpecialIdname(String),
+    /// no group has these names
+    UnknownGroup(Vec<String>),
+    /// a change of a bulk edit can't be done
+    Conflict(PatchConflict),
+}
+
+impl Error for EditError {}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownIdname(idname) => write!(f, "no node declare the idname {:?}", idname),
+            Self::UnknownNode(path) => write!(f, "there is no node at {}", path),
+            Self::UnknownLabel(label) => {
+                write!(f, "the node doesn't have an outgoing link {:?}", label)
+            }
+            Self::NoSocket => write!(f, "the node doesn't have a socket"),
+            Self::NoInput => write!(f, "the inserted node doesn't have any incoming link"),
+            Self::IdnameExists(idname) => {
+                write!(f, "the idname {:?} is already declared by a node", idname)
+            }
+            Self::NotBridgeable(targets) => write!(
+                f,
+                "the node should have one target to bridge its incoming links to (found {:?})",
+                targets
+            ),
+            Self::SpecialIdname(idname) => write!(
+                f,
+                "the node declare the idname {:?}, that is referenced by the game",
+                idname
+            ),
+            Self::UnknownGroup(names) => {
+                write!(f, "the group {:?} doesn't exist", names.join("/"))
+            }
+            Self::Conflict(conflict) => write!(f, "{}", conflict),
+        }
+    }
+}
+
+/// return `hint` if it isn't in `declared`, otherwise `hint` followed by the first number that
+/// make it unique
+fn unique_idname(declared: &HashSet<String>, hint: &str) -> String {
+    if !hint.is_empty() && !declared.contains(hint) {
+        return hint.to_string();
+    };
+    (2..)
+        .map(|number| format!("{}_{}", hint, number))
+        .find(|idname| !declared.contains(idname))
+        .unwrap()
+}
+
+fn for_each_socket_mut<F: FnMut(&mut FollowSocket)>(follow: &mut Follow, function: &mut F) {
+    for node in follow.iter_mut() {
+        if let Some(socket) = node.socket_mut() {
+            function(socket);
+        };
+        if let Some(children) = node.children_mut() {
+            for_each_socket_mut(children, function);
+        }
+    }
+}
+
+impl FlowDataOutput {
+    /// call `function` on the socket of every node
+    pub(crate) fn for_each_socket_mut<F: FnMut(&mut FollowSocket)>(&mut self, mut function: F) {
+        for_each_socket_mut(self.output_mut(), &mut function);
+    }
+
+    /// return every idname declared by a node
+    pub(crate) fn declared_idnames(&self) -> HashSet<String> {
+        self.nodes()
+            .into_iter()
+            .filter_map(|(_, node)| node.socket())
+            .flat_map(|socket| socket.idnames().map(str::to_string).collect::<Vec<_>>())
+            .collect()
+    }
+
+    /// Return an idname no node declare, based on `hint`: `hint` itself if it is free, otherwise
+    /// `hint` followed by a number (like `intro_2`).
+    pub fn fresh_idname(&self, hint: &str) -> String {
+        unique_idname(&self.declared_idnames(), hint)
+    }
+
+    /// Insert `node` in the story, between the node declaring the idname `idname` and the target
+    /// of its outgoing link `label`. Return the path of the inserted node, placed right after its
+    /// predecessor in the same list.
+    ///
+    /// The predecessor is linked to the incoming link of `node` whose label come first in
+    /// alphabetical order (`socket_in` is sorted by label). The incoming links of `node` with an
+    /// empty idname get a fresh idname (see [`FlowDataOutput::fresh_idname`]), based on its
+    /// debugname, and its outgoing links with an empty target are pointed to the previous target
+    /// of `label`. For example, a [`crate::Scenario`] with a socket
+    /// `FollowSocket::new().with_in("start", "").with_out("next", "")` is spliced in the story.
+    pub fn insert_after(
+        &mut self,
+        idname: &str,
+        label: &str,
+        mut node: OutputEnum,
+    ) -> Result<NodePath, EditError> {
+        let path = NodeRef::Idname(idname.to_string())
+            .resolve(self)
+            .ok_or_else(|| EditError::UnknownIdname(idname.to_string()))?;
+        let target = self
+            .get_node(&path)
+            .and_then(|node| node.socket())
+            .and_then(|socket| socket.socket_out().get(label))
+            .cloned()
+            .ok_or_else(|| EditError::UnknownLabel(label.to_string()))?;
+
+        let hint = node
+            .debugname()
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| node.key())
+            .to_string();
+        let mut declared = self.declared_idnames();
+        let socket = node.socket_mut().ok_or(EditError::NoSocket)?;
+        for idname in socket.socket_in.values_mut() {
+            if idname.is_empty() {
+                *idname = unique_idname(&declared, &hint);
+            } else if declared.contains(idname.as_str()) {
+                return Err(EditError::IdnameExists(idname.clone()));
+            };
+            declared.insert(idname.clone());
+        }
+        // the incoming link with the alphabetically first label
+        let new_idname = socket
+            .socket_in
+            .values()
+            .next()
+            .cloned()
+            .ok_or(EditError::NoInput)?;
+        for to in socket.socket_out.values_mut() {
+            if to.is_empty() {
+                *to = target.clone();
+            }
+        }
+
+        let predecessor = self.get_node_mut(&path).unwrap().socket_mut().unwrap();
+        predecessor.add_out(label.to_string(), new_idname);
+        let inserted = path.parent().unwrap().child(path.index().unwrap() + 1);
+        self.get_follow_mut(&path.parent().unwrap())
+            .unwrap()
+            .insert(inserted.index().unwrap(), node);
+        Ok(inserted)
+    }
+
+    /// Remove the node at `path` from the story, and point the links to it to the target of its
+    /// outgoing links. Return the removed node.
+    ///
+    /// The node should have outgoing links to a single idname (other than its own), and shouldn't
+    /// declare an idname referenced by the game (like `$START`).
+    pub fn remove_and_bridge(&mut self, path: &NodePath) -> Result<OutputEnum, EditError> {
+        let node = self
+            .get_node(path)
+            .ok_or_else(|| EditError::UnknownNode(path.clone()))?;
+        let socket = node.socket().ok_or(EditError::NoSocket)?;
+        let idnames: HashSet<&str> = socket.idnames().collect();
+        if let Some(special) = idnames.iter().find(|idname| is_special_idname(idname)) {
+            return Err(EditError::SpecialIdname(special.to_string()));
+        };
+        let targets: BTreeSet<&String> = socket
+            .socket_out()
+            .values()
+            .filter(|to| !idnames.contains(to.as_str()))
+            .collect();
+        let target = match targets.iter().next() {
+            Some(target) if targets.len() == 1 => target.to_string(),
+            _ => {
+                return Err(EditError::NotBridgeable(
+                    targets.into_iter().cloned().collect(),
+                ))
+            }
+        };
+        let idnames: HashSet<String> = idnames.into_iter().map(str::to_string).collect();
+
+        let removed = self
+            .get_follow_mut(&path.parent().unwrap())
+            .unwrap()
+            .remove(path.index().unwrap());
+        self.for_each_socket_mut(|socket| {
+            for to in socket.socket_out.values_mut() {
+                if idnames.contains(to) {
+                    *to = target.clone();
+                }
+            }
+        });
+        Ok(removed)
+    }
+
+    /// Replace the node at `path` by `node`, which take the socket of the replaced node, so every
+    /// link is kept. Return the replaced node.
+    pub fn replace(
+        &mut self,
+        path: &NodePath,
+        mut node: OutputEnum,
+    ) -> Result<OutputEnum, EditError> {
+        let old = self
+            .get_node_mut(path)
+            .ok_or_else(|| EditError::UnknownNode(path.clone()))?;
+        let socket = old.socket().ok_or(EditError::NoSocket)?.clone();
+        *node.socket_mut().ok_or(EditError::NoSocket)? = socket;
+        Ok(std::mem::replace(old, node))
+    }
+
+    /// Rename the `socket_in` idname `old` to `new`, and update every `socket_out` link pointing
+    /// to it. Return the number of links updated.
+    ///
+    /// Fail if no node declare `old`, or if a node already declare `new`.
+    pub fn rename_idname(&mut self, old: &str, new: &str) -> Result<usize, EditError> {
+        let declared = self.declared_idnames();
+        if !declared.contains(old) {
+            return Err(EditError::UnknownIdname(old.to_string()));
+        };
+        if old == new {
+            return Ok(0);
+        };
+        if declared.contains(new) {
+            return Err(EditError::IdnameExists(new.to_string()));
+        };
+        let mut updated = 0;
+        self.for_each_socket_mut(|socket| {
+            for idname in socket.socket_in.values_mut() {
+                if idname == old {
+                    *idname = new.to_string();
+                }
+            }
+            for to in socket.socket_out.values_mut() {
+                if to == old {
+                    *to = new.to_string();
+                    updated += 1;
+                }
+            }
+        });
+        Ok(updated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_same, flow, scenario};
+    use serde_json::json;
+
+    fn base() -> FlowDataOutput {
+        flow(vec![(
+            "Main",
+            vec![
+                scenario("$START", "a01", Some("b")),
+                scenario("b", "b01", Some("c")),
+                scenario("c", "c01", None),
+            ],
+        )])
+    }
+
+    /// a scenario to insert, with an empty idname and an empty target
+    fn unlinked(debugname: &str) -> OutputEnum {
+        let mut node = scenario("", "x01", Some(""));
+        node["Scenario"]["debugname"] = debugname.into();
+        serde_json::from_value(node).unwrap()
+    }
+
+    #[test]
+    fn insert_after_splice_the_node() {
+        let mut output = base();
+        let path = output
+            .insert_after("$START", "next", unlinked("x"))
+            .unwrap();
+        assert_eq!(path, NodePath(vec![0, 1]));
+
+        let mut expected_node = scenario("x", "x01", Some("b"));
+        expected_node["Scenario"]["debugname"] = "x".into();
+        let expected = flow(vec![(
+            "Main",
+            vec![
+                scenario("$START", "a01", Some("x")),
+                expected_node,
+                scenario("b", "b01", Some("c")),
+                scenario("c", "c01", None),
+            ],
+        )]);
+        assert_same(&output, &expected);
+    }
+
+    #[test]
+    fn insert_after_make_the_idname_unique() {
+        let mut output = base();
+        let path = output.insert_after("b", "next", unlinked("b")).unwrap();
+        let socket = output.get_node(&path).unwrap().socket().unwrap();
+        assert_eq!(socket.socket_in()["start"], "b_2");
+        assert_eq!(socket.socket_out()["next"], "c");
+
+        let path = output.insert_after("b", "next", unlinked("")).unwrap();
+        let socket = output.get_node(&path).unwrap().socket().unwrap();
+        assert_eq!(socket.socket_in()["start"], "Scenario");
+        assert_eq!(socket.socket_out()["next"], "b_2");
+    }
+
+    #[test]
+    fn insert_after_errors() {
+        let mut output = base();
+        assert_eq!(
+            output.insert_after("x", "next", unlinked("x")),
+            Err(EditError::UnknownIdname("x".into()))
+        );
+        assert_eq!(
+            output.insert_after("c", "next", unlinked("x")),
+            Err(EditError::UnknownLabel("next".into()))
+        );
+        let declared = serde_json::from_value(scenario("c", "x01", None)).unwrap();
+        assert_eq!(
+            output.insert_after("b", "next", declared),
+            Err(EditError::IdnameExists("c".into()))
+        );
+        assert_same(&output, &base());
+    }
+
+    #[test]
+    fn remove_and_bridge_link_to_the_target() {
+        let mut output = base();
+        let removed = output.remove_and_bridge(&NodePath(vec![0, 1])).unwrap();
+        assert_eq!(removed.debugname(), Some("b"));
+        assert_same(
+            &output,
+            &flow(vec![(
+                "Main",
+                vec![
+                    scenario("$START", "a01", Some("c")),
+                    scenario("c", "c01", None),
+                ],
+            )]),
+        );
+    }
+
+    #[test]
+    fn remove_and_bridge_errors() {
+        let mut output = base();
+        assert_eq!(
+            output.remove_and_bridge(&NodePath(vec![0, 0])).err(),
+            Some(EditError::SpecialIdname("$START".into()))
+        );
+        assert_eq!(
+            output.remove_and_bridge(&NodePath(vec![0, 2])).err(),
+            Some(EditError::NotBridgeable(vec![]))
+        );
+        assert_eq!(
+            output.remove_and_bridge(&NodePath(vec![0, 3])).err(),
+            Some(EditError::UnknownNode(NodePath(vec![0, 3])))
+        );
+        assert_eq!(
+            output.remove_and_bridge(&NodePath(vec![0])).err(),
+            Some(EditError::NoSocket)
+        );
+        assert_same(&output, &base());
+    }
+
+    #[test]
+    fn replace_keep_the_socket() {
+        let mut output = base();
+        let node = serde_json::from_value(scenario("y", "y01", None)).unwrap();
+        let replaced = output.replace(&NodePath(vec![0, 1]), node).unwrap();
+        assert_eq!(replaced.debugname(), Some("b"));
+        let node = serde_json::to_value(output.get_node(&NodePath(vec![0, 1]))).unwrap();
+        assert_eq!(node["Scenario"]["entry"], json!(["y01"]));
+        assert_eq!(node["Scenario"]["debugname"], "y");
+        assert_eq!(
+            node["Scenario"]["socket"],
+            json!({ "socket_in": { "start": "b" }, "socket_out": { "next": "c" } })
+        );
+    }
+
+    #[test]
+    fn rename_idname_update_the_links() {
+        let mut output = base();
+        output
+            .get_follow_mut(&NodePath(vec![0]))
+            .unwrap()
+            .push(serde_json::from_value(scenario("d", "d01", Some("b"))).unwrap());
+        assert_eq!(output.rename_idname("b", "e"), Ok(2));
+        let mut renamed = scenario("e", "b01", Some("c"));
+        renamed["Scenario"]["debugname"] = "b".into();
+        assert_same(
+            &output,
+            &flow(vec![(
+                "Main",
+                vec![
+                    scenario("$START", "a01", Some("e")),
+                    renamed,
+                    scenario("c", "c01", None),
+                    scenario("d", "d01", Some("e")),
+                ],
+            )]),
+        );
+    }
+
+    #[test]
+    fn rename_idname_errors() {
+        let mut output = base();
+        assert_eq!(
+            output.rename_idname("x", "y"),
+            Err(EditError::UnknownIdname("x".into()))
+        );
+        assert_eq!(
+            output.rename_idname("b", "c"),
+            Err(EditError::IdnameExists("c".into()))
+        );
+        assert_eq!(output.rename_idname("b", "b"), Ok(0));
+        assert_same(&output, &base());
+    }
+}
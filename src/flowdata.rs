@@ -8,7 +8,6 @@ use std::io;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::num::TryFromIntError;
 //TODO: ensure comment are also copied
-//TODO: remove all panic!
 
 #[derive(Debug)]
 /// List possible error that can happen while parsing/writing a binary flow file (with [`FlowData`])
@@ -23,7 +22,21 @@ pub enum FlowDataError {
     VecReferenceTooBig(u16, u32),
     UnrecognizedTypeForDic(u16),
     UnrecognizedTypeForVec(u16),
-    Sir0WriteFooterError(Sir0WriteFooterError)
+    Sir0WriteFooterError(Sir0WriteFooterError),
+    NestedVector,
+    RootNotVector(FlowDataValue),
+    UnknownAnchor(String),
+    AnchorCycle(String),
+    Serde(String),
+    EmptyFollow,
+    InvalidFollowEntry(FlowDataValue),
+    InvalidInLabel(String, &'static [&'static str]),
+    InvalidOutLabel(String, &'static [&'static str]),
+    InvalidStringList(FlowDataValue),
+    NoneOutsideField,
+    MissingKey(String),
+    UnexpectedValue(String, &'static str, FlowDataValue),
+    UnorderedDictionaries(u16),
 }
 
 impl Error for FlowDataError {
@@ -50,11 +63,37 @@ impl fmt::Display for FlowDataError {
             Self::VecReferenceTooBig(_pos, _len) => write!(f, "A vec reference is too big (TODO: more debug info)."),
             Self::UnrecognizedTypeForDic(value) => write!(f, "A value for a dic is unreconized (the value is {}).", value),
             Self::UnrecognizedTypeForVec(value) => write!(f, "A value for a vec is unreconized (the value is {}).", value),
-            Self::Sir0WriteFooterError(_) => write!(f, "An error occured writing the sir0 header")
+            Self::Sir0WriteFooterError(_) => write!(f, "An error occured writing the sir0 header"),
+            Self::NestedVector => write!(f, "A vector can't directly contain another vector."),
+            Self::RootNotVector(value) => write!(f, "The root of a flow file should be a vector (found {:?}).", value),
+            Self::UnknownAnchor(name) => write!(f, "The alias {:?} doesn't refer to any previous anchor.", name),
+            Self::AnchorCycle(name) => write!(f, "The alias {:?} refer to one of its parent anchor, which can't be written.", name),
+            Self::Serde(message) => write!(f, "An error happened while (de)serializing a value: {}", message),
+            Self::EmptyFollow => write!(f, "A list of node is empty."),
+            Self::InvalidFollowEntry(value) => write!(f, "An entry of a list of node isn't a dictionary with a single node ({:?}).", value),
+            Self::InvalidInLabel(label, valid) => write!(f, "The incoming link label {:?} isn't accepted by this node (accepted labels: {:?}).", label, valid),
+            Self::InvalidStringList(value) => write!(f, "A value should be a string or a vector of string (found {:?}).", value),
+            Self::NoneOutsideField => write!(f, "A None can only be stored as the value of a struct field or a map entry, where it is left out."),
+            Self::MissingKey(key) => write!(f, "The key {:?} is missing from a dictionary.", key),
+            Self::UnexpectedValue(key, expected, value) => write!(f, "The value of {:?} should be {} (found {:?}).", key, expected, value),
+            Self::UnorderedDictionaries(dic_id) => write!(f, "The dictionary {} is stored before the previous one.", dic_id),
+            Self::InvalidOutLabel(label, valid) => write!(f, "The outgoing link label {:?} isn't accepted by this node (accepted labels: {:?}).", label, valid),
         }
     }
 }
 
+impl serde::ser::Error for FlowDataError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Serde(msg.to_string())
+    }
+}
+
+impl serde::de::Error for FlowDataError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Serde(msg.to_string())
+    }
+}
+
 impl From<io::Error> for FlowDataError {
     fn from(err: io::Error) -> Self {
         Self::IOError(err)
@@ -72,6 +111,15 @@ impl From<Sir0WriteFooterError> for FlowDataError {
     }
 }
 
+/// return the entries of a dictionary, sorted by key
+pub(crate) fn sorted_entries(
+    dic: &HashMap<String, FlowDataValue>,
+) -> Vec<(&String, &FlowDataValue)> {
+    let mut entries: Vec<_> = dic.iter().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    entries
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 /// List possible value for an entry
 pub enum FlowDataValue {
@@ -190,6 +238,22 @@ impl FlowData {
         self.vectors.len()
     }
 
+    /// remove every dictionary and vector added after the [`FlowData`] had `dictionary_len`
+    /// dictionaries and `vector_len` vectors, with the cache that refer to them
+    pub(crate) fn truncate(&mut self, dictionary_len: usize, vector_len: usize) {
+        self.dictionaries.truncate(dictionary_len);
+        self.vectors.truncate(vector_len);
+        let kept = |value: &FlowDataValue| match value {
+            FlowDataValue::RefDic(dicid) => (*dicid as usize) < dictionary_len,
+            _ => true,
+        };
+        self.idnames.retain(|_, parent| kept(parent));
+        self.backlink_dictionaries
+            .retain(|dicid, parent| (*dicid as usize) < dictionary_len && kept(parent));
+        self.backlink_vector
+            .retain(|vecid, parent| (*vecid as usize) < vector_len && kept(parent));
+    }
+
     /// return a reference to a dictionary if it exist, None otherwise
     pub fn get_dictionary(&self, dicid: usize) -> Option<&HashMap<String, FlowDataValue>> {
         if dicid >= self.dictionaries.len() {
@@ -279,7 +343,7 @@ impl FlowData {
             let dic_ptr = read_u32_le(&mut *file)?;
 
             if latest_dict_ptr > dic_ptr {
-                panic!("not well ordered");
+                return Err(FlowDataError::UnorderedDictionaries(dic_id));
             };
             latest_dict_ptr = dic_ptr;
             found_dict_ptr.insert(dic_ptr);
@@ -409,14 +473,15 @@ impl FlowData {
     #[allow(clippy::cognitive_complexity)]
     /// encode a binary flow file
     ///
-    /// note that entry are automatically deduplicated.
+    /// note that entry are automatically deduplicated. The entries of a dictionary are written
+    /// sorted by key, so the same [`FlowData`] is always written to the same bytes.
     pub fn write<T: Write + Seek>(&self, mut file: &mut T) -> Result<(), FlowDataError> {
         //if set to true, it will compare some value (mainly the size of the part) to the file script_flow_data_us.bin in the EU version of the game
         const COMPARE: bool = false;
 
         let mut sir0_pointers = vec![4, 8, 20, 28, 36, 40, 44, 48];
         //write the header. most of the info will be filed at the end
-        file.write_all(&[b'S', b'I', b'R', b'0'])?;
+        file.write_all(b"SIR0")?;
         file.write_all(&[0; 48])?;
 
         let mut unique_data = HashMap::new();
@@ -430,7 +495,7 @@ impl FlowData {
 
         for dicid in 0..self.dictionary_len() {
             let dic = self.get_dictionary(dicid).unwrap();
-            for (key, data) in dic {
+            for (key, data) in sorted_entries(dic) {
                 if !strings.contains_key(key) {
                     strings.insert(key.clone(), strings_vec.len());
                     strings_vec.push(key.clone());
@@ -469,29 +534,29 @@ impl FlowData {
         }
 
         // dictionary metadata
-        let dictionary_meta_offset = file.seek(SeekFrom::Current(0))?;
+        let dictionary_meta_offset = file.stream_position()?;
         if COMPARE {
             assert_eq!(dictionary_meta_offset, 52);
         }
         for _ in 0..self.dictionary_len() {
             file.write_all(&[0; 4])?;
-            sir0_pointers.push(file.seek(SeekFrom::Current(0))? as u32);
+            sir0_pointers.push(file.stream_position()? as u32);
             file.write_all(&[0; 4])?;
         }
 
         // vector metadata
-        let vector_meta_offset = file.seek(SeekFrom::Current(0))?;
+        let vector_meta_offset = file.stream_position()?;
         if COMPARE {
             assert_eq!(vector_meta_offset, 65_172);
         }
         for _ in 0..self.vector_len() {
             file.write_all(&[0; 4])?;
-            sir0_pointers.push(file.seek(SeekFrom::Current(0))? as u32);
+            sir0_pointers.push(file.stream_position()? as u32);
             file.write_all(&[0; 4])?;
         }
 
         // value data (both from dictionary and vector)
-        let values_data_offset = file.seek(SeekFrom::Current(0))?;
+        let values_data_offset = file.stream_position()?;
         if COMPARE {
             assert_eq!(values_data_offset, 79988);
         }
@@ -514,7 +579,7 @@ impl FlowData {
         }
 
         // dic entries
-        let entries_dictionary_offset = file.seek(SeekFrom::Current(0))?;
+        let entries_dictionary_offset = file.stream_position()?;
         if COMPARE {
             assert_eq!(entries_dictionary_offset, 133_244);
         }
@@ -524,7 +589,7 @@ impl FlowData {
         }
 
         //additional information
-        let additional_info_offset = file.seek(SeekFrom::Current(0))?;
+        let additional_info_offset = file.stream_position()?;
         if COMPARE {
             assert_eq!(additional_info_offset, 172_544);
         }
@@ -533,8 +598,8 @@ impl FlowData {
         // The first dictionary entrie... I have no idea why it's here, and not with the other
         let mut dictionary_metadata = Vec::new();
         let dic = self.get_dictionary(0).unwrap();
-        dictionary_metadata.push((file.seek(SeekFrom::Current(0))?, dic.len()));
-        for entry in dic {
+        dictionary_metadata.push((file.stream_position()?, dic.len()));
+        for entry in sorted_entries(dic) {
             file.write_all(&u16::to_le_bytes(
                 unique_entries_dictionary[&entry].try_into()?,
             ))?;
@@ -543,13 +608,13 @@ impl FlowData {
         // same, but with vec
         let mut vector_metadata = Vec::new();
         let vec = self.get_vector(0).unwrap();
-        vector_metadata.push((file.seek(SeekFrom::Current(0))?, vec.len()));
+        vector_metadata.push((file.stream_position()?, vec.len()));
         for entry in vec {
             file.write_all(&u16::to_le_bytes(unique_data[&entry].try_into()?))?;
         }
 
-        assert!(additional_info_offset + 12 >= file.seek(SeekFrom::Current(0)).unwrap());
-        while additional_info_offset + 12 != file.seek(SeekFrom::Current(0)).unwrap() {
+        assert!(additional_info_offset + 12 >= file.stream_position().unwrap());
+        while additional_info_offset + 12 != file.stream_position().unwrap() {
             file.write_all(&[0]).unwrap();
         }
         //pointer to the string -- currently empty
@@ -560,24 +625,24 @@ impl FlowData {
         file.write_all(&[0; 2])?;
 
         // string reference:
-        let strptr_offset = file.seek(SeekFrom::Current(0))?;
+        let strptr_offset = file.stream_position()?;
         if COMPARE {
             assert_eq!(strptr_offset, 172_556);
         }
-        
+
         for _ in 0..strings.len() {
-            sir0_pointers.push(file.seek(SeekFrom::Current(0))? as u32);
+            sir0_pointers.push(file.stream_position()? as u32);
             file.write_all(&[0; 4])?;
         }
 
         // dictionary entries
         if COMPARE {
-            assert_eq!(file.seek(SeekFrom::Current(0))?, 186_060);
+            assert_eq!(file.stream_position()?, 186_060);
         }
         for dicid in 1..self.dictionary_len() {
             let dic = self.get_dictionary(dicid).unwrap();
-            dictionary_metadata.push((file.seek(SeekFrom::Current(0))?, dic.len()));
-            for entry in dic {
+            dictionary_metadata.push((file.stream_position()?, dic.len()));
+            for entry in sorted_entries(dic) {
                 file.write_all(&u16::to_le_bytes(
                     unique_entries_dictionary[&entry].try_into()?,
                 ))?;
@@ -585,13 +650,13 @@ impl FlowData {
         }
 
         // vector entries
-        let vector_list_offset = file.seek(SeekFrom::Current(0))?;
+        let vector_list_offset = file.stream_position()?;
         if COMPARE {
             assert_eq!(vector_list_offset, 233_016);
         }
         for vecid in 1..self.vector_len() {
             let vec = self.get_vector(vecid).unwrap();
-            vector_metadata.push((file.seek(SeekFrom::Current(0))?, vec.len()));
+            vector_metadata.push((file.stream_position()?, vec.len()));
             for entry in vec {
                 file.write_all(&u16::to_le_bytes(unique_data[&entry].try_into()?))?;
             }
@@ -601,20 +666,19 @@ impl FlowData {
         file.write_all(&[0; 2])?;
 
         // strings
-        let string_data_offset = file.seek(SeekFrom::Current(0))?;
+        let string_data_offset = file.stream_position()?;
         if COMPARE {
             assert_eq!(string_data_offset, 243_156);
         }
         let mut string_correspondance: HashMap<String, u32> = HashMap::new();
         for string in &strings_vec {
-            string_correspondance
-                .insert(string.clone(), file.seek(SeekFrom::Current(0))?.try_into()?);
+            string_correspondance.insert(string.clone(), file.stream_position()?.try_into()?);
             file.write_all(string.as_bytes())?;
             file.write_all(&[0])?;
         }
 
         // pointer data
-        let pointer_offset = file.seek(SeekFrom::Current(0))?;
+        let pointer_offset = file.stream_position()?;
 
         // write string reference
         file.seek(SeekFrom::Start(strptr_offset))?;
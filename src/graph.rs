@@ -0,0 +1,389 @@
+use crate::{FlowDataOutput, NodePath};
+use std::collections::HashMap;
+
+/// The idname of the entry point of the story flow, that the game start from
+pub const START_IDNAME: &str = "$START";
+
+/// A node of a [`FlowGraph`]
+#[derive(Debug, Clone)]
+pub struct FlowVertex {
+    /// the position of the node in the [`FlowDataOutput`]
+    pub path: NodePath,
+    /// the key of the node (like `"Scenario"`)
+    pub key: String,
+    /// the debugname of the node
+    pub debugname: String,
+}
+
+/// A link between two nodes of a [`FlowGraph`], created from an entry of `socket_out`
+#[derive(Debug, Clone)]
+pub struct FlowEdge {
+    /// the vertex the link start from
+    pub from: usize,
+    /// the label of the link in the `socket_out` of the origin node (like `next`)
+    pub label: String,
+    /// the vertex the link point to
+    pub to: usize,
+    /// the label of the incoming link in the `socket_in` of the target node (like `start`)
+    pub to_label: String,
+    /// the idname the link point to
+    pub idname: String,
+}
+
+/// An entry of a `socket_out` that point to an idname no node declare
+#[derive(Debug, Clone)]
+pub struct DanglingLink {
+    /// the vertex the link start from
+    pub from: usize,
+    /// the label of the link in the `socket_out` of the origin node
+    pub label: String,
+    /// the idname the link point to
+    pub idname: String,
+}
+
+/// An idname that is declared in the `socket_in` of more than one node
+#[derive(Debug, Clone)]
+pub struct DuplicateIdname {
+    /// the duplicated idname
+    pub idname: String,
+    /// the vertex that first declared it. Links to this idname point to it.
+    pub first: usize,
+    /// the other vertex that declared it
+    pub other: usize,
+}
+
+/// The graph of the nodes of a [`FlowDataOutput`], linked by their sockets.
+///
+/// There is one vertex per node that has a socket (so group aren't vertex). There is an edge for
+/// every entry of a `socket_out`, pointing to the node that declare the same idname in its
+/// `socket_in`.
+#[derive(Debug, Clone)]
+pub struct FlowGraph {
+    vertices: Vec<FlowVertex>,
+    edges: Vec<FlowEdge>,
+    outgoing: Vec<Vec<usize>>,
+    incoming: Vec<Vec<usize>>,
+    by_path: HashMap<NodePath, usize>,
+    idnames: HashMap<String, (usize, String)>,
+    dangling: Vec<DanglingLink>,
+    duplicates: Vec<DuplicateIdname>,
+}
+
+impl FlowGraph {
+    /// Create the graph of a [`FlowDataOutput`]
+    pub fn new(output: &FlowDataOutput) -> Self {
+        let mut vertices = Vec::new();
+        let mut by_path = HashMap::new();
+        let mut idnames: HashMap<String, (usize, String)> = HashMap::new();
+        let mut duplicates = Vec::new();
+        let mut sockets = Vec::new();
+
+        for (path, node) in output.nodes() {
+            let socket = match node.socket() {
+                Some(socket) => socket,
+                None => continue,
+            };
+            let vertex_id = vertices.len();
+            for (label, idname) in socket.socket_in() {
+                match idnames.get(idname) {
+                    Some((first, _)) => duplicates.push(DuplicateIdname {
+                        idname: idname.clone(),
+                        first: *first,
+                        other: vertex_id,
+                    }),
+                    None => {
+                        idnames.insert(idname.clone(), (vertex_id, label.clone()));
+                    }
+                }
+            }
+            by_path.insert(path.clone(), vertex_id);
+            vertices.push(FlowVertex {
+                path,
+                key: node.key().to_string(),
+                debugname: node.debugname().unwrap_or_default().to_string(),
+            });
+            sockets.push(socket);
+        }
+
+        let mut edges = Vec::new();
+        let mut dangling = Vec::new();
+        let mut outgoing = vec![Vec::new(); vertices.len()];
+        let mut incoming = vec![Vec::new(); vertices.len()];
+        for (from, socket) in sockets.iter().enumerate() {
+            for (label, idname) in socket.socket_out() {
+                match idnames.get(idname) {
+                    Some((to, to_label)) => {
+                        outgoing[from].push(edges.len());
+                        incoming[*to].push(edges.len());
+                        edges.push(FlowEdge {
+                            from,
+                            label: label.clone(),
+                            to: *to,
+                            to_label: to_label.clone(),
+                            idname: idname.clone(),
+                        });
+                    }
+                    None => dangling.push(DanglingLink {
+                        from,
+                        label: label.clone(),
+                        idname: idname.clone(),
+                    }),
+                }
+            }
+        }
+
+        Self {
+            vertices,
+            edges,
+            outgoing,
+            incoming,
+            by_path,
+            idnames,
+            dangling,
+            duplicates,
+        }
+    }
+
+    /// return every vertex. The index of a vertex in this slice is its id.
+    pub fn vertices(&self) -> &[FlowVertex] {
+        &self.vertices
+    }
+
+    /// return the vertex `id`
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no vertex with this id
+    pub fn vertex(&self, id: usize) -> &FlowVertex {
+        &self.vertices[id]
+    }
+
+    /// return every edge
+    pub fn edges(&self) -> &[FlowEdge] {
+        &self.edges
+    }
+
+    /// return the id of the vertex of the node at `path`
+    pub fn vertex_by_path(&self, path: &NodePath) -> Option<usize> {
+        self.by_path.get(path).copied()
+    }
+
+    /// return the id of the vertex that declare `idname` in its `socket_in`
+    pub fn vertex_by_idname(&self, idname: &str) -> Option<usize> {
+        self.idnames.get(idname).map(|(vertex, _)| *vertex)
+    }
+
+    /// return the edges that start from `vertex`
+    pub fn successors(&self, vertex: usize) -> impl Iterator<Item = &FlowEdge> {
+        self.outgoing[vertex]
+            .iter()
+            .map(move |edge| &self.edges[*edge])
+    }
+
+    /// return the edges that point to `vertex`
+    pub fn predecessors(&self, vertex: usize) -> impl Iterator<Item = &FlowEdge> {
+        self.incoming[vertex]
+            .iter()
+            .map(move |edge| &self.edges[*edge])
+    }
+
+    /// return the `socket_out` entries that doesn't point to any node
+    pub fn dangling_links(&self) -> &[DanglingLink] {
+        &self.dangling
+    }
+
+    /// return the idnames declared more than once
+    pub fn duplicate_idnames(&self) -> &[DuplicateIdname] {
+        &self.duplicates
+    }
+
+    /// return the vertices that declare the [`START_IDNAME`] idname, where the game start
+    pub fn entry_points(&self) -> Vec<usize> {
+        let mut entries: Vec<usize> = self
+            .duplicates
+            .iter()
+            .filter(|duplicate| duplicate.idname == START_IDNAME)
+            .map(|duplicate| duplicate.other)
+            .chain(self.vertex_by_idname(START_IDNAME))
+            .collect();
+        entries.sort_unstable();
+        entries.dedup();
+        entries
+    }
+
+    /// return, for each vertex, whether it can be reached by following links from `starts`
+    pub fn reachable_from(&self, starts: &[usize]) -> Vec<bool> {
+        let mut reached = vec![false; self.vertices.len()];
+        let mut to_visit = starts.to_vec();
+        while let Some(vertex) = to_visit.pop() {
+            if reached[vertex] {
+                continue;
+            };
+            reached[vertex] = true;
+            to_visit.extend(self.successors(vertex).map(|edge| edge.to));
+        }
+        reached
+    }
+
+    /// return, for each vertex, whether one of `ends` can be reached by following links from it
+    pub fn reaching(&self, ends: &[usize]) -> Vec<bool> {
+        let mut reaching = vec![false; self.vertices.len()];
+        let mut to_visit = ends.to_vec();
+        while let Some(vertex) = to_visit.pop() {
+            if reaching[vertex] {
+                continue;
+            };
+            reaching[vertex] = true;
+            to_visit.extend(self.predecessors(vertex).map(|edge| edge.from));
+        }
+        reaching
+    }
+
+    /// return the strongly connected components of the graph: the groups of vertex that can all
+    /// reach each other. Every vertex is in exactly one component.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        // iterative version of Tarjan's algorithm
+        let count = self.vertices.len();
+        let mut index = vec![usize::MAX; count];
+        let mut lowlink = vec![0; count];
+        let mut on_stack = vec![false; count];
+        let mut stack = Vec::new();
+        let mut components = Vec::new();
+        let mut next_index = 0;
+
+        for root in 0..count {
+            if index[root] != usize::MAX {
+                continue;
+            };
+            // (vertex, position in its outgoing list)
+            let mut call_stack = vec![(root, 0)];
+            index[root] = next_index;
+            lowlink[root] = next_index;
+            next_index += 1;
+            stack.push(root);
+            on_stack[root] = true;
+
+            while let Some((vertex, position)) = call_stack.pop() {
+                if let Some(edge) = self.outgoing[vertex].get(position) {
+                    call_stack.push((vertex, position + 1));
+                    let target = self.edges[*edge].to;
+                    if index[target] == usize::MAX {
+                        index[target] = next_index;
+                        lowlink[target] = next_index;
+                        next_index += 1;
+                        stack.push(target);
+                        on_stack[target] = true;
+                        call_stack.push((target, 0));
+                    } else if on_stack[target] {
+                        lowlink[vertex] = lowlink[vertex].min(index[target]);
+                    }
+                    continue;
+                };
+                if lowlink[vertex] == index[vertex] {
+                    let mut component = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == vertex {
+                            break;
+                        }
+                    }
+                    component.sort_unstable();
+                    components.push(component);
+                }
+                if let Some((parent, _)) = call_stack.last() {
+                    lowlink[*parent] = lowlink[*parent].min(lowlink[vertex]);
+                }
+            }
+        }
+        components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{flow, scenario};
+
+    /// `$START` -> `b` <-> `c`, and `d` in another group, linking to nothing
+    fn graph() -> FlowGraph {
+        FlowGraph::new(&flow(vec![
+            (
+                "Main",
+                vec![
+                    scenario(START_IDNAME, "a01", Some("b")),
+                    scenario("b", "b01", Some("c")),
+                    scenario("c", "c01", Some("b")),
+                ],
+            ),
+            ("Side", vec![scenario("d", "d01", Some("x"))]),
+        ]))
+    }
+
+    #[test]
+    fn vertices_and_edges() {
+        let graph = graph();
+        let paths: Vec<&NodePath> = graph.vertices().iter().map(|vertex| &vertex.path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                &NodePath(vec![0, 0]),
+                &NodePath(vec![0, 1]),
+                &NodePath(vec![0, 2]),
+                &NodePath(vec![1, 0]),
+            ]
+        );
+        assert_eq!(graph.vertex_by_path(&NodePath(vec![1, 0])), Some(3));
+        assert_eq!(graph.vertex_by_path(&NodePath(vec![1])), None);
+        assert_eq!(graph.vertex_by_idname("c"), Some(2));
+
+        let edges: Vec<(usize, usize)> = graph
+            .edges()
+            .iter()
+            .map(|edge| (edge.from, edge.to))
+            .collect();
+        assert_eq!(edges, vec![(0, 1), (1, 2), (2, 1)]);
+        assert_eq!(graph.edges()[0].label, "next");
+        assert_eq!(graph.edges()[0].to_label, "start");
+        assert_eq!(graph.predecessors(1).count(), 2);
+
+        assert_eq!(graph.dangling_links().len(), 1);
+        assert_eq!(graph.dangling_links()[0].from, 3);
+        assert_eq!(graph.dangling_links()[0].idname, "x");
+        assert!(graph.duplicate_idnames().is_empty());
+    }
+
+    #[test]
+    fn reachability() {
+        let graph = graph();
+        assert_eq!(graph.entry_points(), vec![0]);
+        assert_eq!(
+            graph.reachable_from(&graph.entry_points()),
+            vec![true, true, true, false]
+        );
+        assert_eq!(graph.reaching(&[1]), vec![true, true, true, false]);
+        assert_eq!(graph.reaching(&[0]), vec![true, false, false, false]);
+    }
+
+    #[test]
+    fn cycles_are_one_component() {
+        let mut components = graph().strongly_connected_components();
+        components.sort();
+        assert_eq!(components, vec![vec![0], vec![1, 2], vec![3]]);
+    }
+
+    #[test]
+    fn every_start_is_an_entry_point() {
+        let graph = FlowGraph::new(&flow(vec![(
+            "Main",
+            vec![
+                scenario(START_IDNAME, "a01", None),
+                scenario("b", "b01", None),
+                scenario(START_IDNAME, "c01", None),
+            ],
+        )]));
+        assert_eq!(graph.entry_points(), vec![0, 2]);
+        assert_eq!(graph.duplicate_idnames().len(), 1);
+        assert_eq!(graph.duplicate_idnames()[0].first, 0);
+    }
+}
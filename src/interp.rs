@@ -0,0 +1,228 @@
+use crate::{FlowDataOutput, FlowGraph, NodePath, OutputEnum};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The state of the story kept by a [`FlowInterpreter`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FlowState {
+    /// the counters set by [`crate::DgFlowBranchSetCounter`], by identifier. A counter that was
+    /// never set is 0.
+    pub counters: BTreeMap<String, i64>,
+    /// the last non-empty `scenarioProgressNo` of a visited node
+    pub scenario_progress_no: Option<String>,
+}
+
+impl FlowState {
+    /// return the value of the counter `id`
+    pub fn counter(&self, id: &str) -> i64 {
+        self.counters.get(id).copied().unwrap_or(0)
+    }
+}
+
+/// A node visited by a [`FlowInterpreter`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TraceStep {
+    /// the path of the node
+    pub path: NodePath,
+    /// the key of the node (like `"Scenario"`)
+    pub key: String,
+    /// the debugname of the node
+    pub debugname: String,
+    /// the scripts played by this node, if any
+    pub entry: Vec<String>,
+    /// the `socket_in` label the node was entered from, None for the start node
+    pub input: Option<String>,
+    /// the `socket_out` label followed to leave the node, None for the last node
+    pub output: Option<String>,
+}
+
+/// Why a [`FlowInterpreter`] stopped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum StopReason {
+    /// the last node doesn't have any outgoing link
+    Terminal,
+    /// the followed link point to an idname no node declare
+    Dangling(String),
+    /// the choice callback returned None, or a label the node doesn't have
+    NoChoice,
+    /// the maximum number of step was reached
+    MaxSteps,
+}
+
+/// The result of [`FlowInterpreter::run`]
+#[derive(Debug, Clone, Serialize)]
+pub struct Trace {
+    /// the visited nodes, in order
+    pub steps: Vec<TraceStep>,
+    /// why the interpreter stopped
+    pub stop: StopReason,
+    /// the state at the end of the run
+    pub state: FlowState,
+}
+
+/// A decision the interpreter can't take by itself, passed to the choice callback of
+/// [`FlowInterpreter::run`]
+#[derive(Debug)]
+pub struct Choice<'a> {
+    /// the path of the node
+    pub path: &'a NodePath,
+    /// the node that branch
+    pub node: &'a OutputEnum,
+    /// the `socket_out` labels that can be chosen
+    pub labels: Vec<&'a str>,
+    /// the current state
+    pub state: &'a FlowState,
+}
+
+/// Simulate the progression of the story: start at a node, follow its `socket_out` links and keep
+/// the state of the counters and of the `scenarioProgressNo`.
+///
+/// - a [`crate::DgFlowBranchSetCounter`] set its counter `id` to `count`
+/// - a [`crate::DgFlowBranch`] entered from its `reset_zero` input first set its counter to 0. It
+///   then apply its `act` (`inc` or `dec`) to the counter, compare it with `count` using `if`
+///   (like `==` or `<`), and follow `flow_A` if the comparison is true, `flow_B` otherwise
+/// - a node with a single outgoing link follow it
+///
+/// Every other decision (choices, or a branch using a spelling the interpreter doesn't know) is
+/// taken by the callback given to [`FlowInterpreter::run`].
+pub struct FlowInterpreter<'a> {
+    output: &'a FlowDataOutput,
+    graph: FlowGraph,
+    state: FlowState,
+    max_steps: usize,
+}
+
+impl<'a> FlowInterpreter<'a> {
+    /// Create a new interpreter over `output`, with an empty state, stopping after 10000 steps
+    pub fn new(output: &'a FlowDataOutput) -> Self {
+        Self {
+            output,
+            graph: FlowGraph::new(output),
+            state: FlowState::default(),
+            max_steps: 10_000,
+        }
+    }
+
+    /// return this interpreter stopping after visiting `max_steps` nodes
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// return the current state
+    pub fn state(&self) -> &FlowState {
+        &self.state
+    }
+
+    /// return the current state mutably, to set it up before a run
+    pub fn state_mut(&mut self) -> &mut FlowState {
+        &mut self.state
+    }
+
+    /// Take the decision of a branch node, updating the state. Return None if the callback
+    /// should decide.
+    fn evaluate(&mut self, node: &OutputEnum, input: Option<&str>) -> Option<&'static str> {
+        match node {
+            OutputEnum::DgFlowBranchSetCounter(node) => {
+                if let Some(count) = node.count.value() {
+                    self.state.counters.insert(node.id.to_string(), count);
+                };
+                None
+            }
+            OutputEnum::DgFlowBranch(node) => {
+                let id = node.id.to_string();
+                if input == Some("reset_zero") {
+                    self.state.counters.insert(id.clone(), 0);
+                };
+                let value = node.act.apply(self.state.counter(&id))?;
+                self.state.counters.insert(id, value);
+                if node.r#if.compare(value, node.count.value()?)? {
+                    Some("flow_A")
+                } else {
+                    Some("flow_B")
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Run the story from the node at `start` until it stop, calling `choose` to decide which
+    /// `socket_out` label to follow when there is a choice. Return None if there is no node with
+    /// a socket at `start`.
+    ///
+    /// The state is kept between runs.
+    pub fn run<F>(&mut self, start: &NodePath, mut choose: F) -> Option<Trace>
+    where
+        F: FnMut(&Choice<'_>) -> Option<String>,
+    {
+        let mut vertex = self.graph.vertex_by_path(start)?;
+        let mut input: Option<String> = None;
+        let mut steps = Vec::new();
+
+        let stop = loop {
+            if steps.len() >= self.max_steps {
+                break StopReason::MaxSteps;
+            };
+            let path = self.graph.vertex(vertex).path.clone();
+            let node = self.output.get_node(&path)?;
+            let socket = node.socket()?;
+            if let Some(progress) = node.scenario_progress_no() {
+                if !progress.is_empty() {
+                    self.state.scenario_progress_no = Some(progress.to_string());
+                }
+            };
+
+            let decided = self.evaluate(node, input.as_deref());
+            let labels: Vec<&str> = socket.socket_out().keys().map(String::as_str).collect();
+            let label = match (decided, labels.len()) {
+                (_, 0) => None,
+                (Some(label), _) => Some(label.to_string()),
+                (None, 1) => Some(labels[0].to_string()),
+                (None, _) => choose(&Choice {
+                    path: &path,
+                    node,
+                    labels: labels.clone(),
+                    state: &self.state,
+                }),
+            };
+
+            steps.push(TraceStep {
+                key: node.key().to_string(),
+                debugname: node.debugname().unwrap_or_default().to_string(),
+                entry: node.entry().map(<[String]>::to_vec).unwrap_or_default(),
+                path,
+                input: input.take(),
+                output: label.clone(),
+            });
+
+            if labels.is_empty() {
+                break StopReason::Terminal;
+            };
+            let label = match label {
+                Some(label) if labels.contains(&label.as_str()) => label,
+                _ => {
+                    steps.last_mut().unwrap().output = None;
+                    break StopReason::NoChoice;
+                }
+            };
+            let idname = &socket.socket_out()[&label];
+            match self
+                .graph
+                .successors(vertex)
+                .find(|edge| edge.label == label)
+            {
+                Some(edge) => {
+                    input = Some(edge.to_label.clone());
+                    vertex = edge.to;
+                }
+                None => break StopReason::Dangling(idname.clone()),
+            }
+        };
+
+        Some(Trace {
+            steps,
+            stop,
+            state: self.state.clone(),
+        })
+    }
+}
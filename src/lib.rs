@@ -7,10 +7,82 @@
 //! While it can read and write the `script_flow_data_us.bin` file used in this game, this library
 //! isn't finished. It doesn't support different flow file used in this game, and there is many
 //! assertion that crash the program rather than returning Error.
+//!
+//! A decoded story flow ([`FlowDataOutput`]) can be edited directly: every node type has public
+//! fields, a `new` constructor for its main fields, and implement [`Default`] for the others (so
+//! `Scenario { debugname: "intro".into(), ..Scenario::new(entry, socket) }` can be used as a
+//! builder). [`Follow`] can be iterated and modified like a list.
 mod flowdata;
 pub use flowdata::{FlowData, FlowDataError, FlowDataValue};
 
 mod tool;
 
+mod types;
+pub use types::{Comparison, CounterAction, DebugMenuTag, FlowInt, FlowType};
+
 mod output;
-pub use output::FlowDataOutput;
+pub use output::{
+    AskSave, DgFlowBranch, DgFlowBranchSetCounter, DgStagingPost, Dungeon, DungeonEnd, Floor,
+    FlowDataOutput, FlowDataTempory, Follow, FollowGroup, FollowSocket, FreeMove, FreeMoveEvent,
+    Layout, OutputEnum, Scenario, ScenarioWithBranch, ScenarioWithProgNo, Timeline,
+};
+
+mod node;
+pub use node::{CustomNode, FlowNode, FlowNodeBase, NodeRegistry, RawNode, SocketLabels};
+
+mod path;
+pub use path::NodePath;
+
+mod graph;
+pub use graph::{DanglingLink, DuplicateIdname, FlowEdge, FlowGraph, FlowVertex, START_IDNAME};
+
+mod dot;
+pub use dot::DotOptions;
+
+mod lint;
+pub use lint::{LintFinding, LintKind};
+
+mod reach;
+pub use reach::ReachabilityReport;
+
+mod story;
+pub use story::{PathEnd, PathOptions, PathStep, StoryPath, StoryPaths};
+
+mod interp;
+pub use interp::{Choice, FlowInterpreter, FlowState, StopReason, Trace, TraceStep};
+
+mod diff;
+pub use diff::{FieldChange, FlowDiff, LinkChange, LinkDirection, NodeChange, NodeSummary};
+
+mod edit;
+pub use edit::EditError;
+
+mod patch;
+pub use patch::{FlowPatch, NodeRef, PatchConflict, PatchError, PatchOperation};
+
+mod merge;
+pub use merge::{FlowMerge, MergeConflict};
+
+mod query;
+pub use query::{FlowQuery, QueryError};
+
+mod bulk;
+pub use bulk::BulkEdit;
+
+mod project;
+pub use project::ProjectError;
+
+mod text;
+pub use text::TextError;
+
+mod tree;
+pub use tree::FlowTree;
+
+mod ser;
+pub use ser::{to_flowdata, to_flowdata_value, FlowDataSerializer};
+
+mod de;
+pub use de::{from_flowdata, from_flowdata_value, FlowDataDeserializer};
+
+#[cfg(test)]
+mod test_util;
@@ -0,0 +1,384 @@
+use crate::{FlowDataOutput, FlowGraph, NodePath, OutputEnum};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+
+/// The problem found by [`FlowDataOutput::lint`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum LintKind {
+    /// An entry of the `socket_out` point to an idname no node declare
+    DanglingLink {
+        /// the label of the link
+        label: String,
+        /// the idname it point to
+        idname: String,
+    },
+    /// An idname of the `socket_in` was already declared by another node. Links to it will go to
+    /// the first node.
+    DuplicateIdname {
+        /// the duplicated idname
+        idname: String,
+        /// the path of the node that first declared it
+        first: NodePath,
+    },
+    /// No link point to any input of this node
+    NoIncomingLink,
+    /// No link point to this input of the node, while other input are linked
+    UnreachedInput {
+        /// the label of the input
+        label: String,
+        /// the idname of the input
+        idname: String,
+    },
+    /// This [`crate::DgFlowBranch`] read a counter that no [`crate::DgFlowBranchSetCounter`]
+    /// before it set
+    UnsetCounter {
+        /// the counter identifier
+        id: String,
+    },
+    /// The `scenarioProgressNo` of the timeline of this node isn't the one of the node
+    TimelineProgressMismatch {
+        /// the `scenarioProgressNo` of the node
+        node: String,
+        /// the `scenarioProgressNo` of the timeline
+        timeline: String,
+    },
+    /// A field (like `flowtype` or `debugmenu_tag`) has a value this library doesn't know, that may be
+    /// a typo
+    UnknownValue {
+        /// the name of the field, as in the flow file
+        field: String,
+        /// the value of the field
+        value: String,
+    },
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DanglingLink { label, idname } => write!(
+                f,
+                "the output {:?} point to the idname {:?}, that no node declare",
+                label, idname
+            ),
+            Self::DuplicateIdname { idname, first } => write!(
+                f,
+                "the idname {:?} is already declared by the node at {}",
+                idname, first
+            ),
+            Self::NoIncomingLink => write!(f, "no link point to this node"),
+            Self::UnreachedInput { label, idname } => write!(
+                f,
+                "no link point to the input {:?} (idname {:?})",
+                label, idname
+            ),
+            Self::UnsetCounter { id } => write!(
+                f,
+                "the counter {:?} is read, but no node before this one set it",
+                id
+            ),
+            Self::TimelineProgressMismatch { node, timeline } => write!(
+                f,
+                "the timeline is for the progress {:?}, but the node set the progress {:?}",
+                timeline, node
+            ),
+            Self::UnknownValue { field, value } => {
+                write!(f, "the {} {:?} isn't one of the known values", field, value)
+            }
+        }
+    }
+}
+
+/// A problem found by [`FlowDataOutput::lint`] on a node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintFinding {
+    /// the path of the node
+    pub path: NodePath,
+    /// the name of the groups containing the node, from the outermost
+    pub groups: Vec<String>,
+    /// the debugname of the node
+    pub debugname: String,
+    /// the problem found
+    pub kind: LintKind,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for group in &self.groups {
+            write!(f, "{}/", group)?;
+        }
+        write!(f, "{} ({}): {}", self.debugname, self.path, self.kind)
+    }
+}
+
+/// return the name and the value of the fields of `node` that hold a value this library doesn't
+/// know (an `Other` variant)
+fn unknown_values(node: &OutputEnum) -> Vec<(&'static str, &str)> {
+    let mut values = Vec::new();
+    if let Some(tag) = node.debugmenu_tag() {
+        values.push(("debugmenu_tag", tag.is_known(), tag.as_str()));
+    };
+    match node {
+        OutputEnum::Scenario(node) => {
+            values.push(("flowtype", node.flowtype.is_known(), node.flowtype.as_str()))
+        }
+        OutputEnum::Dungeon(node) => {
+            values.push(("flowtype", node.flowtype.is_known(), node.flowtype.as_str()))
+        }
+        _ => (),
+    };
+    values
+        .into_iter()
+        .filter(|(_, known, _)| !known)
+        .map(|(field, _, value)| (field, value))
+        .collect()
+}
+
+/// return true if the idname is a special one, like `$START`, that is referenced by the game
+/// rather than by a node
+pub(crate) fn is_special_idname(idname: &str) -> bool {
+    idname.starts_with('$')
+}
+
+impl FlowDataOutput {
+    /// Check the links between the nodes, and return the problems found.
+    ///
+    /// It report link to an idname no node declare, idname declared twice, node that no link
+    /// point to, input of a node that no link point to, [`crate::DgFlowBranch`] reading a counter
+    /// no upstream node set (or reset with `reset_zero`), [`crate::Timeline`] whose progress isn't
+    /// the one of its node, and fields with a value this library doesn't know (like a `flowtype`
+    /// that isn't a [`crate::FlowType`] variant). Special idnames starting with a `$` (like
+    /// `$START`) are considered to be referenced by the game, and can be declared by several
+    /// nodes.
+    pub fn lint(&self) -> Vec<LintFinding> {
+        let graph = FlowGraph::new(self);
+        let mut findings = Vec::new();
+        let mut finding = |path: &NodePath, kind: LintKind| {
+            findings.push(LintFinding {
+                groups: self
+                    .group_names(path)
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+                debugname: self
+                    .get_node(path)
+                    .and_then(OutputEnum::debugname)
+                    .unwrap_or_default()
+                    .to_string(),
+                path: path.clone(),
+                kind,
+            })
+        };
+
+        for link in graph.dangling_links() {
+            finding(
+                &graph.vertex(link.from).path,
+                LintKind::DanglingLink {
+                    label: link.label.clone(),
+                    idname: link.idname.clone(),
+                },
+            );
+        }
+
+        for duplicate in graph
+            .duplicate_idnames()
+            .iter()
+            .filter(|duplicate| !is_special_idname(&duplicate.idname))
+        {
+            finding(
+                &graph.vertex(duplicate.other).path,
+                LintKind::DuplicateIdname {
+                    idname: duplicate.idname.clone(),
+                    first: graph.vertex(duplicate.first).path.clone(),
+                },
+            );
+        }
+
+        let referenced: HashSet<&str> = graph
+            .edges()
+            .iter()
+            .map(|edge| edge.idname.as_str())
+            .collect();
+        for vertex in graph.vertices() {
+            let socket = match self.get_node(&vertex.path).and_then(|node| node.socket()) {
+                Some(socket) => socket,
+                None => continue,
+            };
+            let unreached: Vec<(&String, &String)> = socket
+                .socket_in()
+                .iter()
+                .filter(|(_, idname)| {
+                    !referenced.contains(idname.as_str()) && !is_special_idname(idname)
+                })
+                .collect();
+            if unreached.len() == socket.socket_in().len() {
+                finding(&vertex.path, LintKind::NoIncomingLink);
+            } else {
+                for (label, idname) in unreached {
+                    finding(
+                        &vertex.path,
+                        LintKind::UnreachedInput {
+                            label: label.clone(),
+                            idname: idname.clone(),
+                        },
+                    );
+                }
+            }
+        }
+
+        for (vertex_id, vertex) in graph.vertices().iter().enumerate() {
+            let id = match self.get_node(&vertex.path) {
+                Some(OutputEnum::DgFlowBranch(branch)) => branch.id.to_string(),
+                _ => continue,
+            };
+            if !self.counter_set_upstream(&graph, vertex_id, &id) {
+                finding(&vertex.path, LintKind::UnsetCounter { id });
+            }
+        }
+
+        for vertex in graph.vertices() {
+            let node = match self.get_node(&vertex.path) {
+                Some(node) => node,
+                None => continue,
+            };
+            if let (Some(progress), Some(timeline)) = (node.scenario_progress_no(), node.timeline())
+            {
+                match &timeline.scenario_progress_no {
+                    Some(timeline_progress) if timeline_progress != progress => finding(
+                        &vertex.path,
+                        LintKind::TimelineProgressMismatch {
+                            node: progress.to_string(),
+                            timeline: timeline_progress.clone(),
+                        },
+                    ),
+                    _ => (),
+                }
+            }
+        }
+
+        for (path, node) in self.nodes() {
+            for (field, value) in unknown_values(node) {
+                finding(
+                    &path,
+                    LintKind::UnknownValue {
+                        field: field.to_string(),
+                        value: value.to_string(),
+                    },
+                );
+            }
+        }
+
+        findings
+    }
+
+    /// return true if a node before `vertex` set the counter `id`, or reset it by linking to the
+    /// `reset_zero` input of a branch using it
+    fn counter_set_upstream(&self, graph: &FlowGraph, vertex: usize, id: &str) -> bool {
+        let mut visited = vec![false; graph.vertices().len()];
+        let mut to_visit = vec![vertex];
+        while let Some(current) = to_visit.pop() {
+            for edge in graph.predecessors(current) {
+                let reset = edge.to_label == "reset_zero"
+                    && matches!(
+                        self.get_node(&graph.vertex(current).path),
+                        Some(OutputEnum::DgFlowBranch(branch)) if branch.id.to_string() == id
+                    );
+                let set = matches!(
+                    self.get_node(&graph.vertex(edge.from).path),
+                    Some(OutputEnum::DgFlowBranchSetCounter(counter)) if counter.id.to_string() == id
+                );
+                if reset || set {
+                    return true;
+                };
+                if !visited[edge.from] {
+                    visited[edge.from] = true;
+                    to_visit.push(edge.from);
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{flow, scenario};
+    use serde_json::Value;
+
+    fn chain(extra: Vec<Value>) -> FlowDataOutput {
+        let mut main = vec![
+            scenario("$START", "a01", Some("b")),
+            scenario("b", "b01", Some("c")),
+            scenario("c", "c01", None),
+        ];
+        main.extend(extra);
+        flow(vec![("Main", main)])
+    }
+
+    fn kinds(flow: &FlowDataOutput) -> Vec<(NodePath, LintKind)> {
+        flow.lint()
+            .into_iter()
+            .map(|finding| (finding.path, finding.kind))
+            .collect()
+    }
+
+    #[test]
+    fn linked_flow_has_no_finding() {
+        assert_eq!(kinds(&chain(vec![])), vec![]);
+    }
+
+    #[test]
+    fn dangling_link_and_unlinked_node() {
+        let flow = chain(vec![scenario("d", "d01", Some("x"))]);
+        assert_eq!(
+            kinds(&flow),
+            vec![
+                (
+                    NodePath(vec![0, 3]),
+                    LintKind::DanglingLink {
+                        label: "next".into(),
+                        idname: "x".into(),
+                    }
+                ),
+                (NodePath(vec![0, 3]), LintKind::NoIncomingLink),
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_idname() {
+        let flow = chain(vec![scenario("b", "b02", None)]);
+        let findings = kinds(&flow);
+        assert!(findings.contains(&(
+            NodePath(vec![0, 3]),
+            LintKind::DuplicateIdname {
+                idname: "b".into(),
+                first: NodePath(vec![0, 1]),
+            }
+        )));
+    }
+
+    #[test]
+    fn special_idname_can_be_declared_twice() {
+        let flow = chain(vec![scenario("$START", "a02", Some("c"))]);
+        assert_eq!(kinds(&flow), vec![]);
+    }
+
+    #[test]
+    fn unknown_flowtype() {
+        let mut node = scenario("d", "d01", None);
+        node["Scenario"]["flowtype"] = "nromal".into();
+        let flow = chain(vec![scenario("$START", "a02", Some("d")), node]);
+        assert_eq!(
+            kinds(&flow),
+            vec![(
+                NodePath(vec![0, 4]),
+                LintKind::UnknownValue {
+                    field: "flowtype".into(),
+                    value: "nromal".into(),
+                }
+            )]
+        );
+    }
+}
@@ -0,0 +1,500 @@
+use crate::diff::match_nodes;
+use crate::patch::{anchor, apply_operation};
+use crate::{
+    FlowDataOutput, FlowPatch, LinkDirection, NodePath, NodeRef, PatchConflict, PatchOperation,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A change of both sides of a [`FlowMerge`] that couldn't be merged
+#[derive(Debug, Clone, Serialize)]
+pub enum MergeConflict {
+    /// both sides changed the same field of a node, to different values
+    Field {
+        /// the node, in the merged flow
+        node: NodeRef,
+        /// the name of the field, as in the JSON
+        field: String,
+        /// the value in the base flow
+        base: Value,
+        /// the value in our flow, kept in the merged flow
+        ours: Value,
+        /// the value in their flow
+        theirs: Value,
+    },
+    /// both sides changed the same socket link of a node, to different idnames
+    Link {
+        /// the node, in the merged flow
+        node: NodeRef,
+        /// whether this is a `socket_in` or a `socket_out` link
+        direction: LinkDirection,
+        /// the label of the link
+        label: String,
+        /// the idname in the base flow
+        base: Option<String>,
+        /// the idname in our flow, kept in the merged flow
+        ours: Option<String>,
+        /// the idname in their flow
+        theirs: Option<String>,
+    },
+    /// their flow deleted (or moved to another group) a node our flow changed. The node is kept.
+    DeletedByTheirs {
+        /// the node, in the merged flow
+        node: NodeRef,
+    },
+    /// a change of their flow couldn't be applied, usually because our flow deleted the node it
+    /// modify
+    Unapplied {
+        /// the change of their flow, relative to the base flow
+        operation: Box<PatchOperation>,
+        /// why it couldn't be applied
+        conflict: PatchConflict,
+    },
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |idname: &Option<String>| match idname {
+            Some(idname) => format!("{:?}", idname),
+            None => "nothing".to_string(),
+        };
+        match self {
+            Self::Field {
+                node,
+                field,
+                base,
+                ours,
+                theirs,
+            } => write!(
+                f,
+                "{}: the field {:?} was changed from {} to {} by ours and to {} by theirs",
+                node, field, base, ours, theirs
+            ),
+            Self::Link {
+                node,
+                direction,
+                label,
+                base,
+                ours,
+                theirs,
+            } => write!(
+                f,
+                "{}: the {} link {:?} was changed from {} to {} by ours and to {} by theirs",
+                node,
+                match direction {
+                    LinkDirection::In => "incoming",
+                    LinkDirection::Out => "outgoing",
+                },
+                label,
+                describe(base),
+                describe(ours),
+                describe(theirs)
+            ),
+            Self::DeletedByTheirs { node } => write!(
+                f,
+                "{}: the node was changed by ours, but deleted or moved by theirs",
+                node
+            ),
+            Self::Unapplied {
+                operation,
+                conflict,
+            } => write!(f, "can't {} from theirs: {}", operation, conflict),
+        }
+    }
+}
+
+/// return the fields of the node at `path`, in the serialized list of node `list`
+fn node_json_mut<'a>(list: &'a mut Value, path: &[usize]) -> Option<&'a mut Map<String, Value>> {
+    let (first, rest) = path.split_first()?;
+    let fields = list
+        .get_mut(*first)?
+        .as_object_mut()?
+        .values_mut()
+        .next()?
+        .as_object_mut()?;
+    if rest.is_empty() {
+        Some(fields)
+    } else {
+        node_json_mut(fields.get_mut("data")?, rest)
+    }
+}
+
+/// return true if the node at `path` has the same key and debugname in both flows
+fn same_node_at(base: &FlowDataOutput, merged: &FlowDataOutput, path: &NodePath) -> bool {
+    match (base.get_node(path), merged.get_node(path)) {
+        (Some(base_node), Some(merged_node)) => {
+            base_node.key() == merged_node.key() && base_node.debugname() == merged_node.debugname()
+        }
+        _ => false,
+    }
+}
+
+/// return the conflict marker put in place of a conflicting value
+fn marker(base: Value, ours: Value, theirs: Value) -> Value {
+    let mut result = Map::new();
+    result.insert("<<<<<<< ours".into(), ours);
+    result.insert("||||||| base".into(), base);
+    result.insert(">>>>>>> theirs".into(), theirs);
+    Value::Object(result)
+}
+
+/// The three-way merge of two flows (ours and theirs) modified from the same base flow.
+///
+/// The changes of their flow are computed as a [`FlowPatch`] from the base flow, and applied on
+/// top of our flow. Nodes are found by their `socket_in` idnames (or their group names), and
+/// followed through the changes of idnames done by our flow. Changes of different nodes, of
+/// different fields or links of a node, or identical changes, are merged. The other changes are
+/// reported as [`MergeConflict`], and our side is kept in the merged flow.
+///
+/// A node that can't be found by idname or group name (like a node without socket) is found by
+/// its position, and a change to it is reported as a conflict if the node at this position in
+/// our flow doesn't have the same key and debugname as in the base flow.
+#[derive(Debug, Clone)]
+pub struct FlowMerge {
+    /// the merged flow, with our side for the conflicts
+    pub merged: FlowDataOutput,
+    /// the changes that couldn't be merged
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl FlowMerge {
+    /// Merge the changes done by `ours` and by `theirs` on `base`
+    pub fn new(base: &FlowDataOutput, ours: &FlowDataOutput, theirs: &FlowDataOutput) -> Self {
+        let mut merge = FlowMerge {
+            merged: ours.clone(),
+            conflicts: Vec::new(),
+        };
+        let (pairs, _, _) = match_nodes(base, ours);
+        let ours_path: HashMap<NodePath, NodePath> = pairs.into_iter().collect();
+
+        for mut operation in FlowPatch::create(base, theirs).operations {
+            if let Some(target) = operation.target_mut() {
+                if let NodeRef::Path(path) = target {
+                    if !same_node_at(base, &merge.merged, path) {
+                        merge.conflicts.push(MergeConflict::Unapplied {
+                            conflict: PatchConflict::NodeNotFound(target.clone()),
+                            operation: Box::new(operation),
+                        });
+                        continue;
+                    }
+                };
+                // follow the node through the idnames changed by our flow
+                if target.resolve(&merge.merged).is_none() {
+                    let found = target
+                        .resolve(base)
+                        .and_then(|path| ours_path.get(&path))
+                        .and_then(|path| anchor(&merge.merged, ours, path));
+                    if let Some(found) = found {
+                        *target = found;
+                    }
+                };
+            };
+
+            if let PatchOperation::DeleteNode { node } = &operation {
+                if let (Some(base_path), Some(merged_path)) =
+                    (node.resolve(base), node.resolve(&merge.merged))
+                {
+                    let base_node = serde_json::to_value(base.get_node(&base_path)).ok();
+                    let merged_node =
+                        serde_json::to_value(merge.merged.get_node(&merged_path)).ok();
+                    if base_node != merged_node {
+                        merge
+                            .conflicts
+                            .push(MergeConflict::DeletedByTheirs { node: node.clone() });
+                        continue;
+                    }
+                }
+            };
+
+            if let Err(conflict) = apply_operation(&mut merge.merged, &operation) {
+                if let Some(conflict) = merge.classify(operation, conflict) {
+                    merge.conflicts.push(conflict);
+                }
+            }
+        }
+        merge
+    }
+
+    /// return the conflict to report for an operation of their flow that failed, or None if our
+    /// flow already did the same change
+    fn classify(
+        &self,
+        operation: PatchOperation,
+        conflict: PatchConflict,
+    ) -> Option<MergeConflict> {
+        match (operation, conflict) {
+            (
+                PatchOperation::SetField {
+                    node,
+                    field,
+                    old,
+                    new,
+                },
+                PatchConflict::FieldMismatch { found, .. },
+            ) => {
+                if found == new {
+                    None
+                } else {
+                    Some(MergeConflict::Field {
+                        node,
+                        field,
+                        base: old,
+                        ours: found,
+                        theirs: new,
+                    })
+                }
+            }
+            (
+                PatchOperation::Rewire {
+                    node,
+                    direction,
+                    label,
+                    old,
+                    new,
+                },
+                PatchConflict::LinkMismatch { found, .. },
+            ) => {
+                if found == new {
+                    None
+                } else {
+                    Some(MergeConflict::Link {
+                        node,
+                        direction,
+                        label,
+                        base: old,
+                        ours: found,
+                        theirs: new,
+                    })
+                }
+            }
+            (PatchOperation::DeleteNode { .. }, PatchConflict::NodeNotFound(_)) => None,
+            (
+                operation @ PatchOperation::InsertAfter { .. },
+                PatchConflict::IdnameExists(idname),
+            )
+            | (
+                operation @ PatchOperation::AddToGroup { .. },
+                PatchConflict::IdnameExists(idname),
+            ) => {
+                let inserted = match &operation {
+                    PatchOperation::InsertAfter { node, .. }
+                    | PatchOperation::AddToGroup { node, .. } => serde_json::to_value(node).ok(),
+                    _ => None,
+                };
+                let existing = NodeRef::Idname(idname.clone())
+                    .resolve(&self.merged)
+                    .and_then(|path| serde_json::to_value(self.merged.get_node(&path)).ok());
+                if inserted == existing {
+                    None
+                } else {
+                    Some(MergeConflict::Unapplied {
+                        operation: Box::new(operation),
+                        conflict: PatchConflict::IdnameExists(idname),
+                    })
+                }
+            }
+            (operation, conflict) => Some(MergeConflict::Unapplied {
+                operation: Box::new(operation),
+                conflict,
+            }),
+        }
+    }
+
+    /// return true if there is no conflict
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    /// Return the merged flow as JSON, with each conflicting field or link replaced by an object
+    /// with the `<<<<<<< ours`, `||||||| base` and `>>>>>>> theirs` values, to be resolved by
+    /// hand. The other conflicts can't be shown this way, and are left out.
+    pub fn to_json_with_markers(&self) -> serde_json::Result<Value> {
+        let mut json = serde_json::to_value(&self.merged)?;
+        for conflict in &self.conflicts {
+            let (node, key, value) = match conflict {
+                MergeConflict::Field {
+                    node,
+                    field,
+                    base,
+                    ours,
+                    theirs,
+                } => (
+                    node,
+                    None,
+                    (field, marker(base.clone(), ours.clone(), theirs.clone())),
+                ),
+                MergeConflict::Link {
+                    node,
+                    direction,
+                    label,
+                    base,
+                    ours,
+                    theirs,
+                } => (
+                    node,
+                    Some(match direction {
+                        LinkDirection::In => "socket_in",
+                        LinkDirection::Out => "socket_out",
+                    }),
+                    (
+                        label,
+                        marker(
+                            serde_json::to_value(base)?,
+                            serde_json::to_value(ours)?,
+                            serde_json::to_value(theirs)?,
+                        ),
+                    ),
+                ),
+                _ => continue,
+            };
+            let path = match node.resolve(&self.merged) {
+                Some(path) => path,
+                None => continue,
+            };
+            let fields = match json
+                .get_mut("output")
+                .and_then(|output| node_json_mut(output, &path.0))
+            {
+                Some(fields) => fields,
+                None => continue,
+            };
+            let fields = match key {
+                Some(key) => match fields
+                    .get_mut("socket")
+                    .and_then(|socket| socket.get_mut(key))
+                    .and_then(Value::as_object_mut)
+                {
+                    Some(links) => links,
+                    None => continue,
+                },
+                None => fields,
+            };
+            fields.insert(value.0.clone(), value.1);
+        }
+        Ok(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, assert_same, scenario};
+    use serde_json::{json, Value};
+
+    /// a flow with the group `Main`, containing the scenarios `a`, `b` and `c` with their entry
+    fn flow(entries: [&str; 3], nexts: [&str; 3]) -> FlowDataOutput {
+        let data: Vec<Value> = ["a", "b", "c"]
+            .iter()
+            .zip(entries.iter().zip(nexts.iter()))
+            .map(|(name, (entry, next))| scenario(name, entry, Some(next)))
+            .collect();
+        test_util::flow(vec![("Main", data)])
+    }
+
+    fn base() -> FlowDataOutput {
+        flow(["a01", "b01", "c01"], ["b", "c", "a"])
+    }
+
+    #[test]
+    fn disjoint_changes_are_merged() {
+        let ours = flow(["a02", "b01", "c01"], ["b", "c", "a"]);
+        let theirs = flow(["a01", "b01", "c02"], ["b", "c", "b"]);
+        let merge = FlowMerge::new(&base(), &ours, &theirs);
+        assert!(merge.is_clean());
+        assert_same(&merge.merged, &flow(["a02", "b01", "c02"], ["b", "c", "b"]));
+    }
+
+    #[test]
+    fn identical_changes_are_merged() {
+        let changed = flow(["a01", "b02", "c01"], ["c", "c", "a"]);
+        let merge = FlowMerge::new(&base(), &changed, &changed);
+        assert!(merge.is_clean());
+        assert_same(&merge.merged, &changed);
+    }
+
+    #[test]
+    fn conflicting_field_keep_ours() {
+        let ours = flow(["a01", "b02", "c01"], ["b", "c", "a"]);
+        let theirs = flow(["a01", "b03", "c01"], ["b", "c", "a"]);
+        let merge = FlowMerge::new(&base(), &ours, &theirs);
+        assert_same(&merge.merged, &ours);
+        assert_eq!(merge.conflicts.len(), 1);
+        match &merge.conflicts[0] {
+            MergeConflict::Field {
+                node,
+                field,
+                base,
+                ours,
+                theirs,
+            } => {
+                assert_eq!(node, &NodeRef::Idname("b".into()));
+                assert_eq!(field, "entry");
+                assert_eq!(base, &json!(["b01"]));
+                assert_eq!(ours, &json!(["b02"]));
+                assert_eq!(theirs, &json!(["b03"]));
+            }
+            other => panic!("unexpected conflict {:?}", other),
+        }
+    }
+
+    #[test]
+    fn conflicting_link_keep_ours() {
+        let ours = flow(["a01", "b01", "c01"], ["c", "c", "a"]);
+        let theirs = flow(["a01", "b01", "c01"], ["a", "c", "a"]);
+        let merge = FlowMerge::new(&base(), &ours, &theirs);
+        assert_same(&merge.merged, &ours);
+        assert_eq!(merge.conflicts.len(), 1);
+        match &merge.conflicts[0] {
+            MergeConflict::Link {
+                node,
+                direction,
+                label,
+                base,
+                ours,
+                theirs,
+            } => {
+                assert_eq!(node, &NodeRef::Idname("a".into()));
+                assert_eq!(*direction, LinkDirection::Out);
+                assert_eq!(label, "next");
+                assert_eq!(base.as_deref(), Some("b"));
+                assert_eq!(ours.as_deref(), Some("c"));
+                assert_eq!(theirs.as_deref(), Some("a"));
+            }
+            other => panic!("unexpected conflict {:?}", other),
+        }
+    }
+
+    #[test]
+    fn markers_replace_the_conflicting_values() {
+        let ours = flow(["a01", "b02", "c01"], ["c", "c", "a"]);
+        let theirs = flow(["a01", "b03", "c01"], ["a", "c", "a"]);
+        let merge = FlowMerge::new(&base(), &ours, &theirs);
+        assert_eq!(merge.conflicts.len(), 2);
+
+        let json = merge.to_json_with_markers().unwrap();
+        let data = &json["output"][0]["FollowGroup"]["data"];
+        assert_eq!(
+            data[0]["Scenario"]["socket"]["socket_out"]["next"],
+            json!({
+                "<<<<<<< ours": "c",
+                "||||||| base": "b",
+                ">>>>>>> theirs": "a",
+            })
+        );
+        assert_eq!(
+            data[1]["Scenario"]["entry"],
+            json!({
+                "<<<<<<< ours": ["b02"],
+                "||||||| base": ["b01"],
+                ">>>>>>> theirs": ["b03"],
+            })
+        );
+        // the other values are the ones of the merged flow
+        assert_eq!(
+            data[2],
+            serde_json::to_value(&ours).unwrap()["output"][0]["FollowGroup"]["data"][2]
+        );
+    }
+}
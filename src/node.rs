@@ -0,0 +1,462 @@
+use crate::output::{
+    AskSave, DgFlowBranch, DgFlowBranchSetCounter, DgStagingPost, Dungeon, DungeonEnd, FollowGroup,
+    FreeMove, FreeMoveEvent, Scenario, ScenarioWithBranch, ScenarioWithProgNo,
+};
+use crate::{
+    FlowData, FlowDataError, FlowDataTempory, FlowDataValue, FlowTree, FollowSocket, OutputEnum,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy)]
+/// The socket labels a node accept. A `*` in a label match any sequence of character.
+pub struct SocketLabels {
+    /// the labels accepted for incoming links (in `socket_in`)
+    pub valid_in: &'static [&'static str],
+    /// the labels accepted for outgoing links (in `socket_out`)
+    pub valid_out: &'static [&'static str],
+}
+
+/// return true if `label` match `pattern`, where a `*` match any sequence of character
+fn label_match(pattern: &str, label: &str) -> bool {
+    match pattern.find('*') {
+        None => pattern == label,
+        Some(star) => {
+            let (prefix, rest) = (&pattern[..star], &pattern[star + 1..]);
+            if !label.starts_with(prefix) {
+                return false;
+            };
+            let label = &label[prefix.len()..];
+            label
+                .char_indices()
+                .map(|(index, _)| index)
+                .chain(std::iter::once(label.len()))
+                .any(|index| label_match(rest, &label[index..]))
+        }
+    }
+}
+
+impl SocketLabels {
+    /// return true if `label` is accepted for an incoming link
+    pub fn accept_in(&self, label: &str) -> bool {
+        self.valid_in
+            .iter()
+            .any(|pattern| label_match(pattern, label))
+    }
+
+    /// return true if `label` is accepted for an outgoing link
+    pub fn accept_out(&self, label: &str) -> bool {
+        self.valid_out
+            .iter()
+            .any(|pattern| label_match(pattern, label))
+    }
+
+    /// check that every link of `socket` has an accepted label
+    pub fn check(&self, socket: &FollowSocket) -> Result<(), FlowDataError> {
+        for label in socket.socket_in().keys() {
+            if !self.accept_in(label) {
+                return Err(FlowDataError::InvalidInLabel(label.clone(), self.valid_in));
+            }
+        }
+        for label in socket.socket_out().keys() {
+            if !self.accept_out(label) {
+                return Err(FlowDataError::InvalidOutLabel(
+                    label.clone(),
+                    self.valid_out,
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A type of node that can be stored in a [`crate::Follow`] (like a scenario or a dungeon).
+///
+/// The node types this library doesn't know about can be implemented outside of this crate, and
+/// added to a [`NodeRegistry`]. They will then be stored as an [`OutputEnum::Custom`].
+pub trait FlowNode: fmt::Debug + FlowNodeBase {
+    /// The key this node is stored with in the flow file (like `"Scenario"`)
+    fn node_key() -> &'static str
+    where
+        Self: Sized;
+
+    /// The socket labels this node accept
+    fn socket_labels() -> SocketLabels
+    where
+        Self: Sized;
+
+    /// Decode this node from the dictionary `dicid` of a [`FlowData`]
+    fn decode(
+        source: &FlowData,
+        tempory: &mut FlowDataTempory,
+        dicid: usize,
+    ) -> Result<Self, FlowDataError>
+    where
+        Self: Sized;
+
+    /// Add this node to a [`FlowData`], returning the id of the created dictionary
+    fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError>;
+
+    /// return the socket of this node, if it has one
+    fn socket(&self) -> Option<&FollowSocket> {
+        None
+    }
+
+    /// return a mutable reference to the socket of this node, if it has one
+    fn socket_mut(&mut self) -> Option<&mut FollowSocket> {
+        None
+    }
+}
+
+/// Helper functions for boxed [`FlowNode`]. It is automatically implemented for every [`FlowNode`]
+/// that is [`Clone`].
+pub trait FlowNodeBase {
+    /// clone this node into a new box
+    fn clone_node(&self) -> Box<dyn FlowNode>;
+    /// return this node as [`Any`], so it can be downcasted
+    fn as_any(&self) -> &dyn Any;
+    /// return this node as a mutable [`Any`], so it can be downcasted
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: FlowNode + Clone + 'static> FlowNodeBase for T {
+    fn clone_node(&self) -> Box<dyn FlowNode> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Clone for Box<dyn FlowNode> {
+    fn clone(&self) -> Self {
+        self.clone_node()
+    }
+}
+
+type DecodeFn = fn(&FlowData, &mut FlowDataTempory, usize) -> Result<OutputEnum, FlowDataError>;
+
+#[derive(Clone, Copy)]
+struct NodeKind {
+    labels: SocketLabels,
+    decode: DecodeFn,
+}
+
+fn decode_custom<N: FlowNode + 'static>(
+    source: &FlowData,
+    tempory: &mut FlowDataTempory,
+    dicid: usize,
+) -> Result<OutputEnum, FlowDataError> {
+    Ok(OutputEnum::Custom(CustomNode::new(N::decode(
+        source, tempory, dicid,
+    )?)))
+}
+
+#[derive(Clone)]
+/// The list of node types that can be decoded from a [`FlowData`].
+///
+/// The default registry contain every node type built in this library. Other node type can be
+/// added with [`NodeRegistry::register`]. Node with a key that isn't registered are decoded as
+/// [`RawNode`].
+pub struct NodeRegistry {
+    kinds: HashMap<String, NodeKind>,
+}
+
+/// register each built in node type, decoded as the [`OutputEnum`] variant of the same name
+macro_rules! register_builtins {
+    ($registry:ident, $($node:ident,)*) => {
+        $(
+            $registry.register_builtin::<$node>(|source, tempory, dicid| {
+                Ok(OutputEnum::$node($node::decode(source, tempory, dicid)?))
+            });
+        )*
+    };
+}
+
+impl Default for NodeRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            kinds: HashMap::new(),
+        };
+        register_builtins!(
+            registry,
+            FollowGroup,
+            ScenarioWithBranch,
+            Scenario,
+            Dungeon,
+            DungeonEnd,
+            AskSave,
+            FreeMove,
+            DgFlowBranchSetCounter,
+            DgFlowBranch,
+            DgStagingPost,
+            ScenarioWithProgNo,
+            FreeMoveEvent,
+        );
+        registry
+    }
+}
+
+impl NodeRegistry {
+    fn register_builtin<N: FlowNode>(&mut self, decode: DecodeFn) {
+        self.kinds.insert(
+            N::node_key().to_string(),
+            NodeKind {
+                labels: N::socket_labels(),
+                decode,
+            },
+        );
+    }
+
+    /// Add a new node type. Node with this key will be decoded as an [`OutputEnum::Custom`]
+    /// containing a `N`. If a node type with the same key is already registered, it is replaced.
+    pub fn register<N: FlowNode + 'static>(&mut self) {
+        self.kinds.insert(
+            N::node_key().to_string(),
+            NodeKind {
+                labels: N::socket_labels(),
+                decode: decode_custom::<N>,
+            },
+        );
+    }
+
+    /// return true if a node type is registered with this key
+    pub fn contains(&self, key: &str) -> bool {
+        self.kinds.contains_key(key)
+    }
+
+    /// return the socket labels of the node type registered with this key
+    pub fn socket_labels(&self, key: &str) -> Option<SocketLabels> {
+        self.kinds.get(key).map(|kind| kind.labels)
+    }
+
+    /// return the key of every registered node type
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.kinds.keys().map(String::as_str)
+    }
+
+    /// Decode the node dictionary `dicid`, that is stored with the key `key`.
+    pub fn decode(
+        &self,
+        key: &str,
+        source: &FlowData,
+        tempory: &mut FlowDataTempory,
+        dicid: usize,
+    ) -> Result<OutputEnum, FlowDataError> {
+        match self.kinds.get(key) {
+            Some(kind) => (kind.decode)(source, tempory, dicid),
+            None => Ok(OutputEnum::Custom(CustomNode::new_raw(
+                key.to_string(),
+                RawNode::decode(source, tempory, dicid)?,
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A node whose type isn't built in this library. It is either a registered [`FlowNode`], or a
+/// [`RawNode`].
+///
+/// It is serialized as its key and a [`FlowTree`] of its content. When deserialized, it is always
+/// a [`RawNode`].
+pub struct CustomNode {
+    key: String,
+    node: Box<dyn FlowNode>,
+}
+
+impl CustomNode {
+    /// Create a new [`CustomNode`] from a [`FlowNode`]
+    pub fn new<N: FlowNode + 'static>(node: N) -> Self {
+        Self {
+            key: N::node_key().to_string(),
+            node: Box::new(node),
+        }
+    }
+
+    /// Create a new [`CustomNode`] from a [`RawNode`], that will be stored with the key `key`
+    pub fn new_raw(key: String, node: RawNode) -> Self {
+        Self {
+            key,
+            node: Box::new(node),
+        }
+    }
+
+    /// return the key this node is stored with in the flow file
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// return the node
+    pub fn node(&self) -> &dyn FlowNode {
+        self.node.as_ref()
+    }
+
+    /// return the node, mutably
+    pub fn node_mut(&mut self) -> &mut dyn FlowNode {
+        self.node.as_mut()
+    }
+
+    /// return a reference to the node if it is a `N`
+    pub fn downcast_ref<N: FlowNode + 'static>(&self) -> Option<&N> {
+        self.node.as_any().downcast_ref()
+    }
+
+    /// return a mutable reference to the node if it is a `N`
+    pub fn downcast_mut<N: FlowNode + 'static>(&mut self) -> Option<&mut N> {
+        self.node.as_any_mut().downcast_mut()
+    }
+
+    /// return the content of this node as a [`FlowTree`]
+    pub fn to_tree(&self) -> Result<FlowTree, FlowDataError> {
+        let mut scratch = FlowData::default();
+        let dicid = self.node.generate(&mut scratch)?;
+        FlowTree::new(&scratch, &FlowDataValue::RefDic(dicid))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CustomNodeSerde {
+    key: String,
+    data: FlowTree,
+}
+
+impl Serialize for CustomNode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        CustomNodeSerde {
+            key: self.key.clone(),
+            data: self.to_tree().map_err(serde::ser::Error::custom)?,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CustomNode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serde_node = CustomNodeSerde::deserialize(deserializer)?;
+        Ok(Self::new_raw(serde_node.key, RawNode::new(serde_node.data)))
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A node of unknown type, stored as a [`FlowTree`].
+///
+/// If the tree contain a `socket` list in the same format as the built-in nodes, it is decoded as
+/// a [`FollowSocket`], and is written back in the tree when generated.
+pub struct RawNode {
+    data: FlowTree,
+    socket: Option<FollowSocket>,
+}
+
+fn unanchor(tree: &FlowTree) -> &FlowTree {
+    match tree {
+        FlowTree::Anchor(_, tree) => unanchor(tree),
+        tree => tree,
+    }
+}
+
+impl RawNode {
+    /// Create a new [`RawNode`] from its content
+    pub fn new(data: FlowTree) -> Self {
+        let socket = unanchor(&data)
+            .get_map()
+            .and_then(|map| map.get("socket"))
+            .and_then(|socket| Self::parse_socket(unanchor(socket)));
+        Self { data, socket }
+    }
+
+    /// return the content of this node. The socket may be outdated, use [`FlowNode::socket`]
+    /// instead.
+    pub fn data(&self) -> &FlowTree {
+        &self.data
+    }
+
+    fn parse_socket(tree: &FlowTree) -> Option<FollowSocket> {
+        let mut socket = FollowSocket::default();
+        for entry in tree.get_list()? {
+            let entry = unanchor(entry).get_map()?;
+            let get = |key: &str| entry.get(key).and_then(|value| value.get_string());
+            if let (Some(idname), Some(label)) = (get("idname"), get("in")) {
+                socket.add_in(label.to_string(), idname.to_string());
+            } else if let (Some(to), Some(label)) = (get("to"), get("out")) {
+                socket.add_out(label.to_string(), to.to_string());
+            } else {
+                return None;
+            }
+        }
+        Some(socket)
+    }
+
+    fn socket_tree(socket: &FollowSocket) -> FlowTree {
+        let mut list = Vec::new();
+        for (label, idname) in socket.socket_in() {
+            let mut entry = BTreeMap::new();
+            entry.insert("idname".to_string(), FlowTree::String(idname.clone()));
+            entry.insert("in".to_string(), FlowTree::String(label.clone()));
+            list.push(FlowTree::Map(entry));
+        }
+        for (label, to) in socket.socket_out() {
+            let mut entry = BTreeMap::new();
+            entry.insert("to".to_string(), FlowTree::String(to.clone()));
+            entry.insert("out".to_string(), FlowTree::String(label.clone()));
+            list.push(FlowTree::Map(entry));
+        }
+        FlowTree::List(list)
+    }
+}
+
+impl FlowNode for RawNode {
+    /// A [`RawNode`] is never registered, its key is stored in its [`CustomNode`]
+    fn node_key() -> &'static str {
+        ""
+    }
+
+    fn socket_labels() -> SocketLabels {
+        SocketLabels {
+            valid_in: &["*"],
+            valid_out: &["*"],
+        }
+    }
+
+    fn decode(
+        source: &FlowData,
+        _tempory: &mut FlowDataTempory,
+        dicid: usize,
+    ) -> Result<Self, FlowDataError> {
+        let dicid = dicid.try_into()?;
+        Ok(Self::new(FlowTree::new(
+            source,
+            &FlowDataValue::RefDic(dicid),
+        )?))
+    }
+
+    fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
+        let data = match (&self.socket, unanchor(&self.data)) {
+            (Some(socket), FlowTree::Map(map)) => {
+                let mut map = map.clone();
+                map.insert("socket".to_string(), Self::socket_tree(socket));
+                FlowTree::Map(map)
+            }
+            _ => self.data.clone(),
+        };
+        match data.generate(dest)? {
+            FlowDataValue::RefDic(dicid) => Ok(dicid),
+            other => Err(FlowDataError::InvalidFollowEntry(other)),
+        }
+    }
+
+    fn socket(&self) -> Option<&FollowSocket> {
+        self.socket.as_ref()
+    }
+
+    fn socket_mut(&mut self) -> Option<&mut FollowSocket> {
+        self.socket.as_mut()
+    }
+}
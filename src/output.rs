@@ -1,8 +1,12 @@
-use crate::{FlowData, FlowDataValue};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-
-#[derive(Serialize, Deserialize, Debug)]
+use crate::{
+    Comparison, CounterAction, CustomNode, DebugMenuTag, FlowData, FlowDataError, FlowDataValue,
+    FlowInt, FlowNode, FlowType, NodeRegistry, SocketLabels,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// A node of a [`Follow`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[allow(clippy::large_enum_variant)]
 pub enum OutputEnum {
     FollowGroup(FollowGroup),
@@ -17,110 +21,426 @@ pub enum OutputEnum {
     DgStagingPost(DgStagingPost),
     ScenarioWithProgNo(ScenarioWithProgNo),
     FreeMoveEvent(FreeMoveEvent),
+    /// A node that isn't built in this library, see [`NodeRegistry`]
+    Custom(CustomNode),
+}
+
+impl OutputEnum {
+    /// return the key this node is stored with in the flow file (like `"Scenario"` or `"Group"`)
+    pub fn key(&self) -> &str {
+        match self {
+            Self::FollowGroup(_) => FollowGroup::node_key(),
+            Self::ScenarioWithBranch(_) => ScenarioWithBranch::node_key(),
+            Self::Scenario(_) => Scenario::node_key(),
+            Self::Dungeon(_) => Dungeon::node_key(),
+            Self::DungeonEnd(_) => DungeonEnd::node_key(),
+            Self::AskSave(_) => AskSave::node_key(),
+            Self::FreeMove(_) => FreeMove::node_key(),
+            Self::DgFlowBranchSetCounter(_) => DgFlowBranchSetCounter::node_key(),
+            Self::DgFlowBranch(_) => DgFlowBranch::node_key(),
+            Self::DgStagingPost(_) => DgStagingPost::node_key(),
+            Self::ScenarioWithProgNo(_) => ScenarioWithProgNo::node_key(),
+            Self::FreeMoveEvent(_) => FreeMoveEvent::node_key(),
+            Self::Custom(custom) => custom.key(),
+        }
+    }
+
+    /// return the socket of this node, if it has one
+    pub fn socket(&self) -> Option<&FollowSocket> {
+        self.as_node().socket()
+    }
+
+    /// return the socket of this node mutably, if it has one
+    pub fn socket_mut(&mut self) -> Option<&mut FollowSocket> {
+        self.as_node_mut().socket_mut()
+    }
+
+    /// return the name of this node, as shown in the debug menu (the group name for a group)
+    pub fn debugname(&self) -> Option<&str> {
+        match self {
+            Self::FollowGroup(node) => Some(&node.debug_groupname),
+            Self::ScenarioWithBranch(node) => Some(&node.debugname),
+            Self::Scenario(node) => Some(&node.debugname),
+            Self::Dungeon(node) => Some(&node.debugname),
+            Self::DungeonEnd(node) => Some(&node.debugname),
+            Self::AskSave(node) => Some(&node.debugname),
+            Self::FreeMove(node) => Some(&node.debugname),
+            Self::DgFlowBranchSetCounter(node) => Some(&node.debugname),
+            Self::DgFlowBranch(node) => Some(&node.debugname),
+            Self::DgStagingPost(node) => Some(&node.debugname),
+            Self::ScenarioWithProgNo(node) => Some(&node.debugname),
+            Self::FreeMoveEvent(node) => Some(&node.debugname),
+            Self::Custom(_) => None,
+        }
+    }
+
+    /// return the layout of this node, if it has one
+    pub fn layout(&self) -> Option<&Layout> {
+        match self {
+            Self::FollowGroup(node) => Some(&node.layout),
+            Self::ScenarioWithBranch(node) => Some(&node.layout),
+            Self::Scenario(node) => Some(&node.layout),
+            Self::Dungeon(node) => Some(&node.layout),
+            Self::DungeonEnd(node) => Some(&node.layout),
+            Self::AskSave(node) => Some(&node.layout),
+            Self::FreeMove(node) => Some(&node.layout),
+            Self::DgFlowBranchSetCounter(node) => Some(&node.layout),
+            Self::DgFlowBranch(node) => Some(&node.layout),
+            Self::DgStagingPost(node) => Some(&node.layout),
+            Self::ScenarioWithProgNo(node) => Some(&node.layout),
+            Self::FreeMoveEvent(node) => Some(&node.layout),
+            Self::Custom(_) => None,
+        }
+    }
+
+    /// return the tag of this node in the debug menu, if it has one
+    pub fn debugmenu_tag(&self) -> Option<&DebugMenuTag> {
+        match self {
+            Self::FollowGroup(node) => Some(&node.debugmenu_tag),
+            Self::ScenarioWithBranch(node) => Some(&node.debugmenu_tag),
+            Self::Scenario(node) => Some(&node.debugmenu_tag),
+            Self::Dungeon(node) => Some(&node.debugmenu_tag),
+            Self::DungeonEnd(node) => Some(&node.debugmenu_tag),
+            Self::AskSave(node) => Some(&node.debugmenu_tag),
+            Self::FreeMove(node) => Some(&node.debugmenu_tag),
+            Self::DgFlowBranchSetCounter(node) => Some(&node.debugmenu_tag),
+            Self::DgFlowBranch(node) => Some(&node.debugmenu_tag),
+            Self::DgStagingPost(node) => Some(&node.debugmenu_tag),
+            Self::ScenarioWithProgNo(node) => Some(&node.debugmenu_tag),
+            Self::FreeMoveEvent(node) => Some(&node.debugmenu_tag),
+            Self::Custom(_) => None,
+        }
+    }
+
+    /// return the scripts this node play (`entry`), if it has some
+    pub fn entry(&self) -> Option<&[String]> {
+        match self {
+            Self::ScenarioWithBranch(node) => Some(&node.entry),
+            Self::Scenario(node) => Some(&node.entry),
+            Self::ScenarioWithProgNo(node) => Some(&node.entry),
+            _ => None,
+        }
+    }
+
+    /// return the timeline of this node, if it has one
+    pub fn timeline(&self) -> Option<&Timeline> {
+        match self {
+            Self::ScenarioWithProgNo(node) => Some(&node.timeline),
+            Self::FreeMove(node) => Some(&node.timeline),
+            Self::DgStagingPost(node) => Some(&node.timeline),
+            _ => None,
+        }
+    }
+
+    /// return the story progress this node set (`scenarioProgressNo`), if it has one
+    pub fn scenario_progress_no(&self) -> Option<&str> {
+        match self {
+            Self::Dungeon(node) => Some(&node.scenario_progress_no),
+            Self::ScenarioWithProgNo(node) => Some(&node.scenario_progress_no),
+            Self::FreeMove(node) => Some(&node.scenario_progress_no),
+            _ => None,
+        }
+    }
+
+    /// return the list of node of this node, if it is a group
+    pub fn children(&self) -> Option<&Follow> {
+        match self {
+            Self::FollowGroup(group) => Some(&group.data),
+            _ => None,
+        }
+    }
+
+    /// return the list of node of this node mutably, if it is a group
+    pub fn children_mut(&mut self) -> Option<&mut Follow> {
+        match self {
+            Self::FollowGroup(group) => Some(&mut group.data),
+            _ => None,
+        }
+    }
+
+    /// return this node as a [`FlowNode`]
+    pub fn as_node(&self) -> &dyn FlowNode {
+        match self {
+            Self::FollowGroup(node) => node,
+            Self::ScenarioWithBranch(node) => node,
+            Self::Scenario(node) => node,
+            Self::Dungeon(node) => node,
+            Self::DungeonEnd(node) => node,
+            Self::AskSave(node) => node,
+            Self::FreeMove(node) => node,
+            Self::DgFlowBranchSetCounter(node) => node,
+            Self::DgFlowBranch(node) => node,
+            Self::DgStagingPost(node) => node,
+            Self::ScenarioWithProgNo(node) => node,
+            Self::FreeMoveEvent(node) => node,
+            Self::Custom(custom) => custom.node(),
+        }
+    }
+
+    /// return this node as a mutable [`FlowNode`]
+    pub fn as_node_mut(&mut self) -> &mut dyn FlowNode {
+        match self {
+            Self::FollowGroup(node) => node,
+            Self::ScenarioWithBranch(node) => node,
+            Self::Scenario(node) => node,
+            Self::Dungeon(node) => node,
+            Self::DungeonEnd(node) => node,
+            Self::AskSave(node) => node,
+            Self::FreeMove(node) => node,
+            Self::DgFlowBranchSetCounter(node) => node,
+            Self::DgFlowBranch(node) => node,
+            Self::DgStagingPost(node) => node,
+            Self::ScenarioWithProgNo(node) => node,
+            Self::FreeMoveEvent(node) => node,
+            Self::Custom(custom) => custom.node_mut(),
+        }
+    }
+}
+
+/// return the dictionary `dicid` of `source`
+fn get_dictionary(
+    source: &FlowData,
+    dicid: usize,
+) -> Result<&HashMap<String, FlowDataValue>, FlowDataError> {
+    source.get_dictionary(dicid).ok_or_else(|| {
+        FlowDataError::DicReferenceTooBig(dicid as u16, source.dictionary_len() as u32)
+    })
+}
+
+/// return the vector `vecid` of `source`
+fn get_vector(source: &FlowData, vecid: usize) -> Result<&Vec<FlowDataValue>, FlowDataError> {
+    source
+        .get_vector(vecid)
+        .ok_or_else(|| FlowDataError::VecReferenceTooBig(vecid as u16, source.vector_len() as u32))
+}
+
+/// return the value of `key` in `dic`
+fn get_entry<'a>(
+    dic: &'a HashMap<String, FlowDataValue>,
+    key: &str,
+) -> Result<&'a FlowDataValue, FlowDataError> {
+    dic.get(key)
+        .ok_or_else(|| FlowDataError::MissingKey(key.to_string()))
+}
+
+/// return the string stored at `key` in `dic`
+fn get_string(dic: &HashMap<String, FlowDataValue>, key: &str) -> Result<String, FlowDataError> {
+    let value = get_entry(dic, key)?;
+    value
+        .get_string()
+        .ok_or_else(|| FlowDataError::UnexpectedValue(key.to_string(), "a string", value.clone()))
+}
+
+/// return the id of the dictionary referenced at `key` in `dic`
+fn get_dicid(dic: &HashMap<String, FlowDataValue>, key: &str) -> Result<usize, FlowDataError> {
+    let value = get_entry(dic, key)?;
+    value.get_dicid().ok_or_else(|| {
+        FlowDataError::UnexpectedValue(key.to_string(), "a dictionary", value.clone())
+    })
+}
+
+/// return the id of the vector referenced at `key` in `dic`
+fn get_vecid(dic: &HashMap<String, FlowDataValue>, key: &str) -> Result<usize, FlowDataError> {
+    let value = get_entry(dic, key)?;
+    value
+        .get_vecid()
+        .ok_or_else(|| FlowDataError::UnexpectedValue(key.to_string(), "a vector", value.clone()))
+}
+
+/// return the strings of the vector `vecid`
+fn get_strings(source: &FlowData, vecid: usize) -> Result<Vec<String>, FlowDataError> {
+    get_vector(source, vecid)?
+        .iter()
+        .map(|value| {
+            value
+                .get_string()
+                .ok_or_else(|| FlowDataError::InvalidStringList(value.clone()))
+        })
+        .collect()
+}
+
+/// Decode a value that is either a string, or a vector of string. Also return true if it was a
+/// vector.
+fn decode_string_list(
+    source: &FlowData,
+    value: &FlowDataValue,
+) -> Result<(Vec<String>, bool), FlowDataError> {
+    match value {
+        FlowDataValue::String(string) => Ok((vec![string.clone()], false)),
+        FlowDataValue::RefVec(vecid) => Ok((get_strings(source, *vecid as usize)?, true)),
+        other => Err(FlowDataError::InvalidStringList(other.clone())),
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Encode a list of string, as a single string if there is one value and `in_a_vec` is false, or
+/// as a vector otherwise
+fn generate_string_list(
+    dest: &mut FlowData,
+    values: &[String],
+    in_a_vec: bool,
+) -> Result<FlowDataValue, FlowDataError> {
+    if values.len() == 1 && !in_a_vec {
+        Ok(FlowDataValue::String(values[0].clone()))
+    } else {
+        let vec = values.iter().cloned().map(FlowDataValue::String).collect();
+        Ok(FlowDataValue::RefVec(dest.push_vector(vec)?))
+    }
+}
+
+/// Deserialize a list of string, also accepting a single string (as written by older version)
+fn deserialize_string_list<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        String(String),
+        List(Vec<String>),
+    }
+    Ok(match StringOrList::deserialize(deserializer)? {
+        StringOrList::String(string) => vec![string],
+        StringOrList::List(list) => list,
+    })
+}
+
+/// The floor links of a [`Dungeon`]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Floor {
-    r#in: String,
-    out: String,
-    out_in_a_vec: bool,
+    /// the `in` value
+    pub r#in: String,
+    /// the `out` values
+    #[serde(deserialize_with = "deserialize_string_list")]
+    pub out: Vec<String>,
+    /// true if `out` is stored in a vector rather than as a string. It is always stored in a
+    /// vector if there isn't exactly one value.
+    #[serde(default)]
+    pub out_in_a_vec: bool,
 }
 
 impl Floor {
-    fn new(source: &FlowData, dicid: usize) -> Self {
-        let dic = source.get_dictionary(dicid).unwrap();
-        let out = &dic["out"];
-        let r#in = dic["in"].get_string().unwrap();
-        let out_in_a_vec;
-        let out = match out {
-            FlowDataValue::String(str) => {
-                out_in_a_vec = false;
-                str.clone()
-            }
-            FlowDataValue::RefVec(vecid) => {
-                out_in_a_vec = true;
-                let vec = source.get_vector(*vecid as usize).unwrap();
-                vec[0].get_string().unwrap()
-            }
-            _ => panic!(),
-        };
-
+    /// Create a new [`Floor`]
+    pub fn new(r#in: String, out: Vec<String>) -> Self {
         Self {
             r#in,
             out,
-            out_in_a_vec,
+            out_in_a_vec: false,
         }
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    /// decode the floor dictionary `dicid`. Return an error if `in` or `out` is missing.
+    pub fn decode(source: &FlowData, dicid: usize) -> Result<Self, FlowDataError> {
+        let dic = get_dictionary(source, dicid)?;
+        let r#in = get_string(dic, "in")?;
+        let (out, out_in_a_vec) = decode_string_list(source, get_entry(dic, "out")?)?;
+
+        Ok(Self {
+            r#in,
+            out,
+            out_in_a_vec,
+        })
+    }
+
+    /// add this floor to a [`FlowData`], returning the id of the created dictionary
+    pub fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
         let mut dic = HashMap::new();
         dic.insert("in".into(), FlowDataValue::String(self.r#in.clone()));
         dic.insert(
             "out".into(),
-            if self.out_in_a_vec {
-                let vec = vec![FlowDataValue::String(self.out.clone())];
-                FlowDataValue::RefVec(dest.push_vector(vec).unwrap())
-            } else {
-                FlowDataValue::String(self.out.clone())
-            },
+            generate_string_list(dest, &self.out, self.out_in_a_vec)?,
         );
-        dest.push_dictionary(dic).unwrap()
+        dest.push_dictionary(dic)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A node that make the player explore a dungeon
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Dungeon {
-    flowtype: String,
+    /// the type of flow (like `normal`)
+    pub flowtype: FlowType,
+    /// a free comment, left by the developers
     #[serde(default)]
-    comment: String,
-    scenario_progress_no: String,
-    socket: FollowSocket,
-    party: Option<Vec<String>>,
-    fixed_party_label: String,
-    dungeon: String,
+    pub comment: String,
+    /// the scenario progress number (`scenarioProgressNo`)
+    pub scenario_progress_no: String,
+    /// the incoming and outgoing links of this node
+    pub socket: FollowSocket,
+    /// the party members, if the party is set by this node
+    pub party: Option<Vec<String>>,
+    /// the label of the fixed party
+    pub fixed_party_label: String,
+    /// the dungeon to enter
+    pub dungeon: String,
+    /// the position of this node in the debug editor
     #[serde(default)]
-    layout: Layout,
-    floor: Floor,
+    pub layout: Layout,
+    /// the floors links of this dungeon
+    pub floor: Floor,
+    /// the name of this node, as shown in the debug menu
     #[serde(default)]
-    debugname: String,
+    pub debugname: String,
+    /// the tag of this node in the debug menu (like `system`)
     #[serde(default)]
-    debugmenu_tag: String,
+    pub debugmenu_tag: DebugMenuTag,
 }
 
 impl Dungeon {
-    fn new(source: &FlowData, tempory: &mut FlowDataTempory, dicid: usize) -> Self {
-        let dic = source.get_dictionary(dicid).unwrap();
-        let flowtype = dic["flowtype"].get_string().unwrap();
-        let comment = dic["comment"].get_string().unwrap();
-        let scenario_progress_no = dic["scenarioProgressNo"].get_string().unwrap();
-        let socket = FollowSocket::new(
+    /// Create a new [`Dungeon`] node, that enter `dungeon`.
+    pub fn new(dungeon: String, socket: FollowSocket) -> Self {
+        Self {
+            dungeon,
+            socket,
+            ..Self::default()
+        }
+    }
+}
+
+impl FlowNode for Dungeon {
+    fn node_key() -> &'static str {
+        "Dungeon"
+    }
+
+    fn socket_labels() -> SocketLabels {
+        SocketLabels {
+            valid_in: &["start"],
+            valid_out: &["SEL_*", "next", "ok", "repeat", "select*"],
+        }
+    }
+
+    fn decode(
+        source: &FlowData,
+        tempory: &mut FlowDataTempory,
+        dicid: usize,
+    ) -> Result<Self, FlowDataError> {
+        let dic = get_dictionary(source, dicid)?;
+        let flowtype = get_string(dic, "flowtype")?.into();
+        let comment = get_string(dic, "comment")?;
+        let scenario_progress_no = get_string(dic, "scenarioProgressNo")?;
+        let socket = FollowSocket::decode(
             source,
             tempory,
-            dic["socket"].get_vecid().unwrap(),
+            get_vecid(dic, "socket")?,
             dicid,
-            &["start"],
-            &["SEL_*", "next", "ok", "repeat", "select*"],
-        );
-        let party = match dic["party"] {
+            &Self::socket_labels(),
+        )?;
+        let party = match get_entry(dic, "party")? {
             FlowDataValue::String(_) => None,
-            FlowDataValue::RefVec(vecid) => Some(
-                source
-                    .get_vector(vecid as usize)
-                    .unwrap()
-                    .iter()
-                    .map(|x| x.get_string().unwrap())
-                    .collect(),
-            ),
-            _ => panic!(),
+            FlowDataValue::RefVec(vecid) => Some(get_strings(source, *vecid as usize)?),
+            other => {
+                return Err(FlowDataError::UnexpectedValue(
+                    "party".to_string(),
+                    "a string or a vector",
+                    other.clone(),
+                ))
+            }
         };
-        let fixed_party_label = dic["fixed_party_label"].get_string().unwrap();
-        let dungeon = dic["dungeon"].get_string().unwrap();
-        let layout = Layout::new(source, dic["layout"].get_dicid().unwrap());
-        let floor = Floor::new(source, dic["floor"].get_dicid().unwrap());
-        let debugname = dic["debugname"].get_string().unwrap();
-        let debugmenu_tag = dic["debugmenu_tag"].get_string().unwrap();
-
-        Self {
+        let fixed_party_label = get_string(dic, "fixed_party_label")?;
+        let dungeon = get_string(dic, "dungeon")?;
+        let layout = Layout::decode(source, get_dicid(dic, "layout")?)?;
+        let floor = Floor::decode(source, get_dicid(dic, "floor")?)?;
+        let debugname = get_string(dic, "debugname")?;
+        let debugmenu_tag = get_string(dic, "debugmenu_tag")?.into();
+
+        Ok(Self {
             flowtype,
             comment,
             scenario_progress_no,
@@ -132,14 +452,14 @@ impl Dungeon {
             floor,
             debugname,
             debugmenu_tag,
-        }
+        })
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
         let mut dic = HashMap::new();
         dic.insert(
             "flowtype".into(),
-            FlowDataValue::String(self.flowtype.clone()),
+            FlowDataValue::String(self.flowtype.to_string()),
         );
         dic.insert(
             "comment".into(),
@@ -151,7 +471,7 @@ impl Dungeon {
         );
         dic.insert(
             "socket".into(),
-            FlowDataValue::RefVec(self.socket.generate(dest)),
+            FlowDataValue::RefVec(self.socket.generate(dest, &Self::socket_labels())?),
         );
         dic.insert(
             "party".into(),
@@ -162,7 +482,7 @@ impl Dungeon {
                     for party in parties {
                         party_vec.push(FlowDataValue::String(party.clone()));
                     }
-                    FlowDataValue::RefVec(dest.push_vector(party_vec).unwrap())
+                    FlowDataValue::RefVec(dest.push_vector(party_vec)?)
                 }
             },
         );
@@ -176,11 +496,11 @@ impl Dungeon {
         );
         dic.insert(
             "layout".into(),
-            FlowDataValue::RefDic(self.layout.generate(dest)),
+            FlowDataValue::RefDic(self.layout.generate(dest)?),
         );
         dic.insert(
             "floor".into(),
-            FlowDataValue::RefDic(self.floor.generate(dest)),
+            FlowDataValue::RefDic(self.floor.generate(dest)?),
         );
         dic.insert(
             "debugname".into(),
@@ -188,54 +508,94 @@ impl Dungeon {
         );
         dic.insert(
             "debugmenu_tag".into(),
-            FlowDataValue::String(self.debugmenu_tag.clone()),
+            FlowDataValue::String(self.debugmenu_tag.to_string()),
         );
-        dest.push_dictionary(dic).unwrap()
+        dest.push_dictionary(dic)
+    }
+
+    fn socket(&self) -> Option<&FollowSocket> {
+        Some(&self.socket)
+    }
+
+    fn socket_mut(&mut self) -> Option<&mut FollowSocket> {
+        Some(&mut self.socket)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A node that ask the player to save
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct AskSave {
+    /// a free comment, left by the developers
     #[serde(default)]
-    comment: String,
-    r#type: String,
-    socket: FollowSocket,
+    pub comment: String,
+    /// the type of save prompt
+    pub r#type: String,
+    /// the incoming and outgoing links of this node
+    pub socket: FollowSocket,
+    /// the position of this node in the debug editor
     #[serde(default)]
-    layout: Layout,
+    pub layout: Layout,
+    /// the name of this node, as shown in the debug menu
     #[serde(default)]
-    debugname: String,
+    pub debugname: String,
+    /// the tag of this node in the debug menu (like `system`)
     #[serde(default)]
-    debugmenu_tag: String,
+    pub debugmenu_tag: DebugMenuTag,
 }
 
 impl AskSave {
-    fn new(source: &FlowData, tempory: &mut FlowDataTempory, dicid: usize) -> Self {
-        let dic = source.get_dictionary(dicid).unwrap();
-        let r#type = dic["type"].get_string().unwrap();
-        let comment = dic["comment"].get_string().unwrap();
-        let socket = FollowSocket::new(
+    /// Create a new [`AskSave`] node, with the given save prompt type.
+    pub fn new(r#type: String, socket: FollowSocket) -> Self {
+        Self {
+            r#type,
+            socket,
+            ..Self::default()
+        }
+    }
+}
+
+impl FlowNode for AskSave {
+    fn node_key() -> &'static str {
+        "AskSave"
+    }
+
+    fn socket_labels() -> SocketLabels {
+        SocketLabels {
+            valid_in: &["in"],
+            valid_out: &["out"],
+        }
+    }
+
+    fn decode(
+        source: &FlowData,
+        tempory: &mut FlowDataTempory,
+        dicid: usize,
+    ) -> Result<Self, FlowDataError> {
+        let dic = get_dictionary(source, dicid)?;
+        let r#type = get_string(dic, "type")?;
+        let comment = get_string(dic, "comment")?;
+        let socket = FollowSocket::decode(
             source,
             tempory,
-            dic["socket"].get_vecid().unwrap(),
+            get_vecid(dic, "socket")?,
             dicid,
-            &["in"],
-            &["out"],
-        );
-        let layout = Layout::new(source, dic["layout"].get_dicid().unwrap());
-        let debugname = dic["debugname"].get_string().unwrap();
-        let debugmenu_tag = dic["debugmenu_tag"].get_string().unwrap();
+            &Self::socket_labels(),
+        )?;
+        let layout = Layout::decode(source, get_dicid(dic, "layout")?)?;
+        let debugname = get_string(dic, "debugname")?;
+        let debugmenu_tag = get_string(dic, "debugmenu_tag")?.into();
 
-        Self {
+        Ok(Self {
             comment,
             r#type,
             socket,
             layout,
             debugname,
             debugmenu_tag,
-        }
+        })
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
         let mut dic = HashMap::new();
 
         dic.insert("type".into(), FlowDataValue::String(self.r#type.clone()));
@@ -245,11 +605,11 @@ impl AskSave {
         );
         dic.insert(
             "socket".into(),
-            FlowDataValue::RefVec(self.socket.generate(dest)),
+            FlowDataValue::RefVec(self.socket.generate(dest, &Self::socket_labels())?),
         );
         dic.insert(
             "layout".into(),
-            FlowDataValue::RefDic(self.layout.generate(dest)),
+            FlowDataValue::RefDic(self.layout.generate(dest)?),
         );
         dic.insert(
             "debugname".into(),
@@ -257,54 +617,94 @@ impl AskSave {
         );
         dic.insert(
             "debugmenu_tag".into(),
-            FlowDataValue::String(self.debugmenu_tag.clone()),
+            FlowDataValue::String(self.debugmenu_tag.to_string()),
         );
-        dest.push_dictionary(dic).unwrap()
+        dest.push_dictionary(dic)
+    }
+
+    fn socket(&self) -> Option<&FollowSocket> {
+        Some(&self.socket)
+    }
+
+    fn socket_mut(&mut self) -> Option<&mut FollowSocket> {
+        Some(&mut self.socket)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A free move event node
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct FreeMoveEvent {
+    /// a free comment, left by the developers
     #[serde(default)]
-    comment: String,
-    socket: FollowSocket,
-    event_type: String,
+    pub comment: String,
+    /// the incoming and outgoing links of this node
+    pub socket: FollowSocket,
+    /// the type of event (`eventType`)
+    pub event_type: String,
+    /// the position of this node in the debug editor
     #[serde(default)]
-    layout: Layout,
+    pub layout: Layout,
+    /// the name of this node, as shown in the debug menu
     #[serde(default)]
-    debugname: String,
+    pub debugname: String,
+    /// the tag of this node in the debug menu (like `system`)
     #[serde(default)]
-    debugmenu_tag: String,
+    pub debugmenu_tag: DebugMenuTag,
 }
 
 impl FreeMoveEvent {
-    fn new(source: &FlowData, tempory: &mut FlowDataTempory, dicid: usize) -> Self {
-        let dic = source.get_dictionary(dicid).unwrap();
-        let comment = dic["comment"].get_string().unwrap();
-        let socket = FollowSocket::new(
+    /// Create a new [`FreeMoveEvent`] node, with the given event type.
+    pub fn new(event_type: String, socket: FollowSocket) -> Self {
+        Self {
+            event_type,
+            socket,
+            ..Self::default()
+        }
+    }
+}
+
+impl FlowNode for FreeMoveEvent {
+    fn node_key() -> &'static str {
+        "FreeMoveEvent"
+    }
+
+    fn socket_labels() -> SocketLabels {
+        SocketLabels {
+            valid_in: &["in"],
+            valid_out: &["out"],
+        }
+    }
+
+    fn decode(
+        source: &FlowData,
+        tempory: &mut FlowDataTempory,
+        dicid: usize,
+    ) -> Result<Self, FlowDataError> {
+        let dic = get_dictionary(source, dicid)?;
+        let comment = get_string(dic, "comment")?;
+        let socket = FollowSocket::decode(
             source,
             tempory,
-            dic["socket"].get_vecid().unwrap(),
+            get_vecid(dic, "socket")?,
             dicid,
-            &["in"],
-            &["out"],
-        );
-        let event_type = dic["eventType"].get_string().unwrap();
-        let layout = Layout::new(source, dic["layout"].get_dicid().unwrap());
-        let debugname = dic["debugname"].get_string().unwrap();
-        let debugmenu_tag = dic["debugmenu_tag"].get_string().unwrap();
-
-        Self {
+            &Self::socket_labels(),
+        )?;
+        let event_type = get_string(dic, "eventType")?;
+        let layout = Layout::decode(source, get_dicid(dic, "layout")?)?;
+        let debugname = get_string(dic, "debugname")?;
+        let debugmenu_tag = get_string(dic, "debugmenu_tag")?.into();
+
+        Ok(Self {
             comment,
             socket,
             event_type,
             layout,
             debugname,
             debugmenu_tag,
-        }
+        })
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
         let mut dic = HashMap::new();
         dic.insert(
             "comment".into(),
@@ -312,7 +712,7 @@ impl FreeMoveEvent {
         );
         dic.insert(
             "socket".into(),
-            FlowDataValue::RefVec(self.socket.generate(dest)),
+            FlowDataValue::RefVec(self.socket.generate(dest, &Self::socket_labels())?),
         );
         dic.insert(
             "eventType".into(),
@@ -320,7 +720,7 @@ impl FreeMoveEvent {
         );
         dic.insert(
             "layout".into(),
-            FlowDataValue::RefDic(self.layout.generate(dest)),
+            FlowDataValue::RefDic(self.layout.generate(dest)?),
         );
         dic.insert(
             "debugname".into(),
@@ -328,51 +728,89 @@ impl FreeMoveEvent {
         );
         dic.insert(
             "debugmenu_tag".into(),
-            FlowDataValue::String(self.debugmenu_tag.clone()),
+            FlowDataValue::String(self.debugmenu_tag.to_string()),
         );
-        dest.push_dictionary(dic).unwrap()
+        dest.push_dictionary(dic)
+    }
+
+    fn socket(&self) -> Option<&FollowSocket> {
+        Some(&self.socket)
+    }
+
+    fn socket_mut(&mut self) -> Option<&mut FollowSocket> {
+        Some(&mut self.socket)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A node that end a dungeon
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct DungeonEnd {
+    /// a free comment, left by the developers
     #[serde(default)]
-    comment: String,
-    socket: FollowSocket,
+    pub comment: String,
+    /// the incoming and outgoing links of this node
+    pub socket: FollowSocket,
+    /// the position of this node in the debug editor
     #[serde(default)]
-    layout: Layout,
+    pub layout: Layout,
+    /// the name of this node, as shown in the debug menu
     #[serde(default)]
-    debugname: String,
+    pub debugname: String,
+    /// the tag of this node in the debug menu (like `system`)
     #[serde(default)]
-    debugmenu_tag: String,
+    pub debugmenu_tag: DebugMenuTag,
 }
 
 impl DungeonEnd {
-    fn new(source: &FlowData, tempory: &mut FlowDataTempory, dicid: usize) -> Self {
-        let dic = source.get_dictionary(dicid).unwrap();
-        let comment = dic["comment"].get_string().unwrap();
-        let socket = FollowSocket::new(
+    /// Create a new [`DungeonEnd`] node.
+    pub fn new(socket: FollowSocket) -> Self {
+        Self {
+            socket,
+            ..Self::default()
+        }
+    }
+}
+
+impl FlowNode for DungeonEnd {
+    fn node_key() -> &'static str {
+        "DungeonEnd"
+    }
+
+    fn socket_labels() -> SocketLabels {
+        SocketLabels {
+            valid_in: &["in"],
+            valid_out: &["out"],
+        }
+    }
+
+    fn decode(
+        source: &FlowData,
+        tempory: &mut FlowDataTempory,
+        dicid: usize,
+    ) -> Result<Self, FlowDataError> {
+        let dic = get_dictionary(source, dicid)?;
+        let comment = get_string(dic, "comment")?;
+        let socket = FollowSocket::decode(
             source,
             tempory,
-            dic["socket"].get_vecid().unwrap(),
+            get_vecid(dic, "socket")?,
             dicid,
-            &["in"],
-            &["out"],
-        );
-        let layout = Layout::new(source, dic["layout"].get_dicid().unwrap());
-        let debugname = dic["debugname"].get_string().unwrap();
-        let debugmenu_tag = dic["debugmenu_tag"].get_string().unwrap();
+            &Self::socket_labels(),
+        )?;
+        let layout = Layout::decode(source, get_dicid(dic, "layout")?)?;
+        let debugname = get_string(dic, "debugname")?;
+        let debugmenu_tag = get_string(dic, "debugmenu_tag")?.into();
 
-        Self {
+        Ok(Self {
             comment,
             socket,
             layout,
             debugname,
             debugmenu_tag,
-        }
+        })
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
         let mut dic = HashMap::new();
         dic.insert(
             "comment".into(),
@@ -380,11 +818,11 @@ impl DungeonEnd {
         );
         dic.insert(
             "socket".into(),
-            FlowDataValue::RefVec(self.socket.generate(dest)),
+            FlowDataValue::RefVec(self.socket.generate(dest, &Self::socket_labels())?),
         );
         dic.insert(
             "layout".into(),
-            FlowDataValue::RefDic(self.layout.generate(dest)),
+            FlowDataValue::RefDic(self.layout.generate(dest)?),
         );
         dic.insert(
             "debugname".into(),
@@ -392,42 +830,81 @@ impl DungeonEnd {
         );
         dic.insert(
             "debugmenu_tag".into(),
-            FlowDataValue::String(self.debugmenu_tag.clone()),
+            FlowDataValue::String(self.debugmenu_tag.to_string()),
         );
-        dest.push_dictionary(dic).unwrap()
+        dest.push_dictionary(dic)
+    }
+
+    fn socket(&self) -> Option<&FollowSocket> {
+        Some(&self.socket)
+    }
+
+    fn socket_mut(&mut self) -> Option<&mut FollowSocket> {
+        Some(&mut self.socket)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A named group of node, stored with the key `Group`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct FollowGroup {
-    debug_groupname: String,
-    data: Follow,
+    /// the name of this group, as shown in the debug menu
+    pub debug_groupname: String,
+    /// the nodes contained in this group
+    pub data: Follow,
+    /// the position of this node in the debug editor
     #[serde(default)]
-    layout: Layout,
+    pub layout: Layout,
+    /// the tag of this node in the debug menu (like `system`)
     #[serde(default)]
-    debugmenu_tag: String,
+    pub debugmenu_tag: DebugMenuTag,
 }
 
 impl FollowGroup {
-    fn new(source: &FlowData, tempory: &mut FlowDataTempory, dicid: usize) -> Self {
-        let dic = source.get_dictionary(dicid).unwrap();
-        let debug_groupname = dic["debug_groupname"].get_string().unwrap();
-        let data = Follow::new(
+    /// Create a new [`FollowGroup`] named `debug_groupname`, containing `data`.
+    pub fn new(debug_groupname: String, data: Follow) -> Self {
+        Self {
+            debug_groupname,
+            data,
+            ..Self::default()
+        }
+    }
+}
+
+impl FlowNode for FollowGroup {
+    fn node_key() -> &'static str {
+        "Group"
+    }
+
+    fn socket_labels() -> SocketLabels {
+        SocketLabels {
+            valid_in: &[],
+            valid_out: &[],
+        }
+    }
+
+    fn decode(
+        source: &FlowData,
+        tempory: &mut FlowDataTempory,
+        dicid: usize,
+    ) -> Result<Self, FlowDataError> {
+        let dic = get_dictionary(source, dicid)?;
+        let debug_groupname = get_string(dic, "debug_groupname")?;
+        let data = Follow::decode(
             source,
             tempory,
-            source.get_vector(dic["data"].get_vecid().unwrap()).unwrap(),
-        );
-        let layout = Layout::new(source, dic["layout"].get_dicid().unwrap());
-        let debugmenu_tag = dic["debugmenu_tag"].get_string().unwrap();
-        Self {
+            get_vector(source, get_vecid(dic, "data")?)?,
+        )?;
+        let layout = Layout::decode(source, get_dicid(dic, "layout")?)?;
+        let debugmenu_tag = get_string(dic, "debugmenu_tag")?.into();
+        Ok(Self {
             debug_groupname,
             data,
             layout,
             debugmenu_tag,
-        }
+        })
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
         let mut dic = HashMap::new();
         dic.insert(
             "debug_groupname".to_string(),
@@ -435,51 +912,84 @@ impl FollowGroup {
         );
         dic.insert(
             "data".into(),
-            FlowDataValue::RefVec(self.data.generate(dest)),
+            FlowDataValue::RefVec(self.data.generate(dest)?),
         );
         dic.insert(
             "layout".into(),
-            FlowDataValue::RefDic(self.layout.generate(dest)),
+            FlowDataValue::RefDic(self.layout.generate(dest)?),
         );
         dic.insert(
             "debugmenu_tag".to_string(),
-            FlowDataValue::String(self.debugmenu_tag.clone()),
+            FlowDataValue::String(self.debugmenu_tag.to_string()),
         );
-        dest.push_dictionary(dic).unwrap()
+        dest.push_dictionary(dic)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// The position of a node in the debug editor of the game
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Layout {
+    /// true if this node start a new line (`lineBreak`)
     #[serde(default)]
-    line_break: bool,
+    pub line_break: bool,
+    /// vertical position (`posH`)
     #[serde(default)]
-    h: i64,
+    pub h: i64,
+    /// width (`posW`)
     #[serde(default)]
-    w: i64,
+    pub w: i64,
+    /// horizontal position (`posX`)
     #[serde(default)]
-    x: i64,
+    pub x: i64,
 }
 
 impl Layout {
-    fn new(source: &FlowData, dicid: usize) -> Self {
-        let layout_dic = source.get_dictionary(dicid).unwrap();
-        let pos_dic = source
-            .get_dictionary(layout_dic["layoutPos"].get_dicid().unwrap())
-            .unwrap();
+    /// Create a new [`Layout`]
+    pub fn new(x: i64, w: i64, h: i64, line_break: bool) -> Self {
         Self {
-            line_break: match layout_dic["lineBreak"].get_string().unwrap().as_str() {
-                "true" => true,
-                "false" => false,
-                _ => panic!(),
-            },
-            h: pos_dic["posH"].get_string().unwrap().parse().unwrap(),
-            w: pos_dic["posW"].get_string().unwrap().parse().unwrap(),
-            x: pos_dic["posX"].get_string().unwrap().parse().unwrap(),
+            line_break,
+            h,
+            w,
+            x,
         }
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    /// decode the layout dictionary `dicid`. Return an error if `lineBreak` isn't a boolean, or if
+    /// a position isn't a number.
+    pub fn decode(source: &FlowData, dicid: usize) -> Result<Self, FlowDataError> {
+        let layout_dic = get_dictionary(source, dicid)?;
+        let pos_dic = get_dictionary(source, get_dicid(layout_dic, "layoutPos")?)?;
+        let line_break = match get_string(layout_dic, "lineBreak")?.as_str() {
+            "true" => true,
+            "false" => false,
+            other => {
+                return Err(FlowDataError::UnexpectedValue(
+                    "lineBreak".to_string(),
+                    "\"true\" or \"false\"",
+                    FlowDataValue::String(other.to_string()),
+                ))
+            }
+        };
+        let position = |key: &str| -> Result<i64, FlowDataError> {
+            let value = get_string(pos_dic, key)?;
+            value.parse().map_err(|_| {
+                FlowDataError::UnexpectedValue(
+                    key.to_string(),
+                    "a number",
+                    FlowDataValue::String(value),
+                )
+            })
+        };
+        Ok(Self {
+            line_break,
+            h: position("posH")?,
+            w: position("posW")?,
+            x: position("posX")?,
+        })
+    }
+
+    /// add this layout to a [`FlowData`], returning the id of the created dictionary
+    pub fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
         let mut layout = HashMap::new();
         let mut pos = HashMap::new();
         pos.insert(
@@ -496,60 +1006,84 @@ impl Layout {
         );
         layout.insert(
             "layoutPos".into(),
-            FlowDataValue::RefDic(dest.push_dictionary(pos).unwrap()),
+            FlowDataValue::RefDic(dest.push_dictionary(pos)?),
         );
         layout.insert(
             "lineBreak".into(),
             FlowDataValue::String(if self.line_break { "true" } else { "false" }.into()),
         );
 
-        dest.push_dictionary(layout).unwrap()
+        dest.push_dictionary(layout)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A scenario node with branches
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ScenarioWithBranch {
-    entry: Vec<String>,
+    /// the scripts to run
+    pub entry: Vec<String>,
+    /// a free comment, left by the developers
     #[serde(default)]
-    comment: String,
-    socket: FollowSocket,
-    branch: Vec<String>,
+    pub comment: String,
+    /// the incoming and outgoing links of this node
+    pub socket: FollowSocket,
+    /// the branches of this scenario
+    pub branch: Vec<String>,
+    /// the position of this node in the debug editor
     #[serde(default)]
-    layout: Layout,
+    pub layout: Layout,
+    /// the name of this node, as shown in the debug menu
     #[serde(default)]
-    debugname: String,
+    pub debugname: String,
+    /// the tag of this node in the debug menu (like `system`)
     #[serde(default)]
-    debugmenu_tag: String,
+    pub debugmenu_tag: DebugMenuTag,
 }
 
 impl ScenarioWithBranch {
-    fn new(source: &FlowData, tempory: &mut FlowDataTempory, dicid: usize) -> Self {
-        let dic = source.get_dictionary(dicid).unwrap();
-        let entry = source
-            .get_vector(dic["entry"].get_vecid().unwrap())
-            .unwrap()
-            .iter()
-            .map(|x| x.get_string().unwrap())
-            .collect();
-        let comment = dic["comment"].get_string().unwrap();
-        let socket = FollowSocket::new(
+    /// Create a new [`ScenarioWithBranch`] node, that run the scripts `entry`.
+    pub fn new(entry: Vec<String>, branch: Vec<String>, socket: FollowSocket) -> Self {
+        Self {
+            entry,
+            branch,
+            socket,
+            ..Self::default()
+        }
+    }
+}
+
+impl FlowNode for ScenarioWithBranch {
+    fn node_key() -> &'static str {
+        "ScenarioWithBranch"
+    }
+
+    fn socket_labels() -> SocketLabels {
+        SocketLabels {
+            valid_in: &["start"],
+            valid_out: &["SEL_*", "next", "ok", "repeat", "select*"],
+        }
+    }
+
+    fn decode(
+        source: &FlowData,
+        tempory: &mut FlowDataTempory,
+        dicid: usize,
+    ) -> Result<Self, FlowDataError> {
+        let dic = get_dictionary(source, dicid)?;
+        let entry = get_strings(source, get_vecid(dic, "entry")?)?;
+        let comment = get_string(dic, "comment")?;
+        let socket = FollowSocket::decode(
             source,
             tempory,
-            dic["socket"].get_vecid().unwrap(),
+            get_vecid(dic, "socket")?,
             dicid,
-            &["start"],
-            &["SEL_*", "next", "ok", "repeat", "select*"],
-        );
-        let branch = source
-            .get_vector(dic["branch"].get_vecid().unwrap())
-            .unwrap()
-            .iter()
-            .map(|x| x.get_string().unwrap())
-            .collect();
-        let layout = Layout::new(source, dic["layout"].get_dicid().unwrap());
-        let debugname = dic["debugname"].get_string().unwrap();
-        let debugmenu_tag = dic["debugmenu_tag"].get_string().unwrap();
-        Self {
+            &Self::socket_labels(),
+        )?;
+        let branch = get_strings(source, get_vecid(dic, "branch")?)?;
+        let layout = Layout::decode(source, get_dicid(dic, "layout")?)?;
+        let debugname = get_string(dic, "debugname")?;
+        let debugmenu_tag = get_string(dic, "debugmenu_tag")?.into();
+        Ok(Self {
             entry,
             comment,
             socket,
@@ -557,10 +1091,10 @@ impl ScenarioWithBranch {
             layout,
             debugname,
             debugmenu_tag,
-        }
+        })
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
         let mut dic = HashMap::new();
         let mut entry_vec = Vec::new();
         for entry in &self.entry {
@@ -568,7 +1102,7 @@ impl ScenarioWithBranch {
         }
         dic.insert(
             "entry".into(),
-            FlowDataValue::RefVec(dest.push_vector(entry_vec).unwrap()),
+            FlowDataValue::RefVec(dest.push_vector(entry_vec)?),
         );
         dic.insert(
             "comment".into(),
@@ -576,7 +1110,7 @@ impl ScenarioWithBranch {
         );
         dic.insert(
             "socket".into(),
-            FlowDataValue::RefVec(self.socket.generate(dest)),
+            FlowDataValue::RefVec(self.socket.generate(dest, &Self::socket_labels())?),
         );
         let mut branch_vec = Vec::new();
         for branch in &self.branch {
@@ -584,11 +1118,11 @@ impl ScenarioWithBranch {
         }
         dic.insert(
             "branch".into(),
-            FlowDataValue::RefVec(dest.push_vector(branch_vec).unwrap()),
+            FlowDataValue::RefVec(dest.push_vector(branch_vec)?),
         );
         dic.insert(
             "layout".into(),
-            FlowDataValue::RefDic(self.layout.generate(dest)),
+            FlowDataValue::RefDic(self.layout.generate(dest)?),
         );
         dic.insert(
             "debugname".into(),
@@ -596,52 +1130,90 @@ impl ScenarioWithBranch {
         );
         dic.insert(
             "debugmenu_tag".into(),
-            FlowDataValue::String(self.debugmenu_tag.clone()),
+            FlowDataValue::String(self.debugmenu_tag.to_string()),
         );
-        dest.push_dictionary(dic).unwrap()
+        dest.push_dictionary(dic)
+    }
+
+    fn socket(&self) -> Option<&FollowSocket> {
+        Some(&self.socket)
+    }
+
+    fn socket_mut(&mut self) -> Option<&mut FollowSocket> {
+        Some(&mut self.socket)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A scenario node that also set the scenario progress number
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ScenarioWithProgNo {
-    entry: Vec<String>,
+    /// the scripts to run
+    pub entry: Vec<String>,
+    /// a free comment, left by the developers
     #[serde(default)]
-    comment: String,
-    socket: FollowSocket,
+    pub comment: String,
+    /// the incoming and outgoing links of this node
+    pub socket: FollowSocket,
+    /// the position of this node in the debug editor
     #[serde(default)]
-    layout: Layout,
+    pub layout: Layout,
+    /// the name of this node, as shown in the debug menu
     #[serde(default)]
-    debugname: String,
-    timeline: Timeline,
+    pub debugname: String,
+    /// the timeline of this node
+    pub timeline: Timeline,
+    /// the tag of this node in the debug menu (like `system`)
     #[serde(default)]
-    debugmenu_tag: String,
-    scenario_progress_no: String,
+    pub debugmenu_tag: DebugMenuTag,
+    /// the scenario progress number (`scenarioProgressNo`)
+    pub scenario_progress_no: String,
 }
 
 impl ScenarioWithProgNo {
-    fn new(source: &FlowData, tempory: &mut FlowDataTempory, dicid: usize) -> Self {
-        let dic = source.get_dictionary(dicid).unwrap();
-        let entry = source
-            .get_vector(dic["entry"].get_vecid().unwrap())
-            .unwrap()
-            .iter()
-            .map(|x| x.get_string().unwrap())
-            .collect();
-        let comment = dic["comment"].get_string().unwrap();
-        let scenario_progress_no = dic["scenarioProgressNo"].get_string().unwrap();
-        let socket = FollowSocket::new(
+    /// Create a new [`ScenarioWithProgNo`] node, that run the scripts `entry`.
+    pub fn new(entry: Vec<String>, scenario_progress_no: String, socket: FollowSocket) -> Self {
+        Self {
+            entry,
+            scenario_progress_no,
+            socket,
+            ..Self::default()
+        }
+    }
+}
+
+impl FlowNode for ScenarioWithProgNo {
+    fn node_key() -> &'static str {
+        "ScenarioWithProgNo"
+    }
+
+    fn socket_labels() -> SocketLabels {
+        SocketLabels {
+            valid_in: &["start"],
+            valid_out: &["next"],
+        }
+    }
+
+    fn decode(
+        source: &FlowData,
+        tempory: &mut FlowDataTempory,
+        dicid: usize,
+    ) -> Result<Self, FlowDataError> {
+        let dic = get_dictionary(source, dicid)?;
+        let entry = get_strings(source, get_vecid(dic, "entry")?)?;
+        let comment = get_string(dic, "comment")?;
+        let scenario_progress_no = get_string(dic, "scenarioProgressNo")?;
+        let socket = FollowSocket::decode(
             source,
             tempory,
-            dic["socket"].get_vecid().unwrap(),
+            get_vecid(dic, "socket")?,
             dicid,
-            &["start"],
-            &["next"],
-        );
-        let layout = Layout::new(source, dic["layout"].get_dicid().unwrap());
-        let debugname = dic["debugname"].get_string().unwrap();
-        let timeline = Timeline::new(source, dic["timeline"].get_dicid().unwrap());
-        let debugmenu_tag = dic["debugmenu_tag"].get_string().unwrap();
-        Self {
+            &Self::socket_labels(),
+        )?;
+        let layout = Layout::decode(source, get_dicid(dic, "layout")?)?;
+        let debugname = get_string(dic, "debugname")?;
+        let timeline = Timeline::decode(source, get_dicid(dic, "timeline")?)?;
+        let debugmenu_tag = get_string(dic, "debugmenu_tag")?.into();
+        Ok(Self {
             entry,
             comment,
             socket,
@@ -650,10 +1222,10 @@ impl ScenarioWithProgNo {
             timeline,
             debugmenu_tag,
             scenario_progress_no,
-        }
+        })
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
         let mut dic = HashMap::new();
         let mut entry_vec = Vec::new();
         for entry in &self.entry {
@@ -661,7 +1233,7 @@ impl ScenarioWithProgNo {
         }
         dic.insert(
             "entry".into(),
-            FlowDataValue::RefVec(dest.push_vector(entry_vec).unwrap()),
+            FlowDataValue::RefVec(dest.push_vector(entry_vec)?),
         );
         dic.insert(
             "comment".into(),
@@ -673,11 +1245,11 @@ impl ScenarioWithProgNo {
         );
         dic.insert(
             "socket".into(),
-            FlowDataValue::RefVec(self.socket.generate(dest)),
+            FlowDataValue::RefVec(self.socket.generate(dest, &Self::socket_labels())?),
         );
         dic.insert(
             "layout".into(),
-            FlowDataValue::RefDic(self.layout.generate(dest)),
+            FlowDataValue::RefDic(self.layout.generate(dest)?),
         );
         dic.insert(
             "debugname".into(),
@@ -685,108 +1257,216 @@ impl ScenarioWithProgNo {
         );
         dic.insert(
             "timeline".into(),
-            FlowDataValue::RefDic(self.timeline.generate(dest)),
+            FlowDataValue::RefDic(self.timeline.generate(dest)?),
         );
         dic.insert(
             "debugmenu_tag".into(),
-            FlowDataValue::String(self.debugmenu_tag.clone()),
+            FlowDataValue::String(self.debugmenu_tag.to_string()),
         );
-        dest.push_dictionary(dic).unwrap()
+        dest.push_dictionary(dic)
+    }
+
+    fn socket(&self) -> Option<&FollowSocket> {
+        Some(&self.socket)
+    }
+
+    fn socket_mut(&mut self) -> Option<&mut FollowSocket> {
+        Some(&mut self.socket)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// The timeline of a node ([`ScenarioWithProgNo`], [`FreeMove`] and [`DgStagingPost`]).
+///
+/// The keys this library know about are stored in their own field, and the other in `extras`.
+///
+/// It is serialized as a map with the typed fields and `extras`. The older serialization, with
+/// the whole content in a `dic` map, is still accepted.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(from = "TimelineRepr")]
 pub struct Timeline {
-    dic: HashMap<String, String>,
+    /// the story progress this timeline is for (`scenarioProgressNo`). It should be the same as
+    /// the one of the node that own this timeline.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenario_progress_no: Option<String>,
+    /// the other keys of the timeline, with their value
+    #[serde(default)]
+    pub extras: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TimelineRepr {
+    Legacy {
+        dic: HashMap<String, String>,
+    },
+    Typed {
+        #[serde(default)]
+        scenario_progress_no: Option<String>,
+        #[serde(default)]
+        extras: BTreeMap<String, String>,
+    },
+}
+
+impl From<TimelineRepr> for Timeline {
+    fn from(repr: TimelineRepr) -> Self {
+        match repr {
+            TimelineRepr::Legacy { dic } => Self::from_map(dic),
+            TimelineRepr::Typed {
+                scenario_progress_no,
+                extras,
+            } => Self {
+                scenario_progress_no,
+                extras,
+            },
+        }
+    }
 }
 
 impl Timeline {
-    fn new(source: &FlowData, dicid: usize) -> Self {
-        let dic = source.get_dictionary(dicid).unwrap();
-        let mut new_dic = HashMap::new();
-        for key in dic.keys() {
-            new_dic.insert(key.clone(), dic[key].get_string().unwrap());
+    /// Create a [`Timeline`] from the raw content of its dictionary
+    pub fn from_map<T: IntoIterator<Item = (String, String)>>(content: T) -> Self {
+        let mut result = Self::default();
+        for (key, value) in content {
+            match key.as_str() {
+                "scenarioProgressNo" => result.scenario_progress_no = Some(value),
+                _ => {
+                    result.extras.insert(key, value);
+                }
+            }
         }
-        Self { dic: new_dic }
+        result
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
-        let mut dic = HashMap::new();
-        for key in self.dic.keys() {
-            dic.insert(key.clone(), FlowDataValue::String(self.dic[key].clone()));
+    /// return the raw content of its dictionary
+    pub fn to_map(&self) -> BTreeMap<String, String> {
+        let mut result = self.extras.clone();
+        if let Some(progress) = &self.scenario_progress_no {
+            result.insert("scenarioProgressNo".into(), progress.clone());
+        };
+        result
+    }
+
+    /// decode the timeline dictionary `dicid`. Return an error if a value isn't a string.
+    pub fn decode(source: &FlowData, dicid: usize) -> Result<Self, FlowDataError> {
+        let dic = get_dictionary(source, dicid)?;
+        let mut map = Vec::new();
+        for key in dic.keys() {
+            map.push((key.clone(), get_string(dic, key)?));
         }
-        dest.push_dictionary(dic).unwrap()
+        Ok(Self::from_map(map))
+    }
+
+    /// add this timeline to a [`FlowData`], returning the id of the created dictionary
+    pub fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
+        let dic = self
+            .to_map()
+            .into_iter()
+            .map(|(key, value)| (key, FlowDataValue::String(value)))
+            .collect();
+        dest.push_dictionary(dic)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A node where the player can freely move in a map
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct FreeMove {
-    start_map: String,
-    start_continue: String,
-    start_place: String,
+    /// the map the player start on (`start.map`)
+    pub start_map: String,
+    /// the `start.continue` value
+    pub start_continue: String,
+    /// the place the player start at (`start.place`)
+    pub start_place: String,
+    /// a free comment, left by the developers
+    #[serde(default)]
+    pub comment: String,
+    /// the scenario progress number (`scenarioProgressNo`)
+    pub scenario_progress_no: String,
+    /// the `next_cond.next` value
+    pub next_cond_next: String,
+    /// the `next_cond.other` values
+    #[serde(deserialize_with = "deserialize_string_list")]
+    pub next_cond_other: Vec<String>,
+    /// true if `next_cond.other` is stored in a vector rather than as a string. It is always
+    /// stored in a vector if there isn't exactly one value.
     #[serde(default)]
-    comment: String,
-    scenario_progress_no: String,
-    next_cond_next: String,
-    next_cond_other: String,
-    follow_chara: Vec<String>,
-    socket: FollowSocket,
-    play_btn: String,
+    pub next_cond_other_in_a_vec: bool,
+    /// the three characters following the player (`followChara`)
+    pub follow_chara: Vec<String>,
+    /// the incoming and outgoing links of this node
+    pub socket: FollowSocket,
+    /// the `playBtn` value
+    pub play_btn: String,
+    /// the position of this node in the debug editor
     #[serde(default)]
-    layout: Layout,
+    pub layout: Layout,
+    /// the name of this node, as shown in the debug menu
     #[serde(default)]
-    debugname: String,
-    timeline: Timeline,
+    pub debugname: String,
+    /// the timeline of this node
+    pub timeline: Timeline,
+    /// the tag of this node in the debug menu (like `system`)
     #[serde(default)]
-    debugmenu_tag: String,
+    pub debugmenu_tag: DebugMenuTag,
 }
 
 impl FreeMove {
-    fn new(source: &FlowData, tempory: &mut FlowDataTempory, dicid: usize) -> Self {
-        let dic = source.get_dictionary(dicid).unwrap();
-        let dic_start = source
-            .get_dictionary(dic["start"].get_dicid().unwrap())
-            .unwrap();
-        let start_map = dic_start["map"].get_string().unwrap();
-        let start_continue = dic_start["continue"].get_string().unwrap();
-        let start_place = dic_start["place"].get_string().unwrap();
-        let comment = dic["comment"].get_string().unwrap();
-        let scenario_progress_no = dic["scenarioProgressNo"].get_string().unwrap(); //TODO some more code in the armoredmephit code
-        let dic_next_cond = source
-            .get_dictionary(dic["next_cond"].get_dicid().unwrap())
-            .unwrap();
-        let next_cond_next = dic_next_cond["next"].get_string().unwrap();
-        let next_cond_other = match &dic_next_cond["other"] {
-            FlowDataValue::String(str) => str.clone(),
-            FlowDataValue::RefVec(vecid) => source.get_vector(*vecid as usize).unwrap()[0]
-                .get_string()
-                .unwrap(),
-            _ => panic!(),
-        };
-        //TODO: store if it is in a vec or a string
-        let follow_chara_dic = source
-            .get_dictionary(dic["followChara"].get_dicid().unwrap())
-            .unwrap();
+    /// Create a new [`FreeMove`] node, that start on `start_map`.
+    pub fn new(start_map: String, socket: FollowSocket) -> Self {
+        Self {
+            start_map,
+            socket,
+            ..Self::default()
+        }
+    }
+}
+
+impl FlowNode for FreeMove {
+    fn node_key() -> &'static str {
+        "FreeMove"
+    }
+
+    fn socket_labels() -> SocketLabels {
+        SocketLabels {
+            valid_in: &["start"],
+            valid_out: &["next", "other0"],
+        }
+    }
+
+    fn decode(
+        source: &FlowData,
+        tempory: &mut FlowDataTempory,
+        dicid: usize,
+    ) -> Result<Self, FlowDataError> {
+        let dic = get_dictionary(source, dicid)?;
+        let dic_start = get_dictionary(source, get_dicid(dic, "start")?)?;
+        let start_map = get_string(dic_start, "map")?;
+        let start_continue = get_string(dic_start, "continue")?;
+        let start_place = get_string(dic_start, "place")?;
+        let comment = get_string(dic, "comment")?;
+        let scenario_progress_no = get_string(dic, "scenarioProgressNo")?; //TODO some more code in the armoredmephit code
+        let dic_next_cond = get_dictionary(source, get_dicid(dic, "next_cond")?)?;
+        let next_cond_next = get_string(dic_next_cond, "next")?;
+        let (next_cond_other, next_cond_other_in_a_vec) =
+            decode_string_list(source, get_entry(dic_next_cond, "other")?)?;
+        let follow_chara_dic = get_dictionary(source, get_dicid(dic, "followChara")?)?;
         let mut follow_chara = Vec::new();
         for follow_chara_id in &["follow0", "follow1", "follow2"] {
-            let temp = follow_chara_dic[*follow_chara_id].clone();
-            follow_chara.push(temp.get_string().unwrap());
+            follow_chara.push(get_string(follow_chara_dic, follow_chara_id)?);
         }
-        let socket = FollowSocket::new(
+        let socket = FollowSocket::decode(
             source,
             tempory,
-            dic["socket"].get_vecid().unwrap(),
+            get_vecid(dic, "socket")?,
             dicid,
-            &["start"],
-            &["next", "other0"],
-        );
-        let play_btn = dic["playBtn"].get_string().unwrap();
-        let layout = Layout::new(source, dic["layout"].get_dicid().unwrap());
-        let debugname = dic["debugname"].get_string().unwrap();
-        let timeline = Timeline::new(source, dic["timeline"].get_dicid().unwrap());
-        let debugmenu_tag = dic["debugmenu_tag"].get_string().unwrap();
-
-        FreeMove {
+            &Self::socket_labels(),
+        )?;
+        let play_btn = get_string(dic, "playBtn")?;
+        let layout = Layout::decode(source, get_dicid(dic, "layout")?)?;
+        let debugname = get_string(dic, "debugname")?;
+        let timeline = Timeline::decode(source, get_dicid(dic, "timeline")?)?;
+        let debugmenu_tag = get_string(dic, "debugmenu_tag")?.into();
+
+        Ok(FreeMove {
             start_map,
             start_continue,
             start_place,
@@ -794,6 +1474,7 @@ impl FreeMove {
             scenario_progress_no,
             next_cond_next,
             next_cond_other,
+            next_cond_other_in_a_vec,
             follow_chara,
             socket,
             play_btn,
@@ -801,10 +1482,10 @@ impl FreeMove {
             debugname,
             timeline,
             debugmenu_tag,
-        }
+        })
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
         let mut dic = HashMap::new();
         let mut dic_start = HashMap::new();
         dic_start.insert("map".into(), FlowDataValue::String(self.start_map.clone()));
@@ -818,7 +1499,7 @@ impl FreeMove {
         );
         dic.insert(
             "start".into(),
-            FlowDataValue::RefDic(dest.push_dictionary(dic_start).unwrap()),
+            FlowDataValue::RefDic(dest.push_dictionary(dic_start)?),
         );
 
         dic.insert(
@@ -837,28 +1518,33 @@ impl FreeMove {
         );
         dic_next_cond.insert(
             "other".into(),
-            FlowDataValue::String(self.next_cond_other.clone()),
+            generate_string_list(dest, &self.next_cond_other, self.next_cond_other_in_a_vec)?,
         );
         dic.insert(
             "next_cond".into(),
-            FlowDataValue::RefDic(dest.push_dictionary(dic_next_cond).unwrap()),
+            FlowDataValue::RefDic(dest.push_dictionary(dic_next_cond)?),
         );
 
         let mut dic_follow_chara = HashMap::new();
         for follow_chara_id in 0..3 {
             dic_follow_chara.insert(
                 format!("follow{}", follow_chara_id),
-                FlowDataValue::String(self.follow_chara[follow_chara_id].clone()),
+                FlowDataValue::String(
+                    self.follow_chara
+                        .get(follow_chara_id)
+                        .cloned()
+                        .unwrap_or_default(),
+                ),
             );
         }
         dic.insert(
             "followChara".into(),
-            FlowDataValue::RefDic(dest.push_dictionary(dic_follow_chara).unwrap()),
+            FlowDataValue::RefDic(dest.push_dictionary(dic_follow_chara)?),
         );
 
         dic.insert(
             "socket".into(),
-            FlowDataValue::RefVec(self.socket.generate(dest)),
+            FlowDataValue::RefVec(self.socket.generate(dest, &Self::socket_labels())?),
         );
         dic.insert(
             "playBtn".into(),
@@ -866,7 +1552,7 @@ impl FreeMove {
         );
         dic.insert(
             "layout".into(),
-            FlowDataValue::RefDic(self.layout.generate(dest)),
+            FlowDataValue::RefDic(self.layout.generate(dest)?),
         );
         dic.insert(
             "debugname".into(),
@@ -874,52 +1560,93 @@ impl FreeMove {
         );
         dic.insert(
             "timeline".into(),
-            FlowDataValue::RefDic(self.timeline.generate(dest)),
+            FlowDataValue::RefDic(self.timeline.generate(dest)?),
         );
         dic.insert(
             "debugmenu_tag".into(),
-            FlowDataValue::String(self.debugmenu_tag.clone()),
+            FlowDataValue::String(self.debugmenu_tag.to_string()),
         );
 
-        dest.push_dictionary(dic).unwrap()
+        dest.push_dictionary(dic)
+    }
+
+    fn socket(&self) -> Option<&FollowSocket> {
+        Some(&self.socket)
+    }
+
+    fn socket_mut(&mut self) -> Option<&mut FollowSocket> {
+        Some(&mut self.socket)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A dungeon staging post node
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct DgStagingPost {
-    map: String,
+    /// the map of this staging post
+    pub map: String,
+    /// a free comment, left by the developers
     #[serde(default)]
-    comment: String,
-    socket: FollowSocket,
+    pub comment: String,
+    /// the incoming and outgoing links of this node
+    pub socket: FollowSocket,
+    /// the position of this node in the debug editor
     #[serde(default)]
-    layout: Layout,
+    pub layout: Layout,
+    /// the name of this node, as shown in the debug menu
     #[serde(default)]
-    debugname: String,
-    timeline: Timeline,
+    pub debugname: String,
+    /// the timeline of this node
+    pub timeline: Timeline,
+    /// the tag of this node in the debug menu (like `system`)
     #[serde(default)]
-    debugmenu_tag: String,
+    pub debugmenu_tag: DebugMenuTag,
 }
 
 impl DgStagingPost {
-    fn new(source: &FlowData, tempory: &mut FlowDataTempory, dicid: usize) -> Self {
-        let dic = source.get_dictionary(dicid).unwrap();
+    /// Create a new [`DgStagingPost`] node, on the map `map`.
+    pub fn new(map: String, socket: FollowSocket) -> Self {
+        Self {
+            map,
+            socket,
+            ..Self::default()
+        }
+    }
+}
+
+impl FlowNode for DgStagingPost {
+    fn node_key() -> &'static str {
+        "DgStagingPost"
+    }
 
-        let comment = dic["comment"].get_string().unwrap();
-        let socket = FollowSocket::new(
+    fn socket_labels() -> SocketLabels {
+        SocketLabels {
+            valid_in: &["start"],
+            valid_out: &["next", "other0"],
+        }
+    }
+
+    fn decode(
+        source: &FlowData,
+        tempory: &mut FlowDataTempory,
+        dicid: usize,
+    ) -> Result<Self, FlowDataError> {
+        let dic = get_dictionary(source, dicid)?;
+
+        let comment = get_string(dic, "comment")?;
+        let socket = FollowSocket::decode(
             source,
             tempory,
-            dic["socket"].get_vecid().unwrap(),
+            get_vecid(dic, "socket")?,
             dicid,
-            &["start"],
-            &["next", "other0"],
-        );
-        let map = dic["map"].get_string().unwrap();
-        let layout = Layout::new(source, dic["layout"].get_dicid().unwrap());
-        let debugname = dic["debugname"].get_string().unwrap();
-        let timeline = Timeline::new(source, dic["timeline"].get_dicid().unwrap());
-        let debugmenu_tag = dic["debugmenu_tag"].get_string().unwrap();
-
-        Self {
+            &Self::socket_labels(),
+        )?;
+        let map = get_string(dic, "map")?;
+        let layout = Layout::decode(source, get_dicid(dic, "layout")?)?;
+        let debugname = get_string(dic, "debugname")?;
+        let timeline = Timeline::decode(source, get_dicid(dic, "timeline")?)?;
+        let debugmenu_tag = get_string(dic, "debugmenu_tag")?.into();
+
+        Ok(Self {
             comment,
             socket,
             map,
@@ -927,10 +1654,10 @@ impl DgStagingPost {
             debugname,
             timeline,
             debugmenu_tag,
-        }
+        })
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
         let mut dic = HashMap::new();
         dic.insert(
             "comment".into(),
@@ -938,12 +1665,12 @@ impl DgStagingPost {
         );
         dic.insert(
             "socket".into(),
-            FlowDataValue::RefVec(self.socket.generate(dest)),
+            FlowDataValue::RefVec(self.socket.generate(dest, &Self::socket_labels())?),
         );
         dic.insert("map".into(), FlowDataValue::String(self.map.clone()));
         dic.insert(
             "layout".into(),
-            FlowDataValue::RefDic(self.layout.generate(dest)),
+            FlowDataValue::RefDic(self.layout.generate(dest)?),
         );
         dic.insert(
             "debugname".into(),
@@ -951,53 +1678,96 @@ impl DgStagingPost {
         );
         dic.insert(
             "timeline".into(),
-            FlowDataValue::RefDic(self.timeline.generate(dest)),
+            FlowDataValue::RefDic(self.timeline.generate(dest)?),
         );
         dic.insert(
             "debugmenu_tag".into(),
-            FlowDataValue::String(self.debugmenu_tag.clone()),
+            FlowDataValue::String(self.debugmenu_tag.to_string()),
         );
-        dest.push_dictionary(dic).unwrap()
+        dest.push_dictionary(dic)
+    }
+
+    fn socket(&self) -> Option<&FollowSocket> {
+        Some(&self.socket)
+    }
+
+    fn socket_mut(&mut self) -> Option<&mut FollowSocket> {
+        Some(&mut self.socket)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A node that branch depending on a counter
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct DgFlowBranch {
-    act: String,
+    /// the action done on the counter before the comparison
+    pub act: CounterAction,
+    /// a free comment, left by the developers
     #[serde(default)]
-    comment: String,
-    count: String,
-    socket: FollowSocket,
-    r#if: String,
-    id: String,
+    pub comment: String,
+    /// the value the counter is compared with
+    pub count: FlowInt,
+    /// the incoming and outgoing links of this node
+    pub socket: FollowSocket,
+    /// the comparison done with the counter
+    pub r#if: Comparison,
+    /// the counter identifier
+    pub id: FlowInt,
+    /// the position of this node in the debug editor
     #[serde(default)]
-    layout: Layout,
+    pub layout: Layout,
+    /// the name of this node, as shown in the debug menu
     #[serde(default)]
-    debugname: String,
+    pub debugname: String,
+    /// the tag of this node in the debug menu (like `system`)
     #[serde(default)]
-    debugmenu_tag: String,
+    pub debugmenu_tag: DebugMenuTag,
 }
 
 impl DgFlowBranch {
-    fn new(source: &FlowData, tempory: &mut FlowDataTempory, dicid: usize) -> Self {
-        let dic = source.get_dictionary(dicid).unwrap();
-        let act = dic["act"].get_string().unwrap();
-        let comment = dic["comment"].get_string().unwrap();
-        let count = dic["count"].get_string().unwrap();
-        let socket = FollowSocket::new(
+    /// Create a new [`DgFlowBranch`] node, that read the counter `id`.
+    pub fn new(id: FlowInt, socket: FollowSocket) -> Self {
+        Self {
+            id,
+            socket,
+            ..Self::default()
+        }
+    }
+}
+
+impl FlowNode for DgFlowBranch {
+    fn node_key() -> &'static str {
+        "DgFlowBranch"
+    }
+
+    fn socket_labels() -> SocketLabels {
+        SocketLabels {
+            valid_in: &["in", "reset_zero"],
+            valid_out: &["flow_A", "flow_B"],
+        }
+    }
+
+    fn decode(
+        source: &FlowData,
+        tempory: &mut FlowDataTempory,
+        dicid: usize,
+    ) -> Result<Self, FlowDataError> {
+        let dic = get_dictionary(source, dicid)?;
+        let act = get_string(dic, "act")?.into();
+        let comment = get_string(dic, "comment")?;
+        let count = get_string(dic, "count")?.into();
+        let socket = FollowSocket::decode(
             source,
             tempory,
-            dic["socket"].get_vecid().unwrap(),
+            get_vecid(dic, "socket")?,
             dicid,
-            &["in", "reset_zero"],
-            &["flow_A", "flow_B"],
-        );
-        let r#if = dic["if"].get_string().unwrap();
-        let id = dic["id"].get_string().unwrap();
-        let layout = Layout::new(source, dic["layout"].get_dicid().unwrap());
-        let debugname = dic["debugname"].get_string().unwrap();
-        let debugmenu_tag = dic["debugmenu_tag"].get_string().unwrap();
-        Self {
+            &Self::socket_labels(),
+        )?;
+        let r#if = get_string(dic, "if")?.into();
+        let id = get_string(dic, "id")?.into();
+        let layout = Layout::decode(source, get_dicid(dic, "layout")?)?;
+        let debugname = get_string(dic, "debugname")?;
+        let debugmenu_tag = get_string(dic, "debugmenu_tag")?.into();
+        Ok(Self {
             act,
             comment,
             count,
@@ -1007,26 +1777,29 @@ impl DgFlowBranch {
             layout,
             debugname,
             debugmenu_tag,
-        }
+        })
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
         let mut dic = HashMap::new();
-        dic.insert("act".into(), FlowDataValue::String(self.act.clone()));
+        dic.insert("act".into(), FlowDataValue::String(self.act.to_string()));
         dic.insert(
             "comment".into(),
             FlowDataValue::String(self.comment.clone()),
         );
-        dic.insert("count".into(), FlowDataValue::String(self.count.clone()));
+        dic.insert(
+            "count".into(),
+            FlowDataValue::String(self.count.to_string()),
+        );
         dic.insert(
             "socket".into(),
-            FlowDataValue::RefVec(self.socket.generate(dest)),
+            FlowDataValue::RefVec(self.socket.generate(dest, &Self::socket_labels())?),
         );
-        dic.insert("if".into(), FlowDataValue::String(self.r#if.clone()));
-        dic.insert("id".into(), FlowDataValue::String(self.id.clone()));
+        dic.insert("if".into(), FlowDataValue::String(self.r#if.to_string()));
+        dic.insert("id".into(), FlowDataValue::String(self.id.to_string()));
         dic.insert(
             "layout".into(),
-            FlowDataValue::RefDic(self.layout.generate(dest)),
+            FlowDataValue::RefDic(self.layout.generate(dest)?),
         );
         dic.insert(
             "debugname".into(),
@@ -1034,50 +1807,86 @@ impl DgFlowBranch {
         );
         dic.insert(
             "debugmenu_tag".into(),
-            FlowDataValue::String(self.debugmenu_tag.clone()),
+            FlowDataValue::String(self.debugmenu_tag.to_string()),
         );
-        dest.push_dictionary(dic).unwrap()
+        dest.push_dictionary(dic)
+    }
+
+    fn socket(&self) -> Option<&FollowSocket> {
+        Some(&self.socket)
+    }
+
+    fn socket_mut(&mut self) -> Option<&mut FollowSocket> {
+        Some(&mut self.socket)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A node that play scripts
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Scenario {
-    flowtype: String,
-    entry: Vec<String>,
+    /// the type of flow (like `normal`)
+    pub flowtype: FlowType,
+    /// the scripts to run
+    pub entry: Vec<String>,
+    /// a free comment, left by the developers
     #[serde(default)]
-    comment: String,
-    socket: FollowSocket,
+    pub comment: String,
+    /// the incoming and outgoing links of this node
+    pub socket: FollowSocket,
+    /// the position of this node in the debug editor
     #[serde(default)]
-    layout: Layout,
+    pub layout: Layout,
+    /// the name of this node, as shown in the debug menu
     #[serde(default)]
-    debugname: String,
+    pub debugname: String,
+    /// the tag of this node in the debug menu (like `system`)
     #[serde(default)]
-    debugmenu_tag: String,
+    pub debugmenu_tag: DebugMenuTag,
 }
 
 impl Scenario {
-    fn new(source: &FlowData, tempory: &mut FlowDataTempory, dicid: usize) -> Self {
-        let dic = source.get_dictionary(dicid).unwrap();
-        let flowtype = dic["flowtype"].get_string().unwrap();
-        let entry = source
-            .get_vector(dic["entry"].get_vecid().unwrap())
-            .unwrap()
-            .iter()
-            .map(|x| x.get_string().unwrap())
-            .collect();
-        let comment = dic["comment"].get_string().unwrap();
-        let socket = FollowSocket::new(
+    /// Create a new [`Scenario`] node, that run the scripts `entry`.
+    pub fn new(entry: Vec<String>, socket: FollowSocket) -> Self {
+        Self {
+            entry,
+            socket,
+            ..Self::default()
+        }
+    }
+}
+
+impl FlowNode for Scenario {
+    fn node_key() -> &'static str {
+        "Scenario"
+    }
+
+    fn socket_labels() -> SocketLabels {
+        SocketLabels {
+            valid_in: &["start"],
+            valid_out: &["next"],
+        }
+    }
+
+    fn decode(
+        source: &FlowData,
+        tempory: &mut FlowDataTempory,
+        dicid: usize,
+    ) -> Result<Self, FlowDataError> {
+        let dic = get_dictionary(source, dicid)?;
+        let flowtype = get_string(dic, "flowtype")?.into();
+        let entry = get_strings(source, get_vecid(dic, "entry")?)?;
+        let comment = get_string(dic, "comment")?;
+        let socket = FollowSocket::decode(
             source,
             tempory,
-            dic["socket"].get_vecid().unwrap(),
+            get_vecid(dic, "socket")?,
             dicid,
-            &["start"],
-            &["next"],
-        );
-        let layout = Layout::new(source, dic["layout"].get_dicid().unwrap());
-        let debugname = dic["debugname"].get_string().unwrap();
-        let debugmenu_tag = dic["debugmenu_tag"].get_string().unwrap();
-        Self {
+            &Self::socket_labels(),
+        )?;
+        let layout = Layout::decode(source, get_dicid(dic, "layout")?)?;
+        let debugname = get_string(dic, "debugname")?;
+        let debugmenu_tag = get_string(dic, "debugmenu_tag")?.into();
+        Ok(Self {
             flowtype,
             entry,
             comment,
@@ -1085,13 +1894,13 @@ impl Scenario {
             layout,
             debugname,
             debugmenu_tag,
-        }
+        })
     }
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
         let mut dic = HashMap::new();
         dic.insert(
             "flowtype".into(),
-            FlowDataValue::String(self.flowtype.clone()),
+            FlowDataValue::String(self.flowtype.to_string()),
         );
 
         let entrys = self
@@ -1101,7 +1910,7 @@ impl Scenario {
             .collect();
         dic.insert(
             "entry".into(),
-            FlowDataValue::RefVec(dest.push_vector(entrys).unwrap()),
+            FlowDataValue::RefVec(dest.push_vector(entrys)?),
         );
 
         dic.insert(
@@ -1110,11 +1919,11 @@ impl Scenario {
         );
         dic.insert(
             "socket".into(),
-            FlowDataValue::RefVec(self.socket.generate(dest)),
+            FlowDataValue::RefVec(self.socket.generate(dest, &Self::socket_labels())?),
         );
         dic.insert(
             "layout".into(),
-            FlowDataValue::RefDic(self.layout.generate(dest)),
+            FlowDataValue::RefDic(self.layout.generate(dest)?),
         );
         dic.insert(
             "debugname".into(),
@@ -1122,46 +1931,88 @@ impl Scenario {
         );
         dic.insert(
             "debugmenu_tag".into(),
-            FlowDataValue::String(self.debugmenu_tag.clone()),
+            FlowDataValue::String(self.debugmenu_tag.to_string()),
         );
 
-        dest.push_dictionary(dic).unwrap()
+        dest.push_dictionary(dic)
+    }
+
+    fn socket(&self) -> Option<&FollowSocket> {
+        Some(&self.socket)
+    }
+
+    fn socket_mut(&mut self) -> Option<&mut FollowSocket> {
+        Some(&mut self.socket)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A node that set a counter, read by [`DgFlowBranch`]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct DgFlowBranchSetCounter {
+    /// a free comment, left by the developers
     #[serde(default)]
-    comment: String,
-    count: String,
-    socket: FollowSocket,
-    id: String,
+    pub comment: String,
+    /// the value the counter is set to
+    pub count: FlowInt,
+    /// the incoming and outgoing links of this node
+    pub socket: FollowSocket,
+    /// the counter identifier
+    pub id: FlowInt,
+    /// the position of this node in the debug editor
     #[serde(default)]
-    layout: Layout,
+    pub layout: Layout,
+    /// the name of this node, as shown in the debug menu
     #[serde(default)]
-    debugname: String,
+    pub debugname: String,
+    /// the tag of this node in the debug menu (like `system`)
     #[serde(default)]
-    debugmenu_tag: String,
+    pub debugmenu_tag: DebugMenuTag,
 }
 
 impl DgFlowBranchSetCounter {
-    fn new(source: &FlowData, tempory: &mut FlowDataTempory, dicid: usize) -> Self {
-        let dic = source.get_dictionary(dicid).unwrap();
-        let comment = dic["comment"].get_string().unwrap();
-        let count = dic["count"].get_string().unwrap();
-        let socket = FollowSocket::new(
+    /// Create a new [`DgFlowBranchSetCounter`] node, that set the counter `id` to `count`.
+    pub fn new(id: FlowInt, count: FlowInt, socket: FollowSocket) -> Self {
+        Self {
+            id,
+            count,
+            socket,
+            ..Self::default()
+        }
+    }
+}
+
+impl FlowNode for DgFlowBranchSetCounter {
+    fn node_key() -> &'static str {
+        "DgFlowBranchSetCounter"
+    }
+
+    fn socket_labels() -> SocketLabels {
+        SocketLabels {
+            valid_in: &["in"],
+            valid_out: &["out"],
+        }
+    }
+
+    fn decode(
+        source: &FlowData,
+        tempory: &mut FlowDataTempory,
+        dicid: usize,
+    ) -> Result<Self, FlowDataError> {
+        let dic = get_dictionary(source, dicid)?;
+        let comment = get_string(dic, "comment")?;
+        let count = get_string(dic, "count")?.into();
+        let socket = FollowSocket::decode(
             source,
             tempory,
-            dic["socket"].get_vecid().unwrap(),
+            get_vecid(dic, "socket")?,
             dicid,
-            &["in"],
-            &["out"],
-        );
-        let id = dic["id"].get_string().unwrap();
-        let layout = Layout::new(source, dic["layout"].get_dicid().unwrap());
-        let debugname = dic["debugname"].get_string().unwrap();
-        let debugmenu_tag = dic["debugmenu_tag"].get_string().unwrap();
-        Self {
+            &Self::socket_labels(),
+        )?;
+        let id = get_string(dic, "id")?.into();
+        let layout = Layout::decode(source, get_dicid(dic, "layout")?)?;
+        let debugname = get_string(dic, "debugname")?;
+        let debugmenu_tag = get_string(dic, "debugmenu_tag")?.into();
+        Ok(Self {
             comment,
             count,
             socket,
@@ -1169,24 +2020,27 @@ impl DgFlowBranchSetCounter {
             id,
             debugname,
             debugmenu_tag,
-        }
+        })
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
         let mut dic = HashMap::new();
         dic.insert(
             "comment".into(),
             FlowDataValue::String(self.comment.clone()),
         );
-        dic.insert("count".into(), FlowDataValue::String(self.count.clone()));
+        dic.insert(
+            "count".into(),
+            FlowDataValue::String(self.count.to_string()),
+        );
         dic.insert(
             "socket".into(),
-            FlowDataValue::RefVec(self.socket.generate(dest)),
+            FlowDataValue::RefVec(self.socket.generate(dest, &Self::socket_labels())?),
         );
-        dic.insert("id".into(), FlowDataValue::String(self.id.clone()));
+        dic.insert("id".into(), FlowDataValue::String(self.id.to_string()));
         dic.insert(
             "layout".into(),
-            FlowDataValue::RefDic(self.layout.generate(dest)),
+            FlowDataValue::RefDic(self.layout.generate(dest)?),
         );
         dic.insert(
             "debugname".into(),
@@ -1194,244 +2048,381 @@ impl DgFlowBranchSetCounter {
         );
         dic.insert(
             "debugmenu_tag".into(),
-            FlowDataValue::String(self.debugmenu_tag.clone()),
+            FlowDataValue::String(self.debugmenu_tag.to_string()),
         );
-        dest.push_dictionary(dic).unwrap()
+        dest.push_dictionary(dic)
+    }
+
+    fn socket(&self) -> Option<&FollowSocket> {
+        Some(&self.socket)
+    }
+
+    fn socket_mut(&mut self) -> Option<&mut FollowSocket> {
+        Some(&mut self.socket)
     }
 }
 
 fn follow_incoming_link(
     source: &FlowData,
-    tempory: &mut FlowDataTempory,
     dicid: usize,
-    parent_dicid: usize,
-    _valid_in_label: &[&str],
-) -> HashMap<String, HashMap<String, String>> {
-    let dic = source.get_dictionary(dicid).unwrap();
-    let idn = dic["idname"].get_string().unwrap();
-    let inl = dic["in"].get_string().unwrap();
-    tempory
-        .idname_set
-        .insert(idn.clone(), (inl.clone(), parent_dicid));
-    let mut result = HashMap::new();
-    result.insert("%IN".into(), {
-        let mut temp = HashMap::new();
-        temp.insert(inl, idn);
-        temp
-    });
-    result
+    labels: &SocketLabels,
+) -> Result<(String, String), FlowDataError> {
+    let dic = get_dictionary(source, dicid)?;
+    let idn = get_string(dic, "idname")?;
+    let inl = get_string(dic, "in")?;
+    if !labels.accept_in(&inl) {
+        return Err(FlowDataError::InvalidInLabel(inl, labels.valid_in));
+    };
+    Ok((inl, idn))
 }
 
 fn follow_outgoing_link(
     source: &FlowData,
-    _tempory: &mut FlowDataTempory,
     dicid: usize,
-    _parent_dicid: usize,
-    _valid_out_label: &[&str],
-) -> HashMap<String, String> {
-    let dic = source.get_dictionary(dicid).unwrap();
-    let label = dic["out"].get_string().unwrap();
-    let dest = dic["to"].get_string().unwrap();
-    let mut result = HashMap::new();
-    result.insert(label, dest);
-    result
-}
-
-#[derive(Serialize, Deserialize, Debug)]
+    labels: &SocketLabels,
+) -> Result<(String, String), FlowDataError> {
+    let dic = get_dictionary(source, dicid)?;
+    let label = get_string(dic, "out")?;
+    let dest = get_string(dic, "to")?;
+    if !labels.accept_out(&label) {
+        return Err(FlowDataError::InvalidOutLabel(label, labels.valid_out));
+    };
+    Ok((label, dest))
+}
+
+/// The incoming and outgoing links of a node.
+///
+/// A node is the target of an outgoing link if one of its incoming link has the idname the
+/// outgoing link point to.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct FollowSocket {
-    socket_in: HashMap<String, String>,
-    socket_out: HashMap<String, String>,
+    /// the incoming links, as a map of label to idname
+    pub socket_in: BTreeMap<String, String>,
+    /// the outgoing links, as a map of label to the target idname
+    pub socket_out: BTreeMap<String, String>,
 }
 
 impl FollowSocket {
-    fn new(
+    /// decode the socket vector `vecid` of a node. Return an error if a link label isn't accepted
+    /// by `labels`.
+    pub fn decode(
         source: &FlowData,
-        tempory: &mut FlowDataTempory,
+        _tempory: &mut FlowDataTempory,
         vecid: usize,
-        parent_dicid: usize,
-        valid_in_label: &[&str],
-        valid_out_label: &[&str],
-    ) -> Self {
-        let vec = source.get_vector(vecid).unwrap();
-        let mut socket_out: HashMap<String, String> = HashMap::new();
-        let mut socket_in: HashMap<String, String> = HashMap::new();
-
-        for dicid in vec.iter().map(|x| x.get_dicid().unwrap()) {
-            let dic = source.get_dictionary(dicid).unwrap();
+        _parent_dicid: usize,
+        labels: &SocketLabels,
+    ) -> Result<Self, FlowDataError> {
+        let vec = get_vector(source, vecid)?;
+        let mut socket_out = BTreeMap::new();
+        let mut socket_in = BTreeMap::new();
+
+        for value in vec {
+            let dicid = value.get_dicid().ok_or_else(|| {
+                FlowDataError::UnexpectedValue("socket".to_string(), "a link", value.clone())
+            })?;
+            let dic = get_dictionary(source, dicid)?;
             if dic.contains_key("idname") {
-                let link =
-                    follow_incoming_link(source, tempory, dicid, parent_dicid, valid_in_label);
-                let link_in = &link["%IN"];
-                for key in link_in.keys() {
-                    socket_in.insert(key.clone(), link_in[key].clone());
-                }
+                let (label, idname) = follow_incoming_link(source, dicid, labels)?;
+                socket_in.insert(label, idname);
             } else if dic.contains_key("out") {
-                let link =
-                    follow_outgoing_link(source, tempory, dicid, parent_dicid, valid_out_label);
-                for key in link.keys() {
-                    socket_out.insert(key.clone(), link[key].clone());
-                }
+                let (label, to) = follow_outgoing_link(source, dicid, labels)?;
+                socket_out.insert(label, to);
             } else {
-                panic!();
+                return Err(FlowDataError::UnexpectedValue(
+                    "socket".to_string(),
+                    "a link with an \"idname\" or an \"out\"",
+                    value.clone(),
+                ));
             }
         }
 
-        FollowSocket {
+        Ok(FollowSocket {
             socket_in,
             socket_out,
-        }
+        })
+    }
+
+    /// Create a new [`FollowSocket`], without any link
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// return this socket with the incoming link `label` added (or replaced)
+    pub fn with_in(mut self, label: &str, idname: &str) -> Self {
+        self.add_in(label.to_string(), idname.to_string());
+        self
+    }
+
+    /// return this socket with the outgoing link `label` added (or replaced)
+    pub fn with_out(mut self, label: &str, to: &str) -> Self {
+        self.add_out(label.to_string(), to.to_string());
+        self
+    }
+
+    /// return the incoming links, as a map of label to idname
+    pub fn socket_in(&self) -> &BTreeMap<String, String> {
+        &self.socket_in
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    /// return the outgoing links, as a map of label to the target idname
+    pub fn socket_out(&self) -> &BTreeMap<String, String> {
+        &self.socket_out
+    }
+
+    /// add (or replace) an incoming link
+    pub fn add_in(&mut self, label: String, idname: String) {
+        self.socket_in.insert(label, idname);
+    }
+
+    /// add (or replace) an outgoing link
+    pub fn add_out(&mut self, label: String, to: String) {
+        self.socket_out.insert(label, to);
+    }
+
+    /// return the idnames of the incoming links
+    pub fn idnames(&self) -> impl Iterator<Item = &str> {
+        self.socket_in.values().map(String::as_str)
+    }
+
+    /// add this socket to a [`FlowData`], returning the id of the created vector. Return an error
+    /// if a link label isn't accepted by `labels`.
+    pub fn generate(
+        &self,
+        dest: &mut FlowData,
+        labels: &SocketLabels,
+    ) -> Result<u16, FlowDataError> {
+        labels.check(self)?;
         let mut vec = Vec::new();
         for socket_in in &self.socket_in {
             let mut dic = HashMap::new();
             dic.insert("idname".into(), FlowDataValue::String(socket_in.1.clone()));
             dic.insert("in".into(), FlowDataValue::String(socket_in.0.clone()));
-            vec.push(FlowDataValue::RefDic(dest.push_dictionary(dic).unwrap()));
+            vec.push(FlowDataValue::RefDic(dest.push_dictionary(dic)?));
         }
         for socket_out in &self.socket_out {
             let mut dic = HashMap::new();
             dic.insert("to".into(), FlowDataValue::String(socket_out.1.clone()));
             dic.insert("out".into(), FlowDataValue::String(socket_out.0.clone()));
-            vec.push(FlowDataValue::RefDic(dest.push_dictionary(dic).unwrap()));
+            vec.push(FlowDataValue::RefDic(dest.push_dictionary(dic)?));
         }
-        dest.push_vector(vec).unwrap()
+        dest.push_vector(vec)
     }
 }
-#[derive(Serialize, Deserialize, Debug)]
+/// A list of node, as stored at the top level of a flow file or in a [`FollowGroup`]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Follow(Vec<OutputEnum>);
 
 impl Follow {
-    fn new(source: &FlowData, tempory: &mut FlowDataTempory, top_vec: &[FlowDataValue]) -> Self {
+    /// Create a new, empty [`Follow`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// return the number of node in this list
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// return true if this list doesn't contain any node
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// return the node at `index`, if it exist
+    pub fn get(&self, index: usize) -> Option<&OutputEnum> {
+        self.0.get(index)
+    }
+
+    /// return the node at `index` mutably, if it exist
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut OutputEnum> {
+        self.0.get_mut(index)
+    }
+
+    /// add a node at the end of this list
+    pub fn push(&mut self, node: OutputEnum) {
+        self.0.push(node)
+    }
+
+    /// insert a node at `index`, shifting the following nodes
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`
+    pub fn insert(&mut self, index: usize, node: OutputEnum) {
+        self.0.insert(index, node)
+    }
+
+    /// remove and return the node at `index`, shifting the following nodes
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds
+    pub fn remove(&mut self, index: usize) -> OutputEnum {
+        self.0.remove(index)
+    }
+
+    /// iterate over the nodes of this list
+    pub fn iter(&self) -> std::slice::Iter<'_, OutputEnum> {
+        self.0.iter()
+    }
+
+    /// iterate mutably over the nodes of this list
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, OutputEnum> {
+        self.0.iter_mut()
+    }
+
+    /// decode a list of node. The decoder for each node is taken from the [`NodeRegistry`] of
+    /// `tempory`.
+    pub fn decode(
+        source: &FlowData,
+        tempory: &mut FlowDataTempory,
+        top_vec: &[FlowDataValue],
+    ) -> Result<Self, FlowDataError> {
         if top_vec.is_empty() {
-            panic!();
+            return Err(FlowDataError::EmptyFollow);
         };
         let mut datas = Vec::new();
         for value in top_vec {
-            if let FlowDataValue::RefDic(dicid) = value {
-                let dic = source.get_dictionary(*dicid as usize).unwrap();
-                if dic.len() != 1 {
-                    panic!();
-                };
-                let key = dic.keys().next().unwrap();
-                let dicid = match dic[key] {
-                    FlowDataValue::RefDic(refid) => refid,
-                    _ => panic!(),
-                } as usize;
-                let to_add = match key.as_str() {
-                    "Group" => OutputEnum::FollowGroup(FollowGroup::new(source, tempory, dicid)),
-                    "ScenarioWithBranch" => OutputEnum::ScenarioWithBranch(
-                        ScenarioWithBranch::new(source, tempory, dicid),
-                    ),
-                    "Scenario" => OutputEnum::Scenario(Scenario::new(source, tempory, dicid)),
-                    "Dungeon" => OutputEnum::Dungeon(Dungeon::new(source, tempory, dicid)),
-                    "DungeonEnd" => OutputEnum::DungeonEnd(DungeonEnd::new(source, tempory, dicid)),
-                    "AskSave" => OutputEnum::AskSave(AskSave::new(source, tempory, dicid)),
-                    "FreeMove" => OutputEnum::FreeMove(FreeMove::new(source, tempory, dicid)),
-                    "DgFlowBranchSetCounter" => OutputEnum::DgFlowBranchSetCounter(
-                        DgFlowBranchSetCounter::new(source, tempory, dicid),
-                    ),
-                    "DgFlowBranch" => {
-                        OutputEnum::DgFlowBranch(DgFlowBranch::new(source, tempory, dicid))
-                    }
-                    "DgStagingPost" => {
-                        OutputEnum::DgStagingPost(DgStagingPost::new(source, tempory, dicid))
-                    }
-                    "ScenarioWithProgNo" => OutputEnum::ScenarioWithProgNo(
-                        ScenarioWithProgNo::new(source, tempory, dicid),
-                    ),
-                    "FreeMoveEvent" => {
-                        OutputEnum::FreeMoveEvent(FreeMoveEvent::new(source, tempory, dicid))
-                    }
-                    unreconized => panic!("unreconized value: {:?}", unreconized),
-                };
-                datas.push(to_add);
-            } else {
-                panic!()
-            }
+            let dic = match value {
+                FlowDataValue::RefDic(dicid) => get_dictionary(source, *dicid as usize)?,
+                _ => return Err(FlowDataError::InvalidFollowEntry(value.clone())),
+            };
+            if dic.len() != 1 {
+                return Err(FlowDataError::InvalidFollowEntry(value.clone()));
+            };
+            let (key, node_value) = dic.iter().next().unwrap();
+            let dicid = match node_value {
+                FlowDataValue::RefDic(refid) => *refid as usize,
+                _ => return Err(FlowDataError::InvalidFollowEntry(value.clone())),
+            };
+            let registry = tempory.registry;
+            datas.push(registry.decode(key, source, tempory, dicid)?);
         }
-        Self(datas)
+        Ok(Self(datas))
     }
 
-    fn generate(&self, dest: &mut FlowData) -> u16 {
+    /// add this list of node to a [`FlowData`], returning the id of the created vector
+    pub fn generate(&self, dest: &mut FlowData) -> Result<u16, FlowDataError> {
         let mut datas = Vec::new();
         for data in &self.0 {
-            let (reference, dicid) = match data {
-                OutputEnum::FollowGroup(group) => ("Group", group.generate(dest)),
-                OutputEnum::Scenario(scenario) => ("Scenario", scenario.generate(dest)),
-                OutputEnum::FreeMove(freemove) => ("FreeMove", freemove.generate(dest)),
-                OutputEnum::ScenarioWithBranch(scenario_with_branch) => {
-                    ("ScenarioWithBranch", scenario_with_branch.generate(dest))
-                }
-                OutputEnum::Dungeon(dungeon) => ("Dungeon", dungeon.generate(dest)),
-                OutputEnum::DungeonEnd(dungeon_end) => ("DungeonEnd", dungeon_end.generate(dest)),
-                OutputEnum::AskSave(ask_save) => ("AskSave", ask_save.generate(dest)),
-                OutputEnum::DgFlowBranchSetCounter(dg_flow_branch_set_counter) => (
-                    "DgFlowBranchSetCounter",
-                    dg_flow_branch_set_counter.generate(dest),
-                ),
-                OutputEnum::DgFlowBranch(dg_flow_branch) => {
-                    ("DgFlowBranch", dg_flow_branch.generate(dest))
-                }
-                OutputEnum::DgStagingPost(dg_staging_post) => {
-                    ("DgStagingPost", dg_staging_post.generate(dest))
-                }
-                OutputEnum::ScenarioWithProgNo(scenario_with_prog_no) => {
-                    ("ScenarioWithProgNo", scenario_with_prog_no.generate(dest))
-                }
-                OutputEnum::FreeMoveEvent(free_move_event) => {
-                    ("FreeMoveEvent", free_move_event.generate(dest))
-                }
-            };
+            let dicid = data.as_node().generate(dest)?;
             let mut dic = HashMap::new();
-            dic.insert(reference.to_string(), FlowDataValue::RefDic(dicid));
-            datas.push(FlowDataValue::RefDic(dest.push_dictionary(dic).unwrap()));
+            dic.insert(data.key().to_string(), FlowDataValue::RefDic(dicid));
+            datas.push(FlowDataValue::RefDic(dest.push_dictionary(dic)?));
         }
-        dest.push_vector(datas).unwrap()
+        dest.push_vector(datas)
     }
 }
 
-#[derive(Default)]
-struct FlowDataTempory {
-    pub idname_set: HashMap<String, (String, usize)>,
+impl From<Vec<OutputEnum>> for Follow {
+    fn from(nodes: Vec<OutputEnum>) -> Self {
+        Self(nodes)
+    }
+}
+
+impl std::iter::FromIterator<OutputEnum> for Follow {
+    fn from_iter<T: IntoIterator<Item = OutputEnum>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for Follow {
+    type Item = OutputEnum;
+    type IntoIter = std::vec::IntoIter<OutputEnum>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Follow {
+    type Item = &'a OutputEnum;
+    type IntoIter = std::slice::Iter<'a, OutputEnum>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Follow {
+    type Item = &'a mut OutputEnum;
+    type IntoIter = std::slice::IterMut<'a, OutputEnum>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
+
+impl From<Follow> for FlowDataOutput {
+    fn from(output: Follow) -> Self {
+        Self { output }
+    }
+}
+
+/// The state shared while decoding a [`FlowData`] into a [`FlowDataOutput`]. It is given to
+/// [`FlowNode::decode`], and hold the [`NodeRegistry`] used to decode the nodes of a group. It
+/// can only be created by [`FlowDataOutput::new_with_registry`].
+pub struct FlowDataTempory<'a> {
+    registry: &'a NodeRegistry,
+}
+
+impl<'a> FlowDataTempory<'a> {
+    fn new(registry: &'a NodeRegistry) -> Self {
+        Self { registry }
+    }
 }
 
 /// A [`FlowData`] representation that can serialized and deserialized, and is easily modifable by
 /// human.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct FlowDataOutput {
     output: Follow,
 }
 
 impl FlowDataOutput {
-    /// Create a new [`FlowDataOutput`] from a [`FlowData`]
-    ///
-    /// # Panics
-    ///
-    /// This function will panic if anything in the [`FlowData`] is invalid
+    /// Create a new [`FlowDataOutput`] from a [`FlowData`], with the built-in node types.
+    /// Return an error if a node is missing a key, or has a value of the wrong kind.
     #[allow(clippy::needless_pass_by_value)]
-    pub fn new(source: FlowData) -> FlowDataOutput {
-        let top_vec = source.get_vector(source.vector_len() - 1).unwrap();
-        let mut tempory = FlowDataTempory::default();
-        let output = Follow::new(&source, &mut tempory, top_vec);
-        FlowDataOutput { output }
+    pub fn new(source: FlowData) -> Result<FlowDataOutput, FlowDataError> {
+        Self::new_with_registry(source, &NodeRegistry::default())
+    }
+
+    /// Create a new [`FlowDataOutput`] from a [`FlowData`], decoding node with the given
+    /// [`NodeRegistry`].
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn new_with_registry(
+        source: FlowData,
+        registry: &NodeRegistry,
+    ) -> Result<FlowDataOutput, FlowDataError> {
+        let top_vecid = source
+            .vector_len()
+            .checked_sub(1)
+            .ok_or(FlowDataError::EmptyFollow)?;
+        let top_vec = get_vector(&source, top_vecid)?;
+        let mut tempory = FlowDataTempory::new(registry);
+        let output = Follow::decode(&source, &mut tempory, top_vec)?;
+        Ok(FlowDataOutput { output })
+    }
+
+    /// return the top level list of node
+    pub fn output(&self) -> &Follow {
+        &self.output
+    }
+
+    /// return the top level list of node, mutably
+    pub fn output_mut(&mut self) -> &mut Follow {
+        &mut self.output
     }
 
     /// Create a new [`FlowData`] from this struct
-    ///
-    /// # Panics
-    ///
-    /// This function will panic if the input file is invalid
-    pub fn generate_flowdata(&self) -> FlowData {
+    pub fn generate_flowdata(&self) -> Result<FlowData, FlowDataError> {
         // those first vec/dic are placed somewhere with limited storage
         let mut result = FlowData::default();
-        let mut first_vec = Vec::new();
-        first_vec.push(FlowDataValue::String("".into()));
-        result.push_vector(first_vec).unwrap();
+        let first_vec = vec![FlowDataValue::String("".into())];
+        result.push_vector(first_vec)?;
         let first_dic = HashMap::new();
-        result.push_dictionary(first_dic).unwrap();
-        self.output.generate(&mut result);
-        result
+        result.push_dictionary(first_dic)?;
+        self.output.generate(&mut result)?;
+        Ok(result)
     }
 }
@@ -0,0 +1,780 @@
+use crate::diff::{compare_nodes, match_nodes, node_fields, node_idnames, LinkDirection};
+use crate::{FlowDataOutput, Follow, NodePath, OutputEnum};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+
+/// A reference to a node, used by a [`PatchOperation`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NodeRef {
+    /// the first node declaring this `socket_in` idname
+    Idname(String),
+    /// the group found by following these group names, from the outermost
+    Group(Vec<String>),
+    /// the node at this path. It change when a node is inserted or deleted before it, so it is
+    /// only used for node that can't be referenced otherwise.
+    Path(NodePath),
+}
+
+impl NodeRef {
+    /// return the path of the referenced node in `flow`, if it exist
+    pub fn resolve(&self, flow: &FlowDataOutput) -> Option<NodePath> {
+        match self {
+            Self::Idname(idname) => flow
+                .nodes()
+                .into_iter()
+                .find(|(_, node)| node_idnames(node).contains(idname))
+                .map(|(path, _)| path),
+            Self::Group(names) if names.is_empty() => None,
+            Self::Group(names) => find_group(flow, names),
+            Self::Path(path) => flow.get_node(path).map(|_| path.clone()),
+        }
+    }
+}
+
+impl fmt::Display for NodeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Idname(idname) => write!(f, "the node with the idname {:?}", idname),
+            Self::Group(names) => write!(f, "the group {:?}", names.join("/")),
+            Self::Path(path) => write!(f, "the node at {}", path),
+        }
+    }
+}
+
+/// An operation of a [`FlowPatch`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PatchOperation {
+    /// insert `node` right after the node `after`, in the same list
+    InsertAfter {
+        /// the node to insert after
+        after: NodeRef,
+        /// the inserted node (with its content for a group)
+        node: OutputEnum,
+    },
+    /// insert `node` in a group
+    AddToGroup {
+        /// the names of the group, from the outermost. Empty for the top level list.
+        group: Vec<String>,
+        /// the index to insert the node at, or None to add it at the end
+        #[serde(default)]
+        position: Option<usize>,
+        /// the inserted node (with its content for a group)
+        node: OutputEnum,
+    },
+    /// change a link of the socket of a node
+    Rewire {
+        /// the node whose socket change
+        node: NodeRef,
+        /// whether this is a `socket_in` or a `socket_out` link
+        direction: LinkDirection,
+        /// the label of the link
+        label: String,
+        /// the idname the link is expected to have, None if it is expected to not exist
+        old: Option<String>,
+        /// the new idname of the link, None to remove it
+        new: Option<String>,
+    },
+    /// change a field of a node, other than its socket or the content of a group
+    SetField {
+        /// the modified node
+        node: NodeRef,
+        /// the name of the field, as in the JSON
+        field: String,
+        /// the value the field is expected to have, null if it is expected to not exist
+        old: Value,
+        /// the new value, null to reset it to its default value
+        new: Value,
+    },
+    /// delete a node (with its content for a group)
+    DeleteNode {
+        /// the deleted node
+        node: NodeRef,
+    },
+}
+
+impl PatchOperation {
+    /// return the reference to the node this operation modify, or insert after
+    pub(crate) fn target_mut(&mut self) -> Option<&mut NodeRef> {
+        match self {
+            Self::InsertAfter { after, .. } => Some(after),
+            Self::AddToGroup { .. } => None,
+            Self::Rewire { node, .. } | Self::SetField { node, .. } | Self::DeleteNode { node } => {
+                Some(node)
+            }
+        }
+    }
+}
+
+impl fmt::Display for PatchOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe_node = |node: &OutputEnum| {
+            format!("{} {:?}", node.key(), node.debugname().unwrap_or_default())
+        };
+        let describe_link = |idname: &Option<String>| match idname {
+            Some(idname) => format!("{:?}", idname),
+            None => "nothing".to_string(),
+        };
+        match self {
+            Self::InsertAfter { after, node } => {
+                write!(f, "insert the {} after {}", describe_node(node), after)
+            }
+            Self::AddToGroup {
+                group,
+                position,
+                node,
+            } => {
+                write!(f, "add the {} to ", describe_node(node))?;
+                if group.is_empty() {
+                    write!(f, "the top level list")?;
+                } else {
+                    write!(f, "the group {:?}", group.join("/"))?;
+                };
+                match position {
+                    Some(position) => write!(f, " at the position {}", position),
+                    None => Ok(()),
+                }
+            }
+            Self::Rewire {
+                node,
+                direction,
+                label,
+                old,
+                new,
+            } => write!(
+                f,
+                "rewire the {} link {:?} of {} from {} to {}",
+                match direction {
+                    LinkDirection::In => "incoming",
+                    LinkDirection::Out => "outgoing",
+                },
+                label,
+                node,
+                describe_link(old),
+                describe_link(new)
+            ),
+            Self::SetField {
+                node,
+                field,
+                old,
+                new,
+            } => write!(
+                f,
+                "set the field {:?} of {} from {} to {}",
+                field, node, old, new
+            ),
+            Self::DeleteNode { node } => write!(f, "delete {}", node),
+        }
+    }
+}
+
+/// Why a [`PatchOperation`] couldn't be applied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum PatchConflict {
+    /// no node match the reference
+    NodeNotFound(NodeRef),
+    /// no group has these names
+    GroupNotFound(Vec<String>),
+    /// the position is after the end of the group
+    PositionOutOfRange {
+        /// the position of the inserted node
+        position: usize,
+        /// the number of node in the group
+        len: usize,
+    },
+    /// the inserted node declare an idname another node already declare
+    IdnameExists(String),
+    /// the rewired node doesn't have a socket
+    NoSocket(NodeRef),
+    /// the link doesn't point to the idname the patch expected
+    LinkMismatch {
+        /// the label of the link
+        label: String,
+        /// the idname the patch expected
+        expected: Option<String>,
+        /// the idname found
+        found: Option<String>,
+    },
+    /// the field doesn't have the value the patch expected
+    FieldMismatch {
+        /// the name of the field
+        field: String,
+        /// the value the patch expected
+        expected: Value,
+        /// the value found
+        found: Value,
+    },
+    /// the field can't be set to this value
+    InvalidField {
+        /// the name of the field
+        field: String,
+        /// why it can't be set
+        message: String,
+    },
+}
+
+impl fmt::Display for PatchConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |idname: &Option<String>| match idname {
+            Some(idname) => format!("{:?}", idname),
+            None => "nothing".to_string(),
+        };
+        match self {
+            Self::NodeNotFound(node) => write!(f, "{} doesn't exist", node),
+            Self::GroupNotFound(names) => {
+                write!(f, "the group {:?} doesn't exist", names.join("/"))
+            }
+            Self::PositionOutOfRange { position, len } => write!(
+                f,
+                "can't insert at the position {} of a group of {} nodes",
+                position, len
+            ),
+            Self::IdnameExists(idname) => write!(
+                f,
+                "the idname {:?} of the inserted node is already declared",
+                idname
+            ),
+            Self::NoSocket(node) => write!(f, "{} doesn't have a socket", node),
+            Self::LinkMismatch {
+                label,
+                expected,
+                found,
+            } => write!(
+                f,
+                "the link {:?} was expected to point to {}, but it point to {}",
+                label,
+                describe(expected),
+                describe(found)
+            ),
+            Self::FieldMismatch {
+                field,
+                expected,
+                found,
+            } => write!(
+                f,
+                "the field {:?} was expected to be {}, but it is {}",
+                field, expected, found
+            ),
+            Self::InvalidField { field, message } => {
+                write!(f, "can't set the field {:?}: {}", field, message)
+            }
+        }
+    }
+}
+
+/// The error returned by [`FlowPatch::apply`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PatchError {
+    /// the index of the operation that failed
+    pub operation: usize,
+    /// why it failed
+    pub conflict: PatchConflict,
+}
+
+impl Error for PatchError {}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "operation {} failed: {}", self.operation, self.conflict)
+    }
+}
+
+/// return the path of the group found by following `names` from the top level list (an empty
+/// path if `names` is empty)
+pub(crate) fn find_group(flow: &FlowDataOutput, names: &[String]) -> Option<NodePath> {
+    let mut path = NodePath::default();
+    for name in names {
+        let index = flow.get_follow(&path)?.iter().position(
+            |node| matches!(node, OutputEnum::FollowGroup(group) if group.debug_groupname == *name),
+        )?;
+        path = path.child(index);
+    }
+    Some(path)
+}
+
+/// return the idnames declared by a node and its content
+fn subtree_idnames(node: &OutputEnum, result: &mut Vec<String>) {
+    result.extend(node_idnames(node));
+    for child in node.children().into_iter().flat_map(Follow::iter) {
+        subtree_idnames(child, result);
+    }
+}
+
+/// return an error if `node` declare an idname already declared in `flow`
+fn check_idnames(flow: &FlowDataOutput, node: &OutputEnum) -> Result<(), PatchConflict> {
+    let declared = flow.declared_idnames();
+    let mut idnames = Vec::new();
+    subtree_idnames(node, &mut idnames);
+    match idnames.into_iter().find(|idname| declared.contains(idname)) {
+        Some(idname) => Err(PatchConflict::IdnameExists(idname)),
+        None => Ok(()),
+    }
+}
+
+fn resolve(flow: &FlowDataOutput, node: &NodeRef) -> Result<NodePath, PatchConflict> {
+    node.resolve(flow)
+        .ok_or_else(|| PatchConflict::NodeNotFound(node.clone()))
+}
+
+fn set_field(
+    node: &mut OutputEnum,
+    field: &str,
+    old: &Value,
+    new: &Value,
+) -> Result<(), PatchConflict> {
+    let invalid = |message: String| PatchConflict::InvalidField {
+        field: field.to_string(),
+        message,
+    };
+    if field == "socket" || field == "data" {
+        return Err(invalid(
+            "the socket and the content of a group are changed by the other operations".into(),
+        ));
+    };
+    if let OutputEnum::Custom(_) = node {
+        return Err(invalid("the fields of a custom node can't be set".into()));
+    };
+    let found = node_fields(node).remove(field).unwrap_or(Value::Null);
+    if found != *old {
+        return Err(PatchConflict::FieldMismatch {
+            field: field.to_string(),
+            expected: old.clone(),
+            found,
+        });
+    };
+
+    let mut json = serde_json::to_value(&*node).map_err(|err| invalid(err.to_string()))?;
+    if let Some(Value::Object(fields)) = json
+        .as_object_mut()
+        .and_then(|object| object.values_mut().next())
+    {
+        if new.is_null() {
+            fields.remove(field);
+        } else {
+            fields.insert(field.to_string(), new.clone());
+        }
+    };
+    let changed: OutputEnum =
+        serde_json::from_value(json).map_err(|err| invalid(err.to_string()))?;
+    if !new.is_null() && node_fields(&changed).get(field) != Some(new) {
+        return Err(invalid(format!(
+            "a {} node doesn't have this field",
+            node.key()
+        )));
+    };
+    *node = changed;
+    Ok(())
+}
+
+/// apply `operation` to `flow`, leaving it unchanged on error
+pub(crate) fn apply_operation(
+    flow: &mut FlowDataOutput,
+    operation: &PatchOperation,
+) -> Result<(), PatchConflict> {
+    match operation {
+        PatchOperation::InsertAfter { after, node } => {
+            let path = resolve(flow, after)?;
+            check_idnames(flow, node)?;
+            let follow = flow.get_follow_mut(&path.parent().unwrap()).unwrap();
+            follow.insert(path.index().unwrap() + 1, node.clone());
+        }
+        PatchOperation::AddToGroup {
+            group,
+            position,
+            node,
+        } => {
+            let path = find_group(flow, group)
+                .ok_or_else(|| PatchConflict::GroupNotFound(group.clone()))?;
+            check_idnames(flow, node)?;
+            let follow = flow.get_follow_mut(&path).unwrap();
+            let position = position.unwrap_or_else(|| follow.len());
+            if position > follow.len() {
+                return Err(PatchConflict::PositionOutOfRange {
+                    position,
+                    len: follow.len(),
+                });
+            };
+            follow.insert(position, node.clone());
+        }
+        PatchOperation::Rewire {
+            node,
+            direction,
+            label,
+            old,
+            new,
+        } => {
+            let path = resolve(flow, node)?;
+            let socket = flow
+                .get_node_mut(&path)
+                .unwrap()
+                .socket_mut()
+                .ok_or_else(|| PatchConflict::NoSocket(node.clone()))?;
+            let links = match direction {
+                LinkDirection::In => &mut socket.socket_in,
+                LinkDirection::Out => &mut socket.socket_out,
+            };
+            let found = links.get(label).cloned();
+            if found != *old {
+                return Err(PatchConflict::LinkMismatch {
+                    label: label.clone(),
+                    expected: old.clone(),
+                    found,
+                });
+            };
+            match new {
+                Some(new) => links.insert(label.clone(), new.clone()),
+                None => links.remove(label),
+            };
+        }
+        PatchOperation::SetField {
+            node,
+            field,
+            old,
+            new,
+        } => {
+            let path = resolve(flow, node)?;
+            set_field(flow.get_node_mut(&path).unwrap(), field, old, new)?;
+        }
+        PatchOperation::DeleteNode { node } => {
+            let path = resolve(flow, node)?;
+            let follow = flow.get_follow_mut(&path.parent().unwrap()).unwrap();
+            follow.remove(path.index().unwrap());
+        }
+    }
+    Ok(())
+}
+
+/// return a reference to the node at `path`: one of its idname, its group names, or its path if
+/// neither of them find it
+pub(crate) fn reference(flow: &FlowDataOutput, path: &NodePath) -> NodeRef {
+    if let Some(node) = flow.get_node(path) {
+        for idname in node_idnames(node) {
+            let candidate = NodeRef::Idname(idname);
+            if candidate.resolve(flow).as_ref() == Some(path) {
+                return candidate;
+            }
+        }
+        if let OutputEnum::FollowGroup(group) = node {
+            let mut names: Vec<String> = flow
+                .group_names(path)
+                .into_iter()
+                .map(str::to_string)
+                .collect();
+            names.push(group.debug_groupname.clone());
+            if find_group(flow, &names).as_ref() == Some(path) {
+                return NodeRef::Group(names);
+            }
+        }
+    }
+    NodeRef::Path(path.clone())
+}
+
+/// return a reference, valid in `flow`, to the node at `path` in `new`. Only idnames and group
+/// names are used, as the paths differ.
+pub(crate) fn anchor(
+    flow: &FlowDataOutput,
+    new: &FlowDataOutput,
+    path: &NodePath,
+) -> Option<NodeRef> {
+    let node = new.get_node(path)?;
+    let groups = new.group_names(path);
+    for idname in node_idnames(node) {
+        let candidate = NodeRef::Idname(idname);
+        if let Some(found) = candidate.resolve(flow) {
+            if flow.group_names(&found) == groups {
+                return Some(candidate);
+            }
+        }
+    }
+    if let OutputEnum::FollowGroup(group) = node {
+        let mut names: Vec<String> = groups.into_iter().map(str::to_string).collect();
+        names.push(group.debug_groupname.clone());
+        if find_group(flow, &names).is_some() {
+            return Some(NodeRef::Group(names));
+        }
+    }
+    None
+}
+
+/// return the sorted paths, without the ones contained in another path of the list
+fn outermost(mut paths: Vec<NodePath>) -> Vec<NodePath> {
+    paths.sort();
+    let mut result: Vec<NodePath> = Vec::new();
+    for path in paths {
+        match result.last() {
+            Some(last) if last.contains(&path) => (),
+            _ => result.push(path),
+        }
+    }
+    result
+}
+
+/// return the path the node at `path` has once the nodes at `deleted` are removed
+fn shift_path(path: &NodePath, deleted: &[NodePath]) -> NodePath {
+    let mut result = path.clone();
+    for (depth, index) in result.0.iter_mut().enumerate() {
+        let before = deleted
+            .iter()
+            .filter(|other| {
+                other.0.len() == depth + 1
+                    && other.0[..depth] == path.0[..depth]
+                    && other.0[depth] < path.0[depth]
+            })
+            .count();
+        *index -= before;
+    }
+    result
+}
+
+/// An ordered list of operations on a [`FlowDataOutput`], that can be distributed instead of a
+/// whole flow file, and applied on top of each other.
+///
+/// Nodes are referenced by one of their `socket_in` idname when possible, and operations record
+/// the value they expect to replace, so applying a patch on a flow modified in the same place
+/// fails instead of silently overwriting the change.
+///
+/// It can be serialized (to JSON for example).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FlowPatch {
+    /// the operations, applied in order
+    pub operations: Vec<PatchOperation>,
+}
+
+impl fmt::Display for FlowPatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for operation in &self.operations {
+            writeln!(f, "{}", operation)?;
+        }
+        Ok(())
+    }
+}
+
+impl FlowPatch {
+    /// Create a new empty [`FlowPatch`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply the operations to `flow`, in order. If an operation fails, return which one and
+    /// why, and leave `flow` unchanged.
+    pub fn apply(&self, flow: &mut FlowDataOutput) -> Result<(), PatchError> {
+        let mut patched = flow.clone();
+        for (index, operation) in self.operations.iter().enumerate() {
+            apply_operation(&mut patched, operation).map_err(|conflict| PatchError {
+                operation: index,
+                conflict,
+            })?;
+        }
+        *flow = patched;
+        Ok(())
+    }
+
+    /// apply `operation` to `working`, and add it to the patch
+    fn push(&mut self, working: &mut FlowDataOutput, operation: PatchOperation) {
+        // an operation that fails is still kept, so applying the patch report the conflict
+        let _ = apply_operation(working, &operation);
+        self.operations.push(operation);
+    }
+
+    /// Create a patch that turn `old` into `new`.
+    ///
+    /// Nodes are paired like in [`crate::FlowDiff`]. The nodes that were removed, moved to another
+    /// group or whose type changed are deleted, the changes of the other nodes are set, then the
+    /// new nodes are inserted after their previous node in `new`. Moving a node in the same list
+    /// isn't recorded.
+    pub fn create(old: &FlowDataOutput, new: &FlowDataOutput) -> Self {
+        let (pairs, removed, added) = match_nodes(old, new);
+        let mut deleted = removed;
+        let mut inserted = added;
+        let mut kept = Vec::new();
+        for (old_path, new_path) in pairs {
+            let moved = old.get_node(&old_path).map(OutputEnum::key)
+                != new.get_node(&new_path).map(OutputEnum::key)
+                || old.group_names(&old_path) != new.group_names(&new_path);
+            if moved {
+                deleted.push(old_path);
+                inserted.push(new_path);
+            } else {
+                kept.push((old_path, new_path));
+            }
+        }
+        let deleted = outermost(deleted);
+        let inserted = outermost(inserted);
+
+        let mut patch = Self::new();
+        let mut working = old.clone();
+        for path in deleted.iter().rev() {
+            let node = reference(&working, path);
+            patch.push(&mut working, PatchOperation::DeleteNode { node });
+        }
+
+        for (old_path, new_path) in &kept {
+            let path = shift_path(old_path, &deleted);
+            let (fields, links) = compare_nodes(
+                old.get_node(old_path).unwrap(),
+                new.get_node(new_path).unwrap(),
+            );
+            for change in fields {
+                let operation = PatchOperation::SetField {
+                    node: reference(&working, &path),
+                    field: change.field,
+                    old: change.old,
+                    new: change.new,
+                };
+                patch.push(&mut working, operation);
+            }
+            for link in links {
+                let operation = PatchOperation::Rewire {
+                    node: reference(&working, &path),
+                    direction: link.direction,
+                    label: link.label,
+                    old: link.old,
+                    new: link.new,
+                };
+                patch.push(&mut working, operation);
+            }
+        }
+
+        for path in &inserted {
+            let node = new.get_node(path).unwrap().clone();
+            let index = path.index().unwrap();
+            let after = match index {
+                0 => None,
+                _ => anchor(&working, new, &path.parent().unwrap().child(index - 1)),
+            };
+            let operation = match after {
+                Some(after) => PatchOperation::InsertAfter { after, node },
+                None => {
+                    let group: Vec<String> = new
+                        .group_names(path)
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect();
+                    let len = find_group(&working, &group)
+                        .and_then(|group| working.get_follow(&group).map(Follow::len))
+                        .unwrap_or(0);
+                    PatchOperation::AddToGroup {
+                        group,
+                        position: Some(index.min(len)),
+                        node,
+                    }
+                }
+            };
+            patch.push(&mut working, operation);
+        }
+        patch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, assert_same, scenario};
+
+    fn flow(main: Vec<Value>, side: Vec<Value>) -> FlowDataOutput {
+        test_util::flow(vec![("Main", main), ("Side", side)])
+    }
+
+    fn base() -> FlowDataOutput {
+        flow(
+            vec![
+                scenario("a", "a01", Some("b")),
+                scenario("b", "b01", Some("c")),
+                scenario("c", "c01", None),
+            ],
+            vec![
+                scenario("s1", "s01", Some("s2")),
+                scenario("s2", "s02", None),
+            ],
+        )
+    }
+
+    #[test]
+    fn create_then_apply_give_the_new_flow() {
+        let old = base();
+        let new = flow(
+            vec![
+                scenario("a", "a01", Some("b")),
+                scenario("b", "b02", Some("x")),
+                scenario("x", "x01", Some("c")),
+                scenario("c", "c01", None),
+            ],
+            vec![scenario("s2", "s02", None)],
+        );
+        let patch = FlowPatch::create(&old, &new);
+
+        let mut patched = old.clone();
+        patch.apply(&mut patched).unwrap();
+        assert_same(&patched, &new);
+
+        // the serialized patch give the same result
+        let patch: FlowPatch =
+            serde_json::from_str(&serde_json::to_string(&patch).unwrap()).unwrap();
+        let mut patched = old;
+        patch.apply(&mut patched).unwrap();
+        assert_same(&patched, &new);
+    }
+
+    #[test]
+    fn delete_every_node_of_a_group() {
+        let old = base();
+        let new = flow(
+            vec![
+                scenario("a", "a01", Some("b")),
+                scenario("b", "b01", Some("c")),
+                scenario("c", "c01", None),
+            ],
+            vec![],
+        );
+        let patch = FlowPatch::create(&old, &new);
+        assert!(patch
+            .operations
+            .iter()
+            .all(|operation| matches!(operation, PatchOperation::DeleteNode { .. })));
+        assert_eq!(patch.operations.len(), 2);
+
+        let mut patched = old;
+        patch.apply(&mut patched).unwrap();
+        assert_same(&patched, &new);
+    }
+
+    #[test]
+    fn same_flow_give_an_empty_patch() {
+        assert!(FlowPatch::create(&base(), &base()).operations.is_empty());
+    }
+
+    #[test]
+    fn conflicting_apply_leave_the_flow_unchanged() {
+        let old = base();
+        let mut new = base();
+        new.get_node_mut(&NodePath(vec![0, 1]))
+            .unwrap()
+            .socket_mut()
+            .unwrap()
+            .socket_out
+            .insert("next".into(), "s1".into());
+        let patch = FlowPatch::create(&old, &new);
+
+        let mut modified = base();
+        modified
+            .get_node_mut(&NodePath(vec![0, 1]))
+            .unwrap()
+            .socket_mut()
+            .unwrap()
+            .socket_out
+            .insert("next".into(), "a".into());
+        let unchanged = modified.clone();
+        let error = patch.apply(&mut modified).unwrap_err();
+        assert_eq!(error.operation, 0);
+        assert_eq!(
+            error.conflict,
+            PatchConflict::LinkMismatch {
+                label: "next".into(),
+                expected: Some("c".into()),
+                found: Some("a".into()),
+            }
+        );
+        assert_same(&modified, &unchanged);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{FlowDataOutput, Follow, OutputEnum};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// The position of a node in a [`FlowDataOutput`]: its index in the top level [`Follow`], then its
+/// index in each nested group.
+///
+/// It is displayed (and parsed) as the indexes separated by `/`, like `0/12/3`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NodePath(pub Vec<usize>);
+
+impl NodePath {
+    /// return the path of the group containing this node, or None for a top level node
+    pub fn parent(&self) -> Option<NodePath> {
+        if self.0.is_empty() {
+            None
+        } else {
+            Some(NodePath(self.0[..self.0.len() - 1].to_vec()))
+        }
+    }
+
+    /// return the path of the child `index` of this node
+    pub fn child(&self, index: usize) -> NodePath {
+        let mut path = self.0.clone();
+        path.push(index);
+        NodePath(path)
+    }
+
+    /// return the index of this node in its parent list
+    pub fn index(&self) -> Option<usize> {
+        self.0.last().copied()
+    }
+
+    /// return true if `other` is this node or one of its descendant
+    pub fn contains(&self, other: &NodePath) -> bool {
+        other.0.starts_with(&self.0)
+    }
+}
+
+impl fmt::Display for NodePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (count, index) in self.0.iter().enumerate() {
+            if count != 0 {
+                write!(f, "/")?;
+            }
+            write!(f, "{}", index)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for NodePath {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(NodePath::default());
+        };
+        Ok(NodePath(
+            s.split('/')
+                .map(str::parse)
+                .collect::<Result<Vec<usize>, _>>()?,
+        ))
+    }
+}
+
+fn collect_nodes<'a>(
+    follow: &'a Follow,
+    parent: &NodePath,
+    result: &mut Vec<(NodePath, &'a OutputEnum)>,
+) {
+    for (index, node) in follow.iter().enumerate() {
+        let path = parent.child(index);
+        result.push((path.clone(), node));
+        if let Some(children) = node.children() {
+            collect_nodes(children, &path, result);
+        }
+    }
+}
+
+impl FlowDataOutput {
+    /// return every node (groups included) with their path, with a parent before its children
+    pub fn nodes(&self) -> Vec<(NodePath, &OutputEnum)> {
+        let mut result = Vec::new();
+        collect_nodes(self.output(), &NodePath::default(), &mut result);
+        result
+    }
+
+    /// return the node at `path`, if it exist
+    pub fn get_node(&self, path: &NodePath) -> Option<&OutputEnum> {
+        let (last, parents) = path.0.split_last()?;
+        let mut follow = self.output();
+        for index in parents {
+            follow = follow.get(*index)?.children()?;
+        }
+        follow.get(*last)
+    }
+
+    /// return the node at `path` mutably, if it exist
+    pub fn get_node_mut(&mut self, path: &NodePath) -> Option<&mut OutputEnum> {
+        let (last, parents) = path.0.split_last()?;
+        let mut follow = self.output_mut();
+        for index in parents {
+            follow = follow.get_mut(*index)?.children_mut()?;
+        }
+        follow.get_mut(*last)
+    }
+
+    /// return the list of node at `path`: the top level list for an empty path, or the content
+    /// of the group at `path`
+    pub fn get_follow(&self, path: &NodePath) -> Option<&Follow> {
+        if path.0.is_empty() {
+            Some(self.output())
+        } else {
+            self.get_node(path)?.children()
+        }
+    }
+
+    /// return the list of node at `path` mutably, like [`FlowDataOutput::get_follow`]
+    pub fn get_follow_mut(&mut self, path: &NodePath) -> Option<&mut Follow> {
+        if path.0.is_empty() {
+            Some(self.output_mut())
+        } else {
+            self.get_node_mut(path)?.children_mut()
+        }
+    }
+
+    /// return the name of the groups containing the node at `path`, from the outermost
+    pub fn group_names(&self, path: &NodePath) -> Vec<&str> {
+        let mut result = Vec::new();
+        let mut follow = self.output();
+        for index in path.0.iter().take(path.0.len().saturating_sub(1)) {
+            match follow.get(*index) {
+                Some(OutputEnum::FollowGroup(group)) => {
+                    result.push(group.debug_groupname.as_str());
+                    follow = &group.data;
+                }
+                _ => break,
+            }
+        }
+        result
+    }
+}
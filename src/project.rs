@@ -0,0 +1,362 @@
+use crate::{DebugMenuTag, FlowDataOutput, Follow, FollowGroup, Layout, OutputEnum};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const MANIFEST_NAME: &str = "manifest.json";
+
+/// The error returned when reading or writing a project directory
+#[derive(Debug)]
+pub enum ProjectError {
+    /// an input/output error happened on this file
+    Io(PathBuf, io::Error),
+    /// this file isn't valid JSON, or doesn't contain the expected value
+    Json(PathBuf, serde_json::Error),
+    /// a manifest list an entry that can't be used as a file name (like `../a.json`)
+    InvalidEntry(PathBuf, String),
+    /// the manifest of a directory listed as a group doesn't contain the fields of the group
+    MissingGroup(PathBuf),
+}
+
+impl Error for ProjectError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(_, err) => Some(err),
+            Self::Json(_, err) => Some(err),
+            Self::InvalidEntry(_, _) | Self::MissingGroup(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            Self::Json(path, err) => write!(f, "{}: {}", path.display(), err),
+            Self::InvalidEntry(path, entry) => write!(
+                f,
+                "{}: the entry {:?} isn't a file name in this directory",
+                path.display(),
+                entry
+            ),
+            Self::MissingGroup(path) => {
+                write!(f, "{}: the fields of the group are missing", path.display())
+            }
+        }
+    }
+}
+
+/// The fields of a [`FollowGroup`] other than its nodes
+#[derive(Serialize, Deserialize)]
+struct GroupHeader {
+    debug_groupname: String,
+    #[serde(default)]
+    layout: Layout,
+    #[serde(default)]
+    debugmenu_tag: DebugMenuTag,
+}
+
+/// The content of the `manifest.json` file of a directory
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    /// the group stored in this directory. None for the top level directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    group: Option<GroupHeader>,
+    /// the file (for a node) or directory (for a group) of each node, in order
+    nodes: Vec<String>,
+}
+
+/// return `name` with the characters that can't be in a portable file name replaced by `_`
+fn sanitize(name: &str) -> String {
+    let result: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    result.trim_start_matches('.').to_string()
+}
+
+/// return a name for the file or directory of `node`, that isn't in `used` (ignoring case)
+fn entry_name(node: &OutputEnum, used: &mut HashSet<String>) -> String {
+    let mut base = sanitize(node.debugname().unwrap_or_default());
+    if base.is_empty() {
+        base = sanitize(node.key());
+    };
+    let extension = match node {
+        OutputEnum::FollowGroup(_) => "",
+        _ => ".json",
+    };
+    let name = (1..)
+        .map(|number| match number {
+            1 => format!("{}{}", base, extension),
+            number => format!("{}_{}{}", base, number, extension),
+        })
+        .find(|name| !used.contains(&name.to_lowercase()))
+        .unwrap();
+    used.insert(name.to_lowercase());
+    name
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), ProjectError> {
+    let mut content =
+        serde_json::to_vec_pretty(value).map_err(|err| ProjectError::Json(path.into(), err))?;
+    content.push(b'\n');
+    fs::write(path, content).map_err(|err| ProjectError::Io(path.into(), err))
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T, ProjectError> {
+    let content = fs::read(path).map_err(|err| ProjectError::Io(path.into(), err))?;
+    serde_json::from_slice(&content).map_err(|err| ProjectError::Json(path.into(), err))
+}
+
+/// return the path of the entry `name` of the manifest of `directory`, refusing names that would
+/// go outside of it
+fn entry_path(directory: &Path, name: &str) -> Result<PathBuf, ProjectError> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(std::path::Component::Normal(_)), None) => Ok(directory.join(name)),
+        _ => Err(ProjectError::InvalidEntry(
+            directory.join(MANIFEST_NAME),
+            name.to_string(),
+        )),
+    }
+}
+
+fn write_directory(
+    directory: &Path,
+    group: Option<GroupHeader>,
+    follow: &Follow,
+) -> Result<(), ProjectError> {
+    fs::create_dir_all(directory).map_err(|err| ProjectError::Io(directory.into(), err))?;
+    let manifest_path = directory.join(MANIFEST_NAME);
+    let previous: Vec<String> = if manifest_path.exists() {
+        read_json::<Manifest>(&manifest_path)?.nodes
+    } else {
+        Vec::new()
+    };
+
+    let mut used = HashSet::new();
+    used.insert(MANIFEST_NAME.to_string());
+    let mut manifest = Manifest {
+        group,
+        nodes: Vec::new(),
+    };
+    for node in follow.iter() {
+        let name = entry_name(node, &mut used);
+        let path = directory.join(&name);
+        match node {
+            OutputEnum::FollowGroup(group) => {
+                if path.is_file() {
+                    fs::remove_file(&path).map_err(|err| ProjectError::Io(path.clone(), err))?;
+                };
+                let header = GroupHeader {
+                    debug_groupname: group.debug_groupname.clone(),
+                    layout: group.layout.clone(),
+                    debugmenu_tag: group.debugmenu_tag.clone(),
+                };
+                write_directory(&path, Some(header), &group.data)?;
+            }
+            node => {
+                if path.is_dir() {
+                    fs::remove_dir_all(&path).map_err(|err| ProjectError::Io(path.clone(), err))?;
+                };
+                write_json(&path, node)?;
+            }
+        }
+        manifest.nodes.push(name);
+    }
+
+    // remove the files of the nodes that are no longer there
+    for name in previous {
+        if used.contains(&name.to_lowercase()) {
+            continue;
+        };
+        let path = entry_path(directory, &name)?;
+        let result = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else if path.is_file() {
+            fs::remove_file(&path)
+        } else {
+            Ok(())
+        };
+        result.map_err(|err| ProjectError::Io(path, err))?;
+    }
+    write_json(&manifest_path, &manifest)
+}
+
+fn read_directory(directory: &Path) -> Result<(Option<GroupHeader>, Follow), ProjectError> {
+    let manifest: Manifest = read_json(&directory.join(MANIFEST_NAME))?;
+    let mut follow = Follow::new();
+    for name in &manifest.nodes {
+        let path = entry_path(directory, name)?;
+        if path.is_dir() {
+            let (header, data) = read_directory(&path)?;
+            let header =
+                header.ok_or_else(|| ProjectError::MissingGroup(path.join(MANIFEST_NAME)))?;
+            follow.push(OutputEnum::FollowGroup(FollowGroup {
+                debug_groupname: header.debug_groupname,
+                data,
+                layout: header.layout,
+                debugmenu_tag: header.debugmenu_tag,
+            }));
+        } else {
+            follow.push(read_json(&path)?);
+        }
+    }
+    Ok((manifest.group, follow))
+}
+
+impl FlowDataOutput {
+    /// Write this flow to `directory` as a project, that is easier to edit and to track in
+    /// version control than a single JSON file.
+    ///
+    /// Each node is written to its own JSON file, named after its debugname, and each
+    /// [`FollowGroup`] to its own directory, named after its `debug_groupname`. A `manifest.json`
+    /// file in each directory list the nodes in order (and the fields of the group). Names are
+    /// made unique by appending a number (like `intro_2.json`).
+    ///
+    /// If `directory` already contain a project, it is updated: the files of the nodes that are
+    /// no longer in the flow are removed. [`FlowDataOutput::read_project`] read it back.
+    pub fn write_project(&self, directory: &Path) -> Result<(), ProjectError> {
+        write_directory(directory, None, self.output())
+    }
+
+    /// Read a project written by [`FlowDataOutput::write_project`]. The result is the same as
+    /// the flow that was written. Only the files listed in the manifests are read.
+    pub fn read_project(directory: &Path) -> Result<Self, ProjectError> {
+        let (_, output) = read_directory(directory)?;
+        Ok(output.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_same, flow, scenario};
+    use serde_json::{json, Value};
+    use std::io::Cursor;
+
+    /// an empty directory for the test `name`, in the temporary directory
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("pmd_flow_{}_{}", name, std::process::id()));
+        if directory.exists() {
+            fs::remove_dir_all(&directory).unwrap();
+        };
+        directory
+    }
+
+    fn group(name: &str, data: Vec<Value>) -> Value {
+        json!({ "FollowGroup": { "debug_groupname": name, "data": data } })
+    }
+
+    fn base() -> FlowDataOutput {
+        let mut other_intro = scenario("intro_b", "i02", None);
+        other_intro["Scenario"]["debugname"] = "Intro".into();
+        flow(vec![
+            (
+                "Main",
+                vec![
+                    scenario("intro", "i01", Some("intro_b")),
+                    other_intro,
+                    group("Sub", vec![scenario("s/1", "s01", None)]),
+                    json!({ "Custom": { "key": "Unknown", "data": { "value": "1" } } }),
+                ],
+            ),
+            ("Side", vec![scenario("a", "a01", None)]),
+        ])
+    }
+
+    fn binary(output: &FlowDataOutput) -> Vec<u8> {
+        let mut file = Cursor::new(Vec::new());
+        output
+            .generate_flowdata()
+            .unwrap()
+            .write(&mut file)
+            .unwrap();
+        file.into_inner()
+    }
+
+    fn manifest(directory: &Path) -> Vec<String> {
+        read_json::<Manifest>(&directory.join(MANIFEST_NAME))
+            .unwrap()
+            .nodes
+    }
+
+    #[test]
+    fn write_then_read() {
+        let directory = temp_directory("write_then_read");
+        let output = base();
+        output.write_project(&directory).unwrap();
+        let read = FlowDataOutput::read_project(&directory).unwrap();
+        assert_same(&read, &output);
+
+        // the same binary as the one written from the JSON layout
+        let from_json: FlowDataOutput =
+            serde_json::from_str(&serde_json::to_string(&output).unwrap()).unwrap();
+        assert_eq!(binary(&read), binary(&from_json));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn names_are_unique_and_portable() {
+        let directory = temp_directory("names");
+        base().write_project(&directory).unwrap();
+        assert_eq!(manifest(&directory), vec!["Main", "Side"]);
+        let main = directory.join("Main");
+        assert_eq!(
+            manifest(&main),
+            vec!["intro.json", "Intro_2.json", "Sub", "Unknown.json"]
+        );
+        assert_eq!(manifest(&main.join("Sub")), vec!["s_1.json"]);
+        assert!(main.join("Intro_2.json").is_file());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn rewrite_remove_the_old_files() {
+        let directory = temp_directory("rewrite");
+        base().write_project(&directory).unwrap();
+        let main = directory.join("Main");
+        fs::write(main.join("notes.txt"), "kept").unwrap();
+
+        let smaller = flow(vec![
+            ("Main", vec![scenario("intro", "i01", None)]),
+            ("Side", vec![scenario("a", "a01", None)]),
+        ]);
+        smaller.write_project(&directory).unwrap();
+        assert_same(&FlowDataOutput::read_project(&directory).unwrap(), &smaller);
+        assert_eq!(manifest(&main), vec!["intro.json"]);
+        assert!(!main.join("Intro_2.json").exists());
+        assert!(!main.join("Sub").exists());
+        assert!(!main.join("Unknown.json").exists());
+        // files that aren't in the manifest are left alone
+        assert!(main.join("notes.txt").is_file());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn manifest_entry_outside_the_directory() {
+        let directory = temp_directory("outside");
+        base().write_project(&directory).unwrap();
+        write_json(
+            &directory.join(MANIFEST_NAME),
+            &json!({ "nodes": ["../Main"] }),
+        )
+        .unwrap();
+        assert!(matches!(
+            FlowDataOutput::read_project(&directory),
+            Err(ProjectError::InvalidEntry(_, _))
+        ));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
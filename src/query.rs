@@ -0,0 +1,497 @@
+use crate::diff::node_fields;
+use crate::{FlowDataOutput, NodePath, OutputEnum};
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The error returned when a [`FlowQuery`] can't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    /// the column (starting at 1) of the character where the error was found
+    pub column: usize,
+    /// what is wrong
+    pub message: String,
+}
+
+impl Error for QueryError {}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+/// A comparison operator of a [`FlowQuery`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    StartsWith,
+    EndsWith,
+    Contains,
+    Less,
+    Greater,
+    LessOrEqual,
+    GreaterOrEqual,
+}
+
+const OPERATORS: [(&str, Operator); 9] = [
+    ("!=", Operator::NotEqual),
+    ("^=", Operator::StartsWith),
+    ("$=", Operator::EndsWith),
+    ("~=", Operator::Contains),
+    ("<=", Operator::LessOrEqual),
+    (">=", Operator::GreaterOrEqual),
+    ("=", Operator::Equal),
+    ("<", Operator::Less),
+    (">", Operator::Greater),
+];
+
+impl Operator {
+    /// return true if `value` compared with `expected` satisfy this operator
+    fn compare(self, value: &str, expected: &str) -> bool {
+        let numbers = || Some((value.parse::<f64>().ok()?, expected.parse::<f64>().ok()?));
+        match self {
+            Self::Equal => value == expected,
+            Self::NotEqual => value != expected,
+            Self::StartsWith => value.starts_with(expected),
+            Self::EndsWith => value.ends_with(expected),
+            Self::Contains => value.contains(expected),
+            Self::Less => matches!(numbers(), Some((value, expected)) if value < expected),
+            Self::Greater => matches!(numbers(), Some((value, expected)) if value > expected),
+            Self::LessOrEqual => matches!(numbers(), Some((value, expected)) if value <= expected),
+            Self::GreaterOrEqual => {
+                matches!(numbers(), Some((value, expected)) if value >= expected)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+    Quoted(String),
+    Operator(Operator),
+}
+
+/// split `text` in tokens, each with the column it start at
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    let operator_at = |index: usize| {
+        OPERATORS.iter().find(|(spelling, _)| {
+            spelling
+                .chars()
+                .enumerate()
+                .all(|(offset, c)| chars.get(index + offset) == Some(&c))
+        })
+    };
+    while index < chars.len() {
+        let column = index + 1;
+        let c = chars[index];
+        if c.is_whitespace() {
+            index += 1;
+        } else if c == '(' || c == ')' {
+            tokens.push((column, if c == '(' { Token::Open } else { Token::Close }));
+            index += 1;
+        } else if let Some((spelling, operator)) = operator_at(index) {
+            tokens.push((column, Token::Operator(*operator)));
+            index += spelling.len();
+        } else if c == '"' || c == '\'' {
+            let mut value = String::new();
+            index += 1;
+            loop {
+                match chars.get(index) {
+                    None => {
+                        return Err(QueryError {
+                            column,
+                            message: "this quoted value is never closed".into(),
+                        })
+                    }
+                    Some('\\') if index + 1 < chars.len() => {
+                        value.push(chars[index + 1]);
+                        index += 2;
+                    }
+                    Some(end) if *end == c => {
+                        index += 1;
+                        break;
+                    }
+                    Some(other) => {
+                        value.push(*other);
+                        index += 1;
+                    }
+                }
+            }
+            tokens.push((column, Token::Quoted(value)));
+        } else {
+            let mut word = String::new();
+            while index < chars.len()
+                && !chars[index].is_whitespace()
+                && !"()\"'".contains(chars[index])
+                && operator_at(index).is_none()
+            {
+                word.push(chars[index]);
+                index += 1;
+            }
+            tokens.push((column, Token::Word(word)));
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expression {
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Compare {
+        field: String,
+        operator: Operator,
+        value: String,
+    },
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(self.end, |(column, _)| *column)
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, QueryError> {
+        Err(QueryError {
+            column: self.column(),
+            message: message.to_string(),
+        })
+    }
+
+    /// return true, and consume it, if the next token is the keyword `keyword`
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn or(&mut self) -> Result<Expression, QueryError> {
+        let mut result = self.and()?;
+        while self.keyword("or") {
+            result = Expression::Or(Box::new(result), Box::new(self.and()?));
+        }
+        Ok(result)
+    }
+
+    fn and(&mut self) -> Result<Expression, QueryError> {
+        let mut result = self.not()?;
+        while self.keyword("and") {
+            result = Expression::And(Box::new(result), Box::new(self.not()?));
+        }
+        Ok(result)
+    }
+
+    fn not(&mut self) -> Result<Expression, QueryError> {
+        if self.keyword("not") {
+            return Ok(Expression::Not(Box::new(self.not()?)));
+        };
+        if self.peek() == Some(&Token::Open) {
+            self.position += 1;
+            let result = self.or()?;
+            if self.peek() != Some(&Token::Close) {
+                return self.error("expected a closing parenthesis");
+            };
+            self.position += 1;
+            return Ok(result);
+        };
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expression, QueryError> {
+        let field = match self.peek() {
+            Some(Token::Word(field)) => field.clone(),
+            _ => return self.error("expected a field name"),
+        };
+        self.position += 1;
+        let operator = match self.peek() {
+            Some(Token::Operator(operator)) => *operator,
+            _ => return self.error("expected an operator (=, !=, ^=, $=, ~=, <, >, <= or >=)"),
+        };
+        self.position += 1;
+        let value = match self.peek() {
+            Some(Token::Word(value)) | Some(Token::Quoted(value)) => value.clone(),
+            _ => return self.error("expected a value"),
+        };
+        self.position += 1;
+        Ok(Expression::Compare {
+            field,
+            operator,
+            value,
+        })
+    }
+}
+
+/// return a field name without case, `_` or `-`, so `scenarioProgressNo` and
+/// `scenario_progress_no` are the same
+pub(crate) fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// add the string values contained in `value` to `result`: the value itself, or every element of
+/// a list
+fn json_values(value: &Value, result: &mut Vec<String>) {
+    match value {
+        Value::String(text) => result.push(text.clone()),
+        Value::Number(number) => result.push(number.to_string()),
+        Value::Bool(boolean) => result.push(boolean.to_string()),
+        Value::Array(values) => {
+            for value in values {
+                json_values(value, result);
+            }
+        }
+        Value::Null | Value::Object(_) => (),
+    }
+}
+
+/// return the values of `field` for the node at `path`
+fn field_values(
+    flow: &FlowDataOutput,
+    path: &NodePath,
+    node: &OutputEnum,
+    field: &str,
+) -> Vec<String> {
+    let socket = node.socket();
+    match normalize(field).as_str() {
+        "kind" => vec![node.key().to_string()],
+        "path" => vec![path.to_string()],
+        "group" => flow
+            .group_names(path)
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        "idname" => socket
+            .map(|socket| socket.idnames().map(str::to_string).collect())
+            .unwrap_or_default(),
+        "linksto" => socket
+            .map(|socket| socket.socket_out().values().cloned().collect())
+            .unwrap_or_default(),
+        "inlabel" => socket
+            .map(|socket| socket.socket_in().keys().cloned().collect())
+            .unwrap_or_default(),
+        "outlabel" => socket
+            .map(|socket| socket.socket_out().keys().cloned().collect())
+            .unwrap_or_default(),
+        _ => {
+            let mut parts = field.split('.');
+            let first = normalize(parts.next().unwrap_or_default());
+            let fields = node_fields(node);
+            let mut value = fields
+                .iter()
+                .find(|(name, _)| normalize(name) == first)
+                .map(|(_, value)| value);
+            for part in parts {
+                let part = normalize(part);
+                value = value.and_then(Value::as_object).and_then(|object| {
+                    object
+                        .iter()
+                        .find(|(name, _)| normalize(name) == part)
+                        .map(|(_, value)| value)
+                });
+            }
+            let mut result = Vec::new();
+            if let Some(value) = value {
+                json_values(value, &mut result);
+            };
+            result
+        }
+    }
+}
+
+impl Expression {
+    fn matches(&self, flow: &FlowDataOutput, path: &NodePath, node: &OutputEnum) -> bool {
+        match self {
+            Self::And(left, right) => {
+                left.matches(flow, path, node) && right.matches(flow, path, node)
+            }
+            Self::Or(left, right) => {
+                left.matches(flow, path, node) || right.matches(flow, path, node)
+            }
+            Self::Not(expression) => !expression.matches(flow, path, node),
+            Self::Compare {
+                field,
+                operator: Operator::NotEqual,
+                value,
+            } => !field_values(flow, path, node, field).contains(value),
+            Self::Compare {
+                field,
+                operator,
+                value,
+            } => field_values(flow, path, node, field)
+                .iter()
+                .any(|found| operator.compare(found, value)),
+        }
+    }
+}
+
+/// A query selecting nodes of a [`FlowDataOutput`], parsed from a text like
+/// `kind = Dungeon and dungeon ^= d0` or `group = "Main scenario" and scenarioProgressNo > 40`.
+///
+/// A query is made of comparisons `field operator value`, combined with `and`, `or`, `not` and
+/// parenthesis. A value containing spaces or operators is quoted with `"` or `'`. The operators
+/// are `=`, `!=`, `^=` (start with), `$=` (end with), `~=` (contain), and `<`, `>`, `<=` and `>=`,
+/// that compare numbers.
+///
+/// The fields are:
+/// - `kind`: the key of the node (like `Scenario` or `Group`)
+/// - `path`: the path of the node (like `0/3`)
+/// - `group`: the name of the groups containing the node
+/// - `idname`: the idnames of its `socket_in`
+/// - `links_to`: the idnames its `socket_out` point to
+/// - `in_label` and `out_label`: the labels of its `socket_in` and `socket_out`
+/// - any other field of the node, as named in the JSON or in the flow file (case and `_` are
+///   ignored, so `scenarioProgressNo` is `scenario_progress_no`). The field of a nested
+///   structure is accessed with a `.`, like `layout.x`.
+///
+/// When a field has several values (like the elements of `entry`), a comparison is true if one
+/// of them match, except `!=` which is true if none of them is equal to the value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowQuery {
+    expression: Expression,
+}
+
+impl FlowQuery {
+    /// return true if the node at `path` in `flow` match this query
+    pub fn matches(&self, flow: &FlowDataOutput, path: &NodePath) -> bool {
+        match flow.get_node(path) {
+            Some(node) => self.expression.matches(flow, path, node),
+            None => false,
+        }
+    }
+}
+
+impl FromStr for FlowQuery {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+            end: s.chars().count() + 1,
+        };
+        let expression = parser.or()?;
+        if parser.peek().is_some() {
+            return parser.error("expected `and`, `or` or the end of the query");
+        };
+        Ok(Self { expression })
+    }
+}
+
+impl FlowDataOutput {
+    /// return the path of every node (groups included) matching `query`, in order
+    pub fn query(&self, query: &FlowQuery) -> Vec<NodePath> {
+        self.nodes()
+            .into_iter()
+            .filter(|(path, node)| query.expression.matches(self, path, node))
+            .map(|(path, _)| path)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{flow, scenario};
+
+    fn base() -> FlowDataOutput {
+        let mut placed = scenario("c", "c01", None);
+        placed["Scenario"]["layout"] = serde_json::json!({ "x": 40 });
+        flow(vec![
+            (
+                "Main scenario",
+                vec![
+                    scenario("a", "a01", Some("b")),
+                    scenario("b", "b01", Some("c")),
+                    placed,
+                ],
+            ),
+            ("Side", vec![scenario("s", "s01", Some("a"))]),
+        ])
+    }
+
+    fn query(text: &str) -> Vec<String> {
+        base()
+            .query(&text.parse().unwrap())
+            .iter()
+            .map(NodePath::to_string)
+            .collect()
+    }
+
+    fn error(text: &str) -> QueryError {
+        text.parse::<FlowQuery>().unwrap_err()
+    }
+
+    #[test]
+    fn special_fields() {
+        assert_eq!(query("kind = Scenario and group = Side"), vec!["1/0"]);
+        assert_eq!(
+            query("group = \"Main scenario\" and idname != a"),
+            vec!["0/1", "0/2"]
+        );
+        assert_eq!(query("links_to = a"), vec!["1/0"]);
+        assert_eq!(query("outLabel = next and path ^= 0/"), vec!["0/0", "0/1"]);
+        assert_eq!(
+            query("in_label = start and not out_label = next"),
+            vec!["0/2"]
+        );
+    }
+
+    #[test]
+    fn fields_of_the_node() {
+        assert_eq!(query("entry ~= 1 and debugname $= b"), vec!["0/1"]);
+        assert_eq!(query("layout.x >= 40"), vec!["0/2"]);
+        // groups have a layout too
+        assert_eq!(query("Layout.X < 40"), vec!["0", "0/0", "0/1", "1", "1/0"]);
+        assert_eq!(query("flow_type = normal and entry = s01"), vec!["1/0"]);
+    }
+
+    #[test]
+    fn and_bind_tighter_than_or() {
+        assert_eq!(
+            query("idname = a or idname = b and group = Side"),
+            vec!["0/0"]
+        );
+        assert_eq!(
+            query("(idname = a or idname = s) and group = Side"),
+            vec!["1/0"]
+        );
+        assert_eq!(query("NOT (idname = a OR kind = Scenario)"), vec!["0", "1"]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(error("kind =").column, 7);
+        assert_eq!(error("kind Scenario").column, 6);
+        assert_eq!(error("= Scenario").column, 1);
+        assert_eq!(error("kind = 'Scenario").column, 8);
+        assert_eq!(error("(kind = Scenario").column, 17);
+        assert_eq!(error("kind = Scenario idname = a").column, 17);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{FlowDataOutput, FlowGraph, NodePath, OutputEnum};
+use serde::Serialize;
+
+/// The result of [`FlowDataOutput::reachability`]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReachabilityReport {
+    /// the nodes declaring the `$START` idname, where the analysis start
+    pub entry_points: Vec<NodePath>,
+    /// the nodes that can't be reached from an entry point
+    pub unreachable_nodes: Vec<NodePath>,
+    /// the groups that doesn't contain any reachable node
+    pub unreachable_groups: Vec<NodePath>,
+    /// the reachable nodes from which no ending can be reached, so the game can't continue to
+    /// an ending from them. An ending is an [`crate::AskSave`], a [`crate::DungeonEnd`] or a link
+    /// to an idname outside of the flow. Nodes of an exitless cycle are only reported there.
+    pub dead_ends: Vec<NodePath>,
+    /// the reachable cycles that doesn't have any link going out of them, and doesn't contain any
+    /// ending
+    pub exitless_cycles: Vec<Vec<NodePath>>,
+}
+
+impl ReachabilityReport {
+    /// return true if no problem were found
+    pub fn is_empty(&self) -> bool {
+        self.unreachable_nodes.is_empty()
+            && self.unreachable_groups.is_empty()
+            && self.dead_ends.is_empty()
+            && self.exitless_cycles.is_empty()
+    }
+}
+
+impl FlowDataOutput {
+    /// Follow the links from the `$START` entry points, and report the content that can't be
+    /// reached, the nodes the game can't reach an ending from, and the cycles the game can't exit.
+    pub fn reachability(&self) -> ReachabilityReport {
+        let graph = FlowGraph::new(self);
+        let path_of = |vertex: &usize| graph.vertex(*vertex).path.clone();
+        let mut leave_flow = vec![false; graph.vertices().len()];
+        for link in graph.dangling_links() {
+            leave_flow[link.from] = true;
+        }
+        let is_ending = |vertex: usize| {
+            leave_flow[vertex]
+                || matches!(
+                    self.get_node(&graph.vertex(vertex).path),
+                    Some(OutputEnum::AskSave(_)) | Some(OutputEnum::DungeonEnd(_))
+                )
+        };
+
+        let entries = graph.entry_points();
+        let reached = graph.reachable_from(&entries);
+        let endings: Vec<usize> = (0..graph.vertices().len())
+            .filter(|vertex| is_ending(*vertex))
+            .collect();
+        let reach_ending = graph.reaching(&endings);
+        let mut report = ReachabilityReport {
+            entry_points: entries.iter().map(path_of).collect(),
+            ..ReachabilityReport::default()
+        };
+
+        let mut in_exitless_cycle = vec![false; graph.vertices().len()];
+        for component in graph.strongly_connected_components() {
+            let first = component[0];
+            if !reached[first] {
+                continue;
+            };
+            let is_cycle =
+                component.len() > 1 || graph.successors(first).any(|edge| edge.to == first);
+            let has_exit = component.iter().any(|vertex| {
+                leave_flow[*vertex]
+                    || graph
+                        .successors(*vertex)
+                        .any(|edge| !component.contains(&edge.to))
+            });
+            if is_cycle && !has_exit && !component.iter().any(|vertex| is_ending(*vertex)) {
+                for vertex in &component {
+                    in_exitless_cycle[*vertex] = true;
+                }
+                report
+                    .exitless_cycles
+                    .push(component.iter().map(path_of).collect());
+            }
+        }
+
+        for (vertex, is_reached) in reached.iter().enumerate() {
+            if !is_reached {
+                report.unreachable_nodes.push(path_of(&vertex));
+            } else if !reach_ending[vertex] && !in_exitless_cycle[vertex] {
+                report.dead_ends.push(path_of(&vertex));
+            }
+        }
+
+        for (path, node) in self.nodes() {
+            if node.children().is_none() {
+                continue;
+            };
+            let contain_reached = graph
+                .vertices()
+                .iter()
+                .enumerate()
+                .any(|(vertex, data)| path.contains(&data.path) && reached[vertex]);
+            if !contain_reached {
+                report.unreachable_groups.push(path);
+            }
+        }
+
+        report
+    }
+}
@@ -0,0 +1,520 @@
+use crate::{FlowData, FlowDataError, FlowDataValue};
+use serde::ser::{self, Serialize};
+use std::collections::HashMap;
+
+/// Serialize `value` as a new [`FlowData`]. `value` should be serialized as a sequence, that will
+/// become the root vector of the file (like the one used by [`crate::FlowDataOutput`]).
+///
+/// The mapping is the following:
+/// - struct and map are stored in dictionary (map key should be string-like)
+/// - sequence and tuple are stored in vector. A vector can't directly contain another vector
+/// - string, char, bool and number are stored as string
+/// - `Some(value)` is stored as `value`. `None` is left out when it is the value of a struct field
+///   or a map entry (and a missing key is deserialized as `None`), and can't be stored elsewhere
+///   (like in a sequence). So `Some("")` and `None` stay different.
+/// - unit is stored as an empty string
+/// - unit variant are stored as the string of their name, other enum variant as a dictionary with
+///   the variant name as the only key (like the node of a [`crate::FlowDataOutput`])
+pub fn to_flowdata<T: Serialize + ?Sized>(value: &T) -> Result<FlowData, FlowDataError> {
+    let mut result = FlowData::default();
+    result.push_vector(vec![FlowDataValue::String("".into())])?;
+    result.push_dictionary(HashMap::new())?;
+    match to_flowdata_value(value, &mut result)? {
+        FlowDataValue::RefVec(_) => Ok(result),
+        other => Err(FlowDataError::RootNotVector(other)),
+    }
+}
+
+/// Serialize `value` into an existing [`FlowData`], and return the value that refer to it.
+///
+/// See [`to_flowdata`] for how values are mapped.
+pub fn to_flowdata_value<T: Serialize + ?Sized>(
+    value: &T,
+    dest: &mut FlowData,
+) -> Result<FlowDataValue, FlowDataError> {
+    serialize_into(value, dest, None)
+}
+
+/// serialize `value` into `dest`, and remove what was added to `dest` if it fail, so a failed
+/// value leave no orphan dictionary or vector behind. If `none_field` is set, a `None` value is
+/// accepted and reported there.
+fn serialize_into<T: Serialize + ?Sized>(
+    value: &T,
+    dest: &mut FlowData,
+    none_field: Option<&mut bool>,
+) -> Result<FlowDataValue, FlowDataError> {
+    let (dictionary_len, vector_len) = (dest.dictionary_len(), dest.vector_len());
+    let result = value.serialize(FlowDataSerializer {
+        dest: &mut *dest,
+        none_field,
+    });
+    if result.is_err() {
+        dest.truncate(dictionary_len, vector_len);
+    }
+    result
+}
+
+/// A serde [`ser::Serializer`] that add the serialized value to a [`FlowData`].
+pub struct FlowDataSerializer<'a> {
+    dest: &'a mut FlowData,
+    /// set when serializing the value of a field, that is left out if it is `None`
+    none_field: Option<&'a mut bool>,
+}
+
+impl<'a> FlowDataSerializer<'a> {
+    /// Create a new serializer that will add the serialized value into `dest`
+    pub fn new(dest: &'a mut FlowData) -> Self {
+        Self {
+            dest,
+            none_field: None,
+        }
+    }
+}
+
+fn wrap_variant(
+    dest: &mut FlowData,
+    variant: &'static str,
+    value: FlowDataValue,
+) -> Result<FlowDataValue, FlowDataError> {
+    let mut dic = HashMap::new();
+    dic.insert(variant.to_string(), value);
+    Ok(FlowDataValue::RefDic(dest.push_dictionary(dic)?))
+}
+
+macro_rules! serialize_as_string {
+    ($($method:ident: $type:ty,)*) => {
+        $(
+            fn $method(self, value: $type) -> Result<FlowDataValue, FlowDataError> {
+                Ok(FlowDataValue::String(value.to_string()))
+            }
+        )*
+    };
+}
+
+impl<'a> ser::Serializer for FlowDataSerializer<'a> {
+    type Ok = FlowDataValue;
+    type Error = FlowDataError;
+
+    type SerializeSeq = SeqSerializer<'a>;
+    type SerializeTuple = SeqSerializer<'a>;
+    type SerializeTupleStruct = SeqSerializer<'a>;
+    type SerializeTupleVariant = SeqSerializer<'a>;
+    type SerializeMap = MapSerializer<'a>;
+    type SerializeStruct = MapSerializer<'a>;
+    type SerializeStructVariant = MapSerializer<'a>;
+
+    serialize_as_string! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+        serialize_str: &str,
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<FlowDataValue, FlowDataError> {
+        Err(ser::Error::custom("bytes can't be stored in a flow file"))
+    }
+
+    fn serialize_none(self) -> Result<FlowDataValue, FlowDataError> {
+        match self.none_field {
+            Some(none_field) => {
+                *none_field = true;
+                Ok(FlowDataValue::String("".into()))
+            }
+            None => Err(FlowDataError::NoneOutsideField),
+        }
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(
+        self,
+        value: &T,
+    ) -> Result<FlowDataValue, FlowDataError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<FlowDataValue, FlowDataError> {
+        Ok(FlowDataValue::String("".into()))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<FlowDataValue, FlowDataError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<FlowDataValue, FlowDataError> {
+        Ok(FlowDataValue::String(variant.into()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<FlowDataValue, FlowDataError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<FlowDataValue, FlowDataError> {
+        let value = to_flowdata_value(value, self.dest)?;
+        wrap_variant(self.dest, variant, value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer<'a>, FlowDataError> {
+        Ok(SeqSerializer {
+            dest: self.dest,
+            values: Vec::with_capacity(len.unwrap_or(0)),
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer<'a>, FlowDataError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer<'a>, FlowDataError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer<'a>, FlowDataError> {
+        Ok(SeqSerializer {
+            dest: self.dest,
+            values: Vec::with_capacity(len),
+            variant: Some(variant),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer<'a>, FlowDataError> {
+        Ok(MapSerializer {
+            dest: self.dest,
+            values: HashMap::new(),
+            next_key: None,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<MapSerializer<'a>, FlowDataError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<MapSerializer<'a>, FlowDataError> {
+        Ok(MapSerializer {
+            dest: self.dest,
+            values: HashMap::new(),
+            next_key: None,
+            variant: Some(variant),
+        })
+    }
+}
+
+/// Serialize sequence, tuple and tuple variant as a vector
+pub struct SeqSerializer<'a> {
+    dest: &'a mut FlowData,
+    values: Vec<FlowDataValue>,
+    variant: Option<&'static str>,
+}
+
+impl<'a> SeqSerializer<'a> {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FlowDataError> {
+        match to_flowdata_value(value, self.dest)? {
+            FlowDataValue::RefVec(_) => Err(FlowDataError::NestedVector),
+            value => {
+                self.values.push(value);
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(self) -> Result<FlowDataValue, FlowDataError> {
+        let value = FlowDataValue::RefVec(self.dest.push_vector(self.values)?);
+        match self.variant {
+            Some(variant) => wrap_variant(self.dest, variant, value),
+            None => Ok(value),
+        }
+    }
+}
+
+impl<'a> ser::SerializeSeq for SeqSerializer<'a> {
+    type Ok = FlowDataValue;
+    type Error = FlowDataError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FlowDataError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<FlowDataValue, FlowDataError> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTuple for SeqSerializer<'a> {
+    type Ok = FlowDataValue;
+    type Error = FlowDataError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FlowDataError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<FlowDataValue, FlowDataError> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for SeqSerializer<'a> {
+    type Ok = FlowDataValue;
+    type Error = FlowDataError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FlowDataError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<FlowDataValue, FlowDataError> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTupleVariant for SeqSerializer<'a> {
+    type Ok = FlowDataValue;
+    type Error = FlowDataError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FlowDataError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<FlowDataValue, FlowDataError> {
+        self.finish()
+    }
+}
+
+/// Serialize map, struct and struct variant as a dictionary
+pub struct MapSerializer<'a> {
+    dest: &'a mut FlowData,
+    values: HashMap<String, FlowDataValue>,
+    next_key: Option<String>,
+    variant: Option<&'static str>,
+}
+
+impl<'a> MapSerializer<'a> {
+    /// add the entry `key`, or leave it out if `value` is `None`
+    fn insert<T: Serialize + ?Sized>(
+        &mut self,
+        key: String,
+        value: &T,
+    ) -> Result<(), FlowDataError> {
+        // only the field value itself can be left out, a nested `None` is still an error
+        let mut is_none = false;
+        let value = serialize_into(value, self.dest, Some(&mut is_none))?;
+        if !is_none {
+            self.values.insert(key, value);
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<FlowDataValue, FlowDataError> {
+        let value = FlowDataValue::RefDic(self.dest.push_dictionary(self.values)?);
+        match self.variant {
+            Some(variant) => wrap_variant(self.dest, variant, value),
+            None => Ok(value),
+        }
+    }
+}
+
+impl<'a> ser::SerializeMap for MapSerializer<'a> {
+    type Ok = FlowDataValue;
+    type Error = FlowDataError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), FlowDataError> {
+        let mut scratch = FlowData::default();
+        match to_flowdata_value(key, &mut scratch)? {
+            FlowDataValue::String(key) => {
+                self.next_key = Some(key);
+                Ok(())
+            }
+            _ => Err(ser::Error::custom("the key of a map should be a string")),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FlowDataError> {
+        let key = match self.next_key.take() {
+            Some(key) => key,
+            None => {
+                return Err(ser::Error::custom(
+                    "serialize_value called before serialize_key",
+                ))
+            }
+        };
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<FlowDataValue, FlowDataError> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeStruct for MapSerializer<'a> {
+    type Ok = FlowDataValue;
+    type Error = FlowDataError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), FlowDataError> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<FlowDataValue, FlowDataError> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeStructVariant for MapSerializer<'a> {
+    type Ok = FlowDataValue;
+    type Error = FlowDataError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), FlowDataError> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<FlowDataValue, FlowDataError> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_flowdata, to_flowdata, FlowData, FlowDataError, FlowDataValue};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Kind {
+        Plain,
+        Named(String),
+        Pair(String, u16),
+        Full { label: String, flag: bool },
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Entry {
+        name: String,
+        comment: Option<String>,
+        kind: Kind,
+        unit: (),
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Nested {
+        name: String,
+        values: Vec<Option<String>>,
+    }
+
+    fn entry(comment: Option<&str>, kind: Kind) -> Entry {
+        Entry {
+            name: "a".into(),
+            comment: comment.map(str::to_string),
+            kind,
+            unit: (),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let entries = vec![
+            entry(None, Kind::Plain),
+            entry(Some(""), Kind::Named("b".into())),
+            entry(Some("c"), Kind::Pair("d".into(), 12)),
+            entry(
+                None,
+                Kind::Full {
+                    label: "e".into(),
+                    flag: true,
+                },
+            ),
+        ];
+        let flowdata = to_flowdata(&entries).unwrap();
+        assert_eq!(from_flowdata::<Vec<Entry>>(&flowdata).unwrap(), entries);
+    }
+
+    #[test]
+    fn none_field_is_left_out() {
+        let flowdata = to_flowdata(&vec![entry(None, Kind::Plain)]).unwrap();
+        let dictionary = flowdata
+            .get_dictionary(flowdata.dictionary_len() - 1)
+            .unwrap();
+        assert!(!dictionary.contains_key("comment"));
+        assert_eq!(dictionary["unit"], FlowDataValue::String("".into()));
+    }
+
+    #[test]
+    fn nested_none_is_an_error() {
+        let nested = vec![Nested {
+            name: "a".into(),
+            values: vec![Some("x".into()), None],
+        }];
+        assert!(matches!(
+            to_flowdata(&nested),
+            Err(FlowDataError::NoneOutsideField)
+        ));
+    }
+
+    #[test]
+    fn failed_value_leave_no_orphan() {
+        let mut flowdata = FlowData::default();
+        let nested = Nested {
+            name: "a".into(),
+            values: vec![Some("x".into()), None],
+        };
+        let err = crate::to_flowdata_value(&(Kind::Named("b".into()), nested), &mut flowdata);
+        assert!(matches!(err, Err(FlowDataError::NoneOutsideField)));
+        assert_eq!(flowdata.dictionary_len(), 0);
+        assert_eq!(flowdata.vector_len(), 0);
+    }
+
+    #[test]
+    fn root_should_be_a_vector() {
+        assert!(matches!(
+            to_flowdata(&entry(None, Kind::Plain)),
+            Err(FlowDataError::RootNotVector(_))
+        ));
+    }
+}
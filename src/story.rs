@@ -0,0 +1,261 @@
+use crate::{FlowDataOutput, FlowGraph, NodePath};
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// A node visited by a [`StoryPath`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PathStep {
+    /// the path of the node
+    pub path: NodePath,
+    /// the key of the node (like `"Scenario"`)
+    pub key: String,
+    /// the debugname of the node
+    pub debugname: String,
+    /// the scripts played by this node, if any
+    pub entry: Vec<String>,
+    /// the label of the `socket_out` link followed to leave this node, or None for the last node
+    pub label: Option<String>,
+}
+
+/// Why a [`StoryPath`] stopped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum PathEnd {
+    /// the last node doesn't have any outgoing link
+    Terminal,
+    /// the last node link back to a node already visited by this path (the one at this path)
+    Loop(NodePath),
+    /// the last node link to an idname no node declare
+    Dangling(String),
+    /// the path reached [`PathOptions::max_length`]
+    MaxLength,
+}
+
+/// A route through the flow, from the start node to the end of the story (or a loop)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StoryPath {
+    /// the visited nodes, in order
+    pub steps: Vec<PathStep>,
+    /// why the path stopped
+    pub end: PathEnd,
+}
+
+/// Options for [`FlowDataOutput::story_paths`]
+#[derive(Debug, Clone)]
+pub struct PathOptions {
+    /// the maximum number of path to return. If there are more path, they are sampled randomly
+    pub max_paths: usize,
+    /// the maximum number of node in a path
+    pub max_length: usize,
+    /// the seed used when sampling path, so the result can be reproduced
+    pub seed: u64,
+}
+
+impl Default for PathOptions {
+    fn default() -> Self {
+        Self {
+            max_paths: 1000,
+            max_length: 1000,
+            seed: 0,
+        }
+    }
+}
+
+/// The paths returned by [`FlowDataOutput::story_paths`]
+#[derive(Debug, Clone, Serialize)]
+pub struct StoryPaths {
+    /// the found paths
+    pub paths: Vec<StoryPath>,
+    /// true if these are all the possible paths, false if there were too much paths and they
+    /// were sampled
+    pub exhaustive: bool,
+}
+
+/// A small linear congruential generator, good enough to choose branches
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, max: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        ((self.0 >> 33) % max as u64) as usize
+    }
+}
+
+struct PathWalker<'a> {
+    output: &'a FlowDataOutput,
+    graph: FlowGraph,
+    options: &'a PathOptions,
+}
+
+impl<'a> PathWalker<'a> {
+    /// the (label, target) the vertex can go to, with the idname as error for a dangling target
+    fn choices(&self, vertex: usize) -> Vec<(String, Result<usize, String>)> {
+        let mut choices: Vec<(String, Result<usize, String>)> = self
+            .graph
+            .successors(vertex)
+            .map(|edge| (edge.label.clone(), Ok(edge.to)))
+            .chain(
+                self.graph
+                    .dangling_links()
+                    .iter()
+                    .filter(|link| link.from == vertex)
+                    .map(|link| (link.label.clone(), Err(link.idname.clone()))),
+            )
+            .collect();
+        choices.sort_by(|a, b| a.0.cmp(&b.0));
+        choices
+    }
+
+    fn step(&self, vertex: usize, label: Option<String>) -> PathStep {
+        let data = self.graph.vertex(vertex);
+        PathStep {
+            path: data.path.clone(),
+            key: data.key.clone(),
+            debugname: data.debugname.clone(),
+            entry: self
+                .output
+                .get_node(&data.path)
+                .and_then(|node| node.entry())
+                .map(<[String]>::to_vec)
+                .unwrap_or_default(),
+            label,
+        }
+    }
+
+    fn finish(&self, visited: &[(usize, Option<String>)], end: PathEnd) -> StoryPath {
+        StoryPath {
+            steps: visited
+                .iter()
+                .map(|(vertex, label)| self.step(*vertex, label.clone()))
+                .collect(),
+            end,
+        }
+    }
+
+    /// return the end of the path if following `target` stop it
+    fn end_for(
+        &self,
+        visited: &[(usize, Option<String>)],
+        target: &Result<usize, String>,
+    ) -> Option<PathEnd> {
+        match target {
+            Err(idname) => Some(PathEnd::Dangling(idname.clone())),
+            Ok(target) if visited.iter().any(|(vertex, _)| vertex == target) => {
+                Some(PathEnd::Loop(self.graph.vertex(*target).path.clone()))
+            }
+            Ok(_) if visited.len() >= self.options.max_length => Some(PathEnd::MaxLength),
+            Ok(_) => None,
+        }
+    }
+
+    /// enumerate every path, returning false if there are more than `max_paths`
+    fn enumerate(
+        &self,
+        visited: &mut Vec<(usize, Option<String>)>,
+        result: &mut Vec<StoryPath>,
+    ) -> bool {
+        let vertex = visited.last().unwrap().0;
+        let choices = self.choices(vertex);
+        if choices.is_empty() {
+            if result.len() >= self.options.max_paths {
+                return false;
+            };
+            result.push(self.finish(visited, PathEnd::Terminal));
+            return true;
+        };
+        for (label, target) in choices {
+            visited.last_mut().unwrap().1 = Some(label);
+            match (self.end_for(visited, &target), target) {
+                (Some(end), _) => {
+                    if result.len() >= self.options.max_paths {
+                        return false;
+                    };
+                    result.push(self.finish(visited, end));
+                }
+                (None, Ok(target)) => {
+                    visited.push((target, None));
+                    let complete = self.enumerate(visited, result);
+                    visited.pop();
+                    if !complete {
+                        return false;
+                    }
+                }
+                (None, Err(_)) => unreachable!(),
+            }
+        }
+        visited.last_mut().unwrap().1 = None;
+        true
+    }
+
+    /// follow random choices from `start` until the path stop
+    fn sample(&self, start: usize, random: &mut Random) -> StoryPath {
+        let mut visited = vec![(start, None)];
+        loop {
+            let vertex = visited.last().unwrap().0;
+            let mut choices = self.choices(vertex);
+            if choices.is_empty() {
+                return self.finish(&visited, PathEnd::Terminal);
+            };
+            let (label, target) = choices.remove(random.below(choices.len()));
+            visited.last_mut().unwrap().1 = Some(label);
+            match (self.end_for(&visited, &target), target) {
+                (Some(end), _) => return self.finish(&visited, end),
+                (None, Ok(target)) => visited.push((target, None)),
+                (None, Err(_)) => unreachable!(),
+            }
+        }
+    }
+}
+
+impl FlowDataOutput {
+    /// Return the distinct routes through the flow starting at the node at `start`, or None if
+    /// there is no node with a socket at `start`.
+    ///
+    /// A route branch at every node with more than one outgoing link, like the `SEL_*` and
+    /// `select*` choices, or the `flow_A`/`flow_B` of a [`crate::DgFlowBranch`]. It stop at a node
+    /// without outgoing link, when it link back to a node it already visited, or at a link to an
+    /// unknown idname.
+    ///
+    /// If there are more than [`PathOptions::max_paths`] routes, the returned routes are instead
+    /// sampled by choosing random branches.
+    pub fn story_paths(&self, start: &NodePath, options: &PathOptions) -> Option<StoryPaths> {
+        let walker = PathWalker {
+            output: self,
+            graph: FlowGraph::new(self),
+            options,
+        };
+        let start = walker.graph.vertex_by_path(start)?;
+
+        let mut paths = Vec::new();
+        if walker.enumerate(&mut vec![(start, None)], &mut paths) {
+            return Some(StoryPaths {
+                paths,
+                exhaustive: true,
+            });
+        };
+
+        let mut random = Random(options.seed);
+        let mut seen = HashSet::new();
+        let mut paths = Vec::new();
+        for _ in 0..options.max_paths.saturating_mul(4) {
+            if paths.len() >= options.max_paths {
+                break;
+            };
+            let path = walker.sample(start, &mut random);
+            let key: Vec<(NodePath, Option<String>)> = path
+                .steps
+                .iter()
+                .map(|step| (step.path.clone(), step.label.clone()))
+                .collect();
+            if seen.insert(key) {
+                paths.push(path);
+            }
+        }
+        Some(StoryPaths {
+            paths,
+            exhaustive: false,
+        })
+    }
+}
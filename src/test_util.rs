@@ -0,0 +1,35 @@
+//! fixtures shared by the unit tests
+use crate::FlowDataOutput;
+use serde_json::{json, Value};
+
+/// a `normal` [`crate::Scenario`] declaring the idname `name` on its `start` input, and linking
+/// its `next` output to `next` if there is one
+pub(crate) fn scenario(name: &str, entry: &str, next: Option<&str>) -> Value {
+    let socket_out = match next {
+        Some(next) => json!({ "next": next }),
+        None => json!({}),
+    };
+    json!({ "Scenario": {
+        "flowtype": "normal",
+        "entry": [entry],
+        "socket": { "socket_in": { "start": name }, "socket_out": socket_out },
+        "debugname": name,
+    }})
+}
+
+/// a flow with a [`crate::FollowGroup`] for each group name and its nodes
+pub(crate) fn flow(groups: Vec<(&str, Vec<Value>)>) -> FlowDataOutput {
+    let output: Vec<Value> = groups
+        .into_iter()
+        .map(|(name, data)| json!({ "FollowGroup": { "debug_groupname": name, "data": data } }))
+        .collect();
+    serde_json::from_value(json!({ "output": output })).unwrap()
+}
+
+/// check that the two flows serialize the same way
+pub(crate) fn assert_same(left: &FlowDataOutput, right: &FlowDataOutput) {
+    assert_eq!(
+        serde_json::to_value(left).unwrap(),
+        serde_json::to_value(right).unwrap()
+    );
+}
@@ -0,0 +1,641 @@
+use crate::{FlowDataOutput, Follow, OutputEnum};
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::fmt;
+
+const INDENT: &str = "    ";
+const GROUP_KEY: &str = "FollowGroup";
+
+/// The error returned when a flow text can't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextError {
+    /// the line (starting at 1) where the error was found
+    pub line: usize,
+    /// the column (starting at 1) of the character where the error was found
+    pub column: usize,
+    /// what is wrong
+    pub message: String,
+}
+
+impl Error for TextError {}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+/// return true if `text` can be written without quote, as a label or an idname
+fn is_bare(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_alphanumeric() || "_$.-/".contains(c))
+}
+
+/// return `text` as a label or an idname, quoted if needed
+fn word(text: &str) -> String {
+    if is_bare(text) {
+        text.to_string()
+    } else {
+        Value::from(text).to_string()
+    }
+}
+
+fn wrap(key: &str, fields: Map<String, Value>) -> Value {
+    let mut node = Map::new();
+    node.insert(key.to_string(), Value::Object(fields));
+    Value::Object(node)
+}
+
+/// return true if the node `key` with `fields` is the same as with `original`, once deserialized
+fn same_node(key: &str, fields: Map<String, Value>, original: &Map<String, Value>) -> bool {
+    serde_json::from_value::<OutputEnum>(wrap(key, fields))
+        .ok()
+        .and_then(|node| serde_json::to_value(node).ok())
+        == Some(wrap(key, original.clone()))
+}
+
+/// return the `socket_in` and `socket_out` links of a serialized socket, or None if it can't be
+/// written as links (like an empty socket)
+#[allow(clippy::type_complexity)]
+fn socket_links(socket: &Value) -> Option<(Vec<(&str, &str)>, Vec<(&str, &str)>)> {
+    let socket = socket.as_object()?;
+    if socket.len() != 2 {
+        return None;
+    };
+    let links = |direction: &str| -> Option<Vec<(&str, &str)>> {
+        socket
+            .get(direction)?
+            .as_object()?
+            .iter()
+            .map(|(label, idname)| Some((label.as_str(), idname.as_str()?)))
+            .collect()
+    };
+    let (socket_in, socket_out) = (links("socket_in")?, links("socket_out")?);
+    if socket_in.is_empty() && socket_out.is_empty() {
+        None
+    } else {
+        Some((socket_in, socket_out))
+    }
+}
+
+/// return the `layout` line of a serialized [`crate::Layout`]
+fn layout_line(layout: &Value) -> Option<String> {
+    let layout = layout.as_object()?;
+    if layout.len() != 4 {
+        return None;
+    };
+    let position = |name: &str| layout.get(name).and_then(Value::as_i64);
+    Some(format!(
+        "layout x={} w={} h={}{}",
+        position("x")?,
+        position("w")?,
+        position("h")?,
+        if layout.get("line_break")?.as_bool()? {
+            " line_break"
+        } else {
+            ""
+        }
+    ))
+}
+
+fn write_node(out: &mut String, node: &OutputEnum, depth: usize) -> serde_json::Result<()> {
+    let indent = INDENT.repeat(depth);
+    let (key, mut fields) = match serde_json::to_value(node)? {
+        Value::Object(node) => match node.into_iter().next() {
+            Some((key, Value::Object(fields))) => (key, fields),
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    };
+    let is_group = key == GROUP_KEY;
+    if is_group {
+        fields.insert("data".into(), Value::Array(Vec::new()));
+    };
+
+    // leave out the fields with a default value, when it doesn't change the node
+    let original = fields.clone();
+    let names: Vec<String> = fields.keys().cloned().collect();
+    for name in names {
+        let mut reduced = fields.clone();
+        reduced.remove(&name);
+        if same_node(&key, reduced.clone(), &original) {
+            fields = reduced;
+        }
+    }
+    if is_group {
+        fields.remove("data");
+    };
+
+    out.push_str(&indent);
+    out.push_str(&key);
+    let name_field = if is_group {
+        "debug_groupname"
+    } else {
+        "debugname"
+    };
+    if let Some(Value::String(name)) = fields.get(name_field) {
+        out.push(' ');
+        out.push_str(&Value::from(name.as_str()).to_string());
+        fields.remove(name_field);
+    };
+    out.push_str(" {\n");
+
+    let links = fields.get("socket").and_then(socket_links);
+    let layout = fields.get("layout").and_then(layout_line);
+    let mut lines = Vec::new();
+    if let Some((socket_in, _)) = &links {
+        for (label, idname) in socket_in {
+            lines.push(format!("<- {}: {}", word(label), word(idname)));
+        }
+    };
+    for (name, value) in &fields {
+        let handled = match name.as_str() {
+            "socket" => links.is_some(),
+            "layout" => layout.is_some(),
+            _ => false,
+        };
+        if !handled {
+            lines.push(format!("{} = {}", name, value));
+        }
+    }
+    lines.extend(layout);
+    if let Some((_, socket_out)) = &links {
+        for (label, target) in socket_out {
+            lines.push(format!("-> {}: {}", word(label), word(target)));
+        }
+    };
+    for line in &lines {
+        out.push_str(&indent);
+        out.push_str(INDENT);
+        out.push_str(line);
+        out.push('\n');
+    }
+
+    if let Some(children) = node.children() {
+        for (index, child) in children.iter().enumerate() {
+            if index != 0 || !lines.is_empty() {
+                out.push('\n');
+            };
+            write_node(out, child, depth + 1)?;
+        }
+    };
+    out.push_str(&indent);
+    out.push_str("}\n");
+    Ok(())
+}
+
+/// A line being parsed
+struct Line<'a> {
+    text: &'a str,
+    number: usize,
+    /// the byte offset of the next character to read
+    position: usize,
+}
+
+impl<'a> Line<'a> {
+    /// return the column of the character at the byte offset `position`
+    fn column(&self, position: usize) -> usize {
+        let mut position = position.min(self.text.len());
+        while !self.text.is_char_boundary(position) {
+            position -= 1;
+        }
+        self.text[..position].chars().count() + 1
+    }
+
+    fn error(&self, position: usize, message: impl Into<String>) -> TextError {
+        TextError {
+            line: self.number,
+            column: self.column(position),
+            message: message.into(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn skip_spaces(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// return true and skip `prefix` if the rest of the line start with it
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest().starts_with(prefix) {
+            self.position += prefix.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, prefix: &str) -> Result<(), TextError> {
+        self.skip_spaces();
+        if self.eat(prefix) {
+            Ok(())
+        } else {
+            Err(self.error(self.position, format!("expected `{}`", prefix)))
+        }
+    }
+
+    /// read the characters while `accept` is true
+    fn take_while<F: Fn(char) -> bool>(&mut self, accept: F) -> &'a str {
+        let rest = self.rest();
+        let length = rest.find(|c| !accept(c)).unwrap_or(rest.len());
+        self.position += length;
+        &rest[..length]
+    }
+
+    fn identifier(&mut self) -> &'a str {
+        self.take_while(|c| c.is_alphanumeric() || c == '_')
+    }
+
+    /// read a JSON value, up to the end of the JSON
+    fn json(&mut self) -> Result<Value, TextError> {
+        self.skip_spaces();
+        let mut values = serde_json::Deserializer::from_str(self.rest()).into_iter::<Value>();
+        match values.next() {
+            Some(Ok(value)) => {
+                self.position += values.byte_offset();
+                Ok(value)
+            }
+            Some(Err(err)) => {
+                let message = err.to_string();
+                let message = match message.rfind(" at line ") {
+                    Some(end) => message[..end].to_string(),
+                    None => message,
+                };
+                Err(self.error(self.position + err.column().saturating_sub(1), message))
+            }
+            None => Err(self.error(self.position, "expected a value")),
+        }
+    }
+
+    /// read a quoted JSON string
+    fn string(&mut self) -> Result<String, TextError> {
+        let start = self.position;
+        match self.json()? {
+            Value::String(text) => Ok(text),
+            _ => Err(self.error(start, "expected a string")),
+        }
+    }
+
+    /// read a label or an idname, bare or quoted
+    fn word(&mut self, what: &str) -> Result<String, TextError> {
+        self.skip_spaces();
+        if self.rest().starts_with('"') {
+            return self.string();
+        };
+        let word = self.take_while(|c| !c.is_whitespace() && !":#\"{}=".contains(c));
+        if word.is_empty() {
+            Err(self.error(self.position, format!("expected {}", what)))
+        } else {
+            Ok(word.to_string())
+        }
+    }
+
+    /// check that only spaces and a comment are left
+    fn end(&mut self) -> Result<(), TextError> {
+        self.skip_spaces();
+        if self.rest().is_empty() || self.rest().starts_with('#') {
+            Ok(())
+        } else {
+            Err(self.error(self.position, "expected the end of the line"))
+        }
+    }
+}
+
+/// A node being parsed
+struct Block {
+    key: String,
+    line: usize,
+    column: usize,
+    fields: Map<String, Value>,
+    /// true if the socket is given with links rather than as a field
+    links: bool,
+    children: Vec<OutputEnum>,
+}
+
+impl Block {
+    fn error(&self, message: String) -> TextError {
+        TextError {
+            line: self.line,
+            column: self.column,
+            message,
+        }
+    }
+
+    fn finish(mut self) -> Result<OutputEnum, TextError> {
+        if self.key == GROUP_KEY {
+            self.fields.insert("data".into(), Value::Array(Vec::new()));
+        };
+        let fields = std::mem::take(&mut self.fields);
+        let mut node: OutputEnum = serde_json::from_value(wrap(&self.key, fields))
+            .map_err(|err| self.error(format!("invalid {}: {}", self.key, err)))?;
+        if let Some(children) = node.children_mut() {
+            *children = Follow::from(self.children);
+        };
+        Ok(node)
+    }
+}
+
+fn parse_link(line: &mut Line, block: &mut Block, direction: &str) -> Result<(), TextError> {
+    line.skip_spaces();
+    let start = line.position;
+    let label = line.word("a label")?;
+    line.expect(":")?;
+    let idname = line.word("an idname")?;
+    line.end()?;
+    if block.fields.contains_key("socket") && !block.links {
+        return Err(line.error(start, "the socket is already given as a field"));
+    };
+    block.links = true;
+    let socket = block.fields.entry("socket").or_insert_with(|| {
+        let mut socket = Map::new();
+        socket.insert("socket_in".into(), Value::Object(Map::new()));
+        socket.insert("socket_out".into(), Value::Object(Map::new()));
+        Value::Object(socket)
+    });
+    let links = socket[direction].as_object_mut().unwrap();
+    if links.contains_key(&label) {
+        return Err(line.error(start, format!("the link {:?} is given twice", label)));
+    };
+    links.insert(label, Value::String(idname));
+    Ok(())
+}
+
+fn parse_layout(line: &mut Line) -> Result<Value, TextError> {
+    let mut layout = Map::new();
+    for name in &["x", "w", "h"] {
+        layout.insert(name.to_string(), Value::from(0));
+    }
+    layout.insert("line_break".into(), Value::Bool(false));
+    loop {
+        line.skip_spaces();
+        if line.rest().is_empty() || line.rest().starts_with('#') {
+            return Ok(Value::Object(layout));
+        };
+        let start = line.position;
+        match line.identifier() {
+            "line_break" => {
+                layout.insert("line_break".into(), Value::Bool(true));
+            }
+            name @ "x" | name @ "w" | name @ "h" => {
+                line.expect("=")?;
+                let value_start = line.position;
+                match line.json()? {
+                    value @ Value::Number(_) if value.is_i64() => {
+                        layout.insert(name.to_string(), value);
+                    }
+                    _ => return Err(line.error(value_start, "expected an integer")),
+                }
+            }
+            _ => return Err(line.error(start, "expected x=, w=, h= or line_break")),
+        }
+    }
+}
+
+fn parse_text(text: &str) -> Result<Vec<OutputEnum>, TextError> {
+    let mut stack: Vec<Block> = Vec::new();
+    let mut nodes = Vec::new();
+    for (index, text) in text.lines().enumerate() {
+        let mut line = Line {
+            text,
+            number: index + 1,
+            position: 0,
+        };
+        line.skip_spaces();
+        if line.rest().is_empty() || line.rest().starts_with('#') {
+            continue;
+        };
+        let start = line.position;
+
+        if line.eat("}") {
+            line.end()?;
+            let block = stack
+                .pop()
+                .ok_or_else(|| line.error(start, "this `}` doesn't close any node"))?;
+            let node = block.finish()?;
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => nodes.push(node),
+            };
+            continue;
+        };
+
+        let direction = if line.eat("<-") {
+            Some("socket_in")
+        } else if line.eat("->") {
+            Some("socket_out")
+        } else {
+            None
+        };
+        if let Some(direction) = direction {
+            let block = stack
+                .last_mut()
+                .ok_or_else(|| line.error(start, "a link should be inside a node"))?;
+            parse_link(&mut line, block, direction)?;
+            continue;
+        };
+
+        let name = line.identifier();
+        if name.is_empty() {
+            return Err(line.error(start, "expected a node, a field, a link or `}`"));
+        };
+        line.skip_spaces();
+        let is_layout = name == "layout" && !line.rest().starts_with(['"', '{']);
+        let is_field = line.eat("=");
+        if is_field || (is_layout && !stack.is_empty()) {
+            let block = stack
+                .last_mut()
+                .ok_or_else(|| line.error(start, "a field should be inside a node"))?;
+            let value = if is_field {
+                let value = line.json()?;
+                line.end()?;
+                value
+            } else {
+                parse_layout(&mut line)?
+            };
+            if block.key == GROUP_KEY && name == "data" {
+                return Err(line.error(start, "the nodes of a group are written inside it"));
+            };
+            if block.fields.contains_key(name) {
+                return Err(line.error(start, format!("the field {:?} is given twice", name)));
+            };
+            block.fields.insert(name.to_string(), value);
+            continue;
+        };
+
+        // the start of a node
+        if let Some(parent) = stack.last() {
+            if parent.key != GROUP_KEY {
+                return Err(line.error(start, "only a FollowGroup can contain other nodes"));
+            }
+        };
+        let mut fields = Map::new();
+        if line.rest().starts_with('"') {
+            let name_field = if name == GROUP_KEY {
+                "debug_groupname"
+            } else {
+                "debugname"
+            };
+            fields.insert(name_field.into(), Value::String(line.string()?));
+        };
+        line.expect("{")?;
+        line.end()?;
+        stack.push(Block {
+            key: name.to_string(),
+            line: line.number,
+            column: line.column(start),
+            fields,
+            links: false,
+            children: Vec::new(),
+        });
+    }
+    match stack.last() {
+        Some(block) => Err(block.error(format!("this {} isn't closed", block.key))),
+        None => Ok(nodes),
+    }
+}
+
+impl FlowDataOutput {
+    /// Return this flow in a compact text format, meant to be read and edited by hand.
+    ///
+    /// Each node is a block, started by its type (as in the JSON) and its quoted debugname, like
+    /// `Scenario "intro" {`, and ended by `}`. The nodes of a [`crate::FollowGroup`] are written
+    /// inside it. In a block, `<- label: idname` is an incoming link and `-> label: target` an
+    /// outgoing link, `layout x=0 w=0 h=0 line_break` is the position in the debug editor, and
+    /// the other fields are written as `name = <JSON value>`. Fields with a default value are left
+    /// out. Text after a `#` is a comment.
+    ///
+    /// [`FlowDataOutput::from_text`] read it back into the same flow.
+    pub fn to_text(&self) -> serde_json::Result<String> {
+        let mut out = String::new();
+        for (index, node) in self.output().iter().enumerate() {
+            if index != 0 {
+                out.push('\n');
+            };
+            write_node(&mut out, node, 0)?;
+        }
+        Ok(out)
+    }
+
+    /// Read a flow written in the text format of [`FlowDataOutput::to_text`]
+    pub fn from_text(text: &str) -> Result<Self, TextError> {
+        Ok(Follow::from(parse_text(text)?).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn flow() -> FlowDataOutput {
+        serde_json::from_value(json!({ "output": [
+            { "FollowGroup": {
+                "debug_groupname": "Main story",
+                "data": [
+                    { "Scenario": {
+                        "flowtype": "normal",
+                        "entry": ["main01_a", "main01_b"],
+                        "comment": "a \"quoted\" comment",
+                        "socket": {
+                            "socket_in": { "start": "$START" },
+                            "socket_out": { "next": "second scene" },
+                        },
+                        "debugname": "first",
+                        "debugmenu_tag": "system",
+                        "layout": { "line_break": true, "h": -3, "w": 200, "x": 40 },
+                    }},
+                    { "Scenario": {
+                        "flowtype": "",
+                        "entry": [],
+                        "socket": {
+                            "socket_in": { "start": "second scene" },
+                            "socket_out": {},
+                        },
+                        "debugname": "",
+                    }},
+                ],
+                "debugmenu_tag": "system",
+            }},
+            { "DungeonEnd": {
+                "socket": { "socket_in": { "start": "end" }, "socket_out": { "next": "$START" } },
+                "debugname": "end",
+            }},
+        ]}))
+        .unwrap()
+    }
+
+    fn parse_error(text: &str) -> (usize, usize) {
+        let error = FlowDataOutput::from_text(text).unwrap_err();
+        (error.line, error.column)
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let flow = flow();
+        let text = flow.to_text().unwrap();
+        let parsed = FlowDataOutput::from_text(&text).unwrap();
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap(),
+            serde_json::to_value(&flow).unwrap()
+        );
+        assert_eq!(parsed.to_text().unwrap(), text);
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_ignored() {
+        let text = r#"# a comment
+
+Scenario "a" { # the node
+    flowtype = "normal"
+    entry = ["x"] # the scripts
+    <- start: a
+}
+"#;
+        let flow = FlowDataOutput::from_text(text).unwrap();
+        assert_eq!(flow.output().len(), 1);
+        assert_eq!(
+            flow.output().get(0).unwrap().entry(),
+            Some(&["x".to_string()][..])
+        );
+    }
+
+    #[test]
+    fn errors_give_the_line_and_column() {
+        let error = FlowDataOutput::from_text("Scenario \"a\" {\n    -> next: b\n    -> next: c\n")
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 3, column 8: the link \"next\" is given twice"
+        );
+        assert_eq!(parse_error("}\n"), (1, 1));
+        assert_eq!(
+            parse_error("Scenario \"a\" {\n    ?? nonsense\n}\n"),
+            (2, 5)
+        );
+        assert_eq!(
+            parse_error("Scenario \"a\" {\n    layout x=abc\n}\n"),
+            (2, 14)
+        );
+        assert_eq!(
+            parse_error("Scenario \"a\" {\n    <- start: a\n    <- start: b\n}\n"),
+            (3, 8)
+        );
+        assert_eq!(
+            parse_error(
+                "\nFollowGroup \"G\" {\n    DungeonEnd \"a\" {\n        <- start: a\n    }\n"
+            ),
+            (2, 1)
+        );
+        assert_eq!(parse_error("    -> next: a\n"), (1, 5));
+    }
+}
@@ -32,7 +32,7 @@ where
     F: Fn(&mut T) -> Result<O, IOError>,
 {
     let ressource_address = read_u32_le(file)? as u64;
-    let _current_address = file.seek(SeekFrom::Current(0))?;
+    let _current_address = file.stream_position()?;
     file.seek(SeekFrom::Start(ressource_address))?;
     let result = parse(file)?;
     file.seek(SeekFrom::Start(ressource_address))?;
@@ -41,7 +41,7 @@ where
 
 /* pub fn add_padding<T>(file: &mut T, pad_indication_number: u64) -> Result<(), IOError>
 where T: Seek + Write {
-    let remaining = pad_indication_number - file.seek(SeekFrom::Current(0))? % pad_indication_number;
+    let remaining = pad_indication_number - file.stream_position()? % pad_indication_number;
     if remaining == pad_indication_number {
         return Ok(())
     }
@@ -0,0 +1,398 @@
+use crate::flowdata::sorted_entries;
+use crate::{FlowData, FlowDataError, FlowDataValue};
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryInto;
+use std::fmt;
+
+const ANCHOR_KEY: &str = "$anchor";
+const ANCHOR_VALUE_KEY: &str = "$value";
+const ALIAS_KEY: &str = "$alias";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A schema-less, owned tree view of (a part of) a [`FlowData`].
+///
+/// Unlike [`FlowData`], it doesn't use dictionary/vector id: a dictionary become a [`FlowTree::Map`],
+/// and a vector a [`FlowTree::List`]. This allow to edit part of a flow file that isn't modeled by
+/// [`crate::FlowDataOutput`].
+///
+/// If a dictionary or a vector is referenced more than once, it is only stored once, in a
+/// [`FlowTree::Anchor`], and every other reference are replaced by a [`FlowTree::Alias`] with the
+/// same name.
+///
+/// When serialized, a string is a string, a map is a map and a list is a list. Anchors are written
+/// as `{"$anchor": name, "$value": tree}` and aliases as `{"$alias": name}`.
+pub enum FlowTree {
+    /// a string
+    String(String),
+    /// a dictionary
+    Map(BTreeMap<String, FlowTree>),
+    /// a vector
+    List(Vec<FlowTree>),
+    /// the first occurrence of a subtree that is referenced multiple time
+    Anchor(String, Box<FlowTree>),
+    /// another reference to the subtree of the [`FlowTree::Anchor`] with the same name
+    Alias(String),
+}
+
+impl FlowTree {
+    /// Create a new [`FlowTree`] from the value `root` of a [`FlowData`].
+    pub fn new(source: &FlowData, root: &FlowDataValue) -> Result<Self, FlowDataError> {
+        let mut reference_count = HashMap::new();
+        count_reference(source, root, &mut reference_count)?;
+        let mut emitted = HashSet::new();
+        build_tree(source, root, &reference_count, &mut emitted)
+    }
+
+    /// Create a new [`FlowTree`] from the root vector of a [`FlowData`] (the last one, as used by
+    /// [`crate::FlowDataOutput`])
+    pub fn from_root(source: &FlowData) -> Result<Self, FlowDataError> {
+        let root_id: u16 = source.vector_len().saturating_sub(1).try_into()?;
+        Self::new(source, &FlowDataValue::RefVec(root_id))
+    }
+
+    /// Add this tree to a [`FlowData`], and return the value that refer to it.
+    ///
+    /// new dictionary/vector id are allocated. Subtree under an [`FlowTree::Anchor`] are only added
+    /// once.
+    pub fn generate(&self, dest: &mut FlowData) -> Result<FlowDataValue, FlowDataError> {
+        let mut anchors = HashMap::new();
+        self.generate_inner(dest, &mut anchors)
+    }
+
+    /// Create a new [`FlowData`] with this tree as its root. The root should be a list.
+    pub fn generate_flowdata(&self) -> Result<FlowData, FlowDataError> {
+        let mut result = FlowData::default();
+        result.push_vector(vec![FlowDataValue::String("".into())])?;
+        result.push_dictionary(HashMap::new())?;
+        match self.generate(&mut result)? {
+            FlowDataValue::RefVec(_) => Ok(result),
+            other => Err(FlowDataError::RootNotVector(other)),
+        }
+    }
+
+    /// return the value of the string if this is a string
+    pub fn get_string(&self) -> Option<&str> {
+        match self {
+            Self::String(str) => Some(str),
+            _ => None,
+        }
+    }
+
+    /// return the map if this is a map
+    pub fn get_map(&self) -> Option<&BTreeMap<String, FlowTree>> {
+        match self {
+            Self::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// return the list if this is a list
+    pub fn get_list(&self) -> Option<&Vec<FlowTree>> {
+        match self {
+            Self::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    fn generate_inner(
+        &self,
+        dest: &mut FlowData,
+        anchors: &mut HashMap<String, Option<FlowDataValue>>,
+    ) -> Result<FlowDataValue, FlowDataError> {
+        Ok(match self {
+            Self::String(str) => FlowDataValue::String(str.clone()),
+            Self::Map(map) => {
+                let mut dic = HashMap::new();
+                for (key, value) in map {
+                    dic.insert(key.clone(), value.generate_inner(dest, anchors)?);
+                }
+                FlowDataValue::RefDic(dest.push_dictionary(dic)?)
+            }
+            Self::List(list) => {
+                let mut vec = Vec::new();
+                for value in list {
+                    match value.generate_inner(dest, anchors)? {
+                        FlowDataValue::RefVec(_) => return Err(FlowDataError::NestedVector),
+                        value => vec.push(value),
+                    }
+                }
+                FlowDataValue::RefVec(dest.push_vector(vec)?)
+            }
+            Self::Anchor(name, tree) => {
+                // None mark an anchor that is being generated
+                anchors.insert(name.clone(), None);
+                let value = tree.generate_inner(dest, anchors)?;
+                anchors.insert(name.clone(), Some(value.clone()));
+                value
+            }
+            Self::Alias(name) => match anchors.get(name) {
+                Some(Some(value)) => value.clone(),
+                Some(None) => return Err(FlowDataError::AnchorCycle(name.clone())),
+                None => return Err(FlowDataError::UnknownAnchor(name.clone())),
+            },
+        })
+    }
+}
+
+fn check_reference(source: &FlowData, value: &FlowDataValue) -> Result<(), FlowDataError> {
+    match value {
+        FlowDataValue::String(_) => Ok(()),
+        FlowDataValue::RefDic(dicid) => {
+            if source.get_dictionary(*dicid as usize).is_none() {
+                Err(FlowDataError::DicReferenceTooBig(
+                    *dicid,
+                    source.dictionary_len() as u32,
+                ))
+            } else {
+                Ok(())
+            }
+        }
+        FlowDataValue::RefVec(vecid) => {
+            if source.get_vector(*vecid as usize).is_none() {
+                Err(FlowDataError::VecReferenceTooBig(
+                    *vecid,
+                    source.vector_len() as u32,
+                ))
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
+
+fn children(source: &FlowData, value: &FlowDataValue) -> Vec<FlowDataValue> {
+    match value {
+        FlowDataValue::String(_) => Vec::new(),
+        FlowDataValue::RefDic(dicid) => source
+            .get_dictionary(*dicid as usize)
+            .unwrap()
+            .values()
+            .cloned()
+            .collect(),
+        FlowDataValue::RefVec(vecid) => source.get_vector(*vecid as usize).unwrap().clone(),
+    }
+}
+
+/// count the number of time each dictionary/vector is referenced (root included)
+fn count_reference(
+    source: &FlowData,
+    root: &FlowDataValue,
+    reference_count: &mut HashMap<FlowDataValue, usize>,
+) -> Result<(), FlowDataError> {
+    let mut to_visit = vec![root.clone()];
+    while let Some(value) = to_visit.pop() {
+        if let FlowDataValue::String(_) = value {
+            continue;
+        };
+        check_reference(source, &value)?;
+        let count = reference_count.entry(value.clone()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            to_visit.extend(children(source, &value));
+        }
+    }
+    Ok(())
+}
+
+fn anchor_name(value: &FlowDataValue) -> String {
+    match value {
+        FlowDataValue::RefDic(dicid) => format!("dic{}", dicid),
+        FlowDataValue::RefVec(vecid) => format!("vec{}", vecid),
+        FlowDataValue::String(str) => str.clone(),
+    }
+}
+
+fn build_tree(
+    source: &FlowData,
+    value: &FlowDataValue,
+    reference_count: &HashMap<FlowDataValue, usize>,
+    emitted: &mut HashSet<FlowDataValue>,
+) -> Result<FlowTree, FlowDataError> {
+    let shared = reference_count.get(value).copied().unwrap_or(0) > 1;
+    if shared {
+        if emitted.contains(value) {
+            return Ok(FlowTree::Alias(anchor_name(value)));
+        }
+        emitted.insert(value.clone());
+    }
+    let tree = match value {
+        FlowDataValue::String(str) => FlowTree::String(str.clone()),
+        FlowDataValue::RefDic(dicid) => {
+            let dic = source.get_dictionary(*dicid as usize).unwrap();
+            let mut map = BTreeMap::new();
+            // in key order, like generate_inner, so an anchor always come before its aliases
+            for (key, child) in sorted_entries(dic) {
+                map.insert(
+                    key.clone(),
+                    build_tree(source, child, reference_count, emitted)?,
+                );
+            }
+            FlowTree::Map(map)
+        }
+        FlowDataValue::RefVec(vecid) => {
+            let vec = source.get_vector(*vecid as usize).unwrap();
+            let mut list = Vec::new();
+            for child in vec {
+                list.push(build_tree(source, child, reference_count, emitted)?);
+            }
+            FlowTree::List(list)
+        }
+    };
+    Ok(if shared {
+        FlowTree::Anchor(anchor_name(value), Box::new(tree))
+    } else {
+        tree
+    })
+}
+
+impl Serialize for FlowTree {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::String(str) => serializer.serialize_str(str),
+            Self::Map(map) => {
+                let mut ser_map = serializer.serialize_map(Some(map.len()))?;
+                for (key, value) in map {
+                    ser_map.serialize_entry(key, value)?;
+                }
+                ser_map.end()
+            }
+            Self::List(list) => {
+                let mut ser_seq = serializer.serialize_seq(Some(list.len()))?;
+                for value in list {
+                    ser_seq.serialize_element(value)?;
+                }
+                ser_seq.end()
+            }
+            Self::Anchor(name, tree) => {
+                let mut ser_map = serializer.serialize_map(Some(2))?;
+                ser_map.serialize_entry(ANCHOR_KEY, name)?;
+                ser_map.serialize_entry(ANCHOR_VALUE_KEY, tree)?;
+                ser_map.end()
+            }
+            Self::Alias(name) => {
+                let mut ser_map = serializer.serialize_map(Some(1))?;
+                ser_map.serialize_entry(ALIAS_KEY, name)?;
+                ser_map.end()
+            }
+        }
+    }
+}
+
+struct FlowTreeVisitor;
+
+impl<'de> Visitor<'de> for FlowTreeVisitor {
+    type Value = FlowTree;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string, a list or a map")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<FlowTree, E> {
+        Ok(FlowTree::String(value.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<FlowTree, E> {
+        Ok(FlowTree::String(value))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<FlowTree, A::Error> {
+        let mut list = Vec::new();
+        while let Some(value) = seq.next_element()? {
+            list.push(value);
+        }
+        Ok(FlowTree::List(list))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<FlowTree, A::Error> {
+        let mut map: BTreeMap<String, FlowTree> = BTreeMap::new();
+        while let Some((key, value)) = access.next_entry()? {
+            map.insert(key, value);
+        }
+        let anchor_name = |map: &BTreeMap<String, FlowTree>, key: &str| match map[key].get_string()
+        {
+            Some(name) => Ok(name.to_string()),
+            None => Err(de::Error::custom(format!("{} should be a string", key))),
+        };
+        if map.len() == 1 && map.contains_key(ALIAS_KEY) {
+            Ok(FlowTree::Alias(anchor_name(&map, ALIAS_KEY)?))
+        } else if map.len() == 2 && map.contains_key(ANCHOR_KEY) {
+            let name = anchor_name(&map, ANCHOR_KEY)?;
+            match map.remove(ANCHOR_VALUE_KEY) {
+                Some(tree) => Ok(FlowTree::Anchor(name, Box::new(tree))),
+                None => Err(de::Error::missing_field(ANCHOR_VALUE_KEY)),
+            }
+        } else {
+            Ok(FlowTree::Map(map))
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FlowTree {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(FlowTreeVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a flow whose root vector contain a dictionary with the keys `a` to `h`, all referencing the
+    /// same dictionary
+    fn shared_flow() -> FlowData {
+        // start like FlowTree::generate_flowdata, so the ids are the same once regenerated
+        let mut flow = FlowData::default();
+        flow.push_vector(vec![FlowDataValue::String("".into())])
+            .unwrap();
+        flow.push_dictionary(HashMap::new()).unwrap();
+        let mut shared = HashMap::new();
+        shared.insert("value".to_string(), FlowDataValue::String("x".into()));
+        let shared = flow.push_dictionary(shared).unwrap();
+        let mut parent = HashMap::new();
+        for key in "abcdefgh".chars() {
+            parent.insert(key.to_string(), FlowDataValue::RefDic(shared));
+        }
+        let parent = flow.push_dictionary(parent).unwrap();
+        flow.push_vector(vec![FlowDataValue::RefDic(parent)])
+            .unwrap();
+        flow
+    }
+
+    #[test]
+    fn shared_subtree_is_anchored_at_the_first_key() {
+        let tree = FlowTree::from_root(&shared_flow()).unwrap();
+        let map = tree.get_list().unwrap()[0].get_map().unwrap();
+        assert!(matches!(map["a"], FlowTree::Anchor(_, _)));
+        for key in ["b", "c", "d", "e", "f", "g", "h"].iter() {
+            assert!(matches!(map[*key], FlowTree::Alias(_)));
+        }
+    }
+
+    #[test]
+    fn shared_subtree_round_trip() {
+        let tree = FlowTree::from_root(&shared_flow()).unwrap();
+        let generated = tree.generate_flowdata().unwrap();
+        // the first empty dictionary, then the shared one only once, then its parent
+        assert_eq!(generated.dictionary_len(), 3);
+        assert_eq!(FlowTree::from_root(&generated).unwrap(), tree);
+    }
+
+    #[test]
+    fn serialized_tree_round_trip() {
+        let tree = FlowTree::from_root(&shared_flow()).unwrap();
+        let json = serde_json::to_string(&tree).unwrap();
+        assert_eq!(serde_json::from_str::<FlowTree>(&json).unwrap(), tree);
+    }
+
+    #[test]
+    fn unknown_alias_is_an_error() {
+        let tree = FlowTree::List(vec![FlowTree::Alias("missing".into())]);
+        assert!(matches!(
+            tree.generate_flowdata(),
+            Err(FlowDataError::UnknownAnchor(name)) if name == "missing"
+        ));
+    }
+}
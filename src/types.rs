@@ -0,0 +1,250 @@
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Define an enum stored as a string in the flow file. Each known variant has its spelling, and
+/// the unknown ones are kept verbatim in the `Other` variant, so they can be written back.
+macro_rules! string_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident => $text:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+            /// a value this library doesn't know, kept as is
+            Other(String),
+        }
+
+        impl $name {
+            /// return the string this value is stored as
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$variant => $text,)*
+                    Self::Other(text) => text,
+                }
+            }
+
+            /// return true if this value is one of the known value
+            pub fn is_known(&self) -> bool {
+                !matches!(self, Self::Other(_))
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(text: &str) -> Self {
+                match text {
+                    $($text => Self::$variant,)*
+                    other => Self::Other(other.to_string()),
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(text: String) -> Self {
+                Self::from(text.as_str())
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::from("")
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer).map(Self::from)
+            }
+        }
+    };
+}
+
+string_enum! {
+    /// The action a [`crate::DgFlowBranch`] do on its counter before comparing it (`act`)
+    pub enum CounterAction {
+        /// leave the counter unchanged
+        Nothing => "",
+        /// add one to the counter
+        Increment => "inc",
+        /// remove one from the counter
+        Decrement => "dec",
+    }
+}
+
+impl CounterAction {
+    /// return the counter after this action, or None if the action isn't known
+    pub fn apply(&self, value: i64) -> Option<i64> {
+        match self {
+            Self::Nothing => Some(value),
+            Self::Increment => Some(value + 1),
+            Self::Decrement => Some(value - 1),
+            Self::Other(_) => None,
+        }
+    }
+}
+
+string_enum! {
+    /// The comparison a [`crate::DgFlowBranch`] do between its counter and its `count` (`if`)
+    pub enum Comparison {
+        /// the counter is equal to `count`
+        Equal => "==",
+        /// the counter is different from `count`
+        NotEqual => "!=",
+        /// the counter is less than `count`
+        Less => "<",
+        /// the counter is less than or equal to `count`
+        LessOrEqual => "<=",
+        /// the counter is greater than `count`
+        Greater => ">",
+        /// the counter is greater than or equal to `count`
+        GreaterOrEqual => ">=",
+    }
+}
+
+impl Comparison {
+    /// return the result of the comparison of `value` with `count`, or None if the comparison
+    /// isn't known
+    pub fn compare(&self, value: i64, count: i64) -> Option<bool> {
+        match self {
+            Self::Equal => Some(value == count),
+            Self::NotEqual => Some(value != count),
+            Self::Less => Some(value < count),
+            Self::LessOrEqual => Some(value <= count),
+            Self::Greater => Some(value > count),
+            Self::GreaterOrEqual => Some(value >= count),
+            Self::Other(_) => None,
+        }
+    }
+}
+
+/// A number stored as a string in the flow file.
+///
+/// It is only parsed as a number if writing it back give the same string (so `"03"` or `""` are
+/// kept as [`FlowInt::Other`]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FlowInt {
+    /// a number
+    Int(i64),
+    /// a string that isn't a number, kept as is
+    Other(String),
+}
+
+impl FlowInt {
+    /// return the number, if this is one
+    pub fn value(&self) -> Option<i64> {
+        match self {
+            Self::Int(value) => Some(*value),
+            Self::Other(_) => None,
+        }
+    }
+}
+
+impl Default for FlowInt {
+    fn default() -> Self {
+        Self::Other(String::new())
+    }
+}
+
+impl From<i64> for FlowInt {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<&str> for FlowInt {
+    fn from(text: &str) -> Self {
+        match text.parse::<i64>() {
+            Ok(value) if value.to_string() == text => Self::Int(value),
+            _ => Self::Other(text.to_string()),
+        }
+    }
+}
+
+impl From<String> for FlowInt {
+    fn from(text: String) -> Self {
+        Self::from(text.as_str())
+    }
+}
+
+impl fmt::Display for FlowInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(value) => write!(f, "{}", value),
+            Self::Other(text) => f.write_str(text),
+        }
+    }
+}
+
+impl Serialize for FlowInt {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+struct FlowIntVisitor;
+
+impl<'de> Visitor<'de> for FlowIntVisitor {
+    type Value = FlowInt;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a number or a string")
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<FlowInt, E> {
+        Ok(FlowInt::Int(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<FlowInt, E> {
+        Ok(FlowInt::from(value.to_string()))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<FlowInt, E> {
+        Ok(FlowInt::from(value))
+    }
+}
+
+impl<'de> Deserialize<'de> for FlowInt {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(FlowIntVisitor)
+    }
+}
+
+string_enum! {
+    /// The type of flow of a [`crate::Scenario`] or a [`crate::Dungeon`] (`flowtype`).
+    ///
+    /// Only the values seen so far are known, the other are kept in [`FlowType::Other`].
+    pub enum FlowType {
+        /// no flow type
+        Empty => "",
+        /// the `normal` flow type
+        Normal => "normal",
+    }
+}
+
+string_enum! {
+    /// The tag of a node in the debug menu (`debugmenu_tag`).
+    ///
+    /// Only the values seen so far are known, the other are kept in [`DebugMenuTag::Other`].
+    pub enum DebugMenuTag {
+        /// no tag
+        Empty => "",
+        /// the `system` tag
+        System => "system",
+    }
+}